@@ -0,0 +1,337 @@
+//! A typed async client for the crawlnicle JSON API under `/api/v1`.
+//!
+//! Requests and responses use the same model types as the server, re-exported from this crate.
+//! Requests are authenticated with a personal access token created on the account page.
+//!
+//! ```no_run
+//! use crawlnicle_client::{Client, GetFeedsOptions};
+//! use futures::TryStreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> crawlnicle_client::Result<()> {
+//! let client = Client::new("https://crawlnicle.example.com/".parse().unwrap(), "cn_...");
+//! let feeds: Vec<_> = client.all_feeds(GetFeedsOptions::default()).try_collect().await?;
+//! for feed in feeds {
+//!     println!("{}", feed.url);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use futures::stream::{self, Stream, TryStreamExt};
+use reqwest::header::ACCEPT;
+use reqwest::{Method, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use url::Url;
+use uuid::Uuid;
+
+use lib::error::PROBLEM_TYPE_PREFIX;
+use lib::headers::Accept;
+pub use lib::models::crawl_log::{CrawlLog, GetCrawlLogsOptions};
+pub use lib::models::entry::{
+    CreateEntry, Entry, EntrySearchResult, GetEntriesOptions, UpdateEntry,
+    DEFAULT_ENTRIES_PAGE_SIZE,
+};
+pub use lib::models::entry_read::MarkReadBefore;
+pub use lib::models::feed::{
+    CreateFeed, Feed, FeedType, FeedsPage, GetFeedsOptions, GetFeedsSort, UpdateFeed,
+};
+pub use lib::models::folder::Folder;
+pub use lib::models::subscription::{SetFolder, Subscription};
+pub use lib::uuid::Base62Uuid;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("request failed: {0}")]
+    Request(#[from] reqwest::Error),
+    #[error("invalid url: {0}")]
+    Url(#[from] url::ParseError),
+    /// The API responded with an error status. `code` is the stable `Error::code` of the error
+    /// from the `type` of its problem details, and `detail` is its message.
+    #[error("{status}: {detail}")]
+    Api {
+        status: StatusCode,
+        code: Option<String>,
+        detail: String,
+    },
+}
+
+pub type Result<T, E = Error> = ::std::result::Result<T, E>;
+
+/// The RFC 7807 problem details of the API's error responses.
+#[derive(Deserialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: String,
+    detail: String,
+}
+
+/// A client for the API of the crawlnicle instance at `base_url`, authenticated with a personal
+/// access token.
+///
+/// Ids are passed and returned as `Uuid`s, they are base62-encoded in request paths by the client.
+#[derive(Clone)]
+pub struct Client {
+    http: reqwest::Client,
+    base_url: Url,
+    token: String,
+}
+
+impl Client {
+    /// Creates a client for the instance at `base_url`, which is the instance's `PUBLIC_URL`.
+    pub fn new(base_url: Url, token: impl Into<String>) -> Self {
+        Self::with_http_client(reqwest::Client::new(), base_url, token)
+    }
+
+    /// Creates a client that makes requests with an existing `reqwest::Client`, e.g. one with
+    /// custom timeouts.
+    pub fn with_http_client(
+        http: reqwest::Client,
+        base_url: Url,
+        token: impl Into<String>,
+    ) -> Self {
+        Client {
+            http,
+            base_url,
+            token: token.into(),
+        }
+    }
+
+    fn request(&self, method: Method, path: &str) -> Result<RequestBuilder> {
+        let url = self.base_url.join(path)?;
+        Ok(self
+            .http
+            .request(method, url)
+            .bearer_auth(&self.token)
+            .header(ACCEPT, Accept::ApplicationJson.to_string()))
+    }
+
+    async fn send(request: RequestBuilder) -> Result<Response> {
+        let response = request.send().await?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        Err(match response.json::<ProblemDetails>().await {
+            Ok(problem) => Error::Api {
+                status,
+                code: problem
+                    .problem_type
+                    .strip_prefix(PROBLEM_TYPE_PREFIX)
+                    .map(str::to_owned),
+                detail: problem.detail,
+            },
+            Err(_) => Error::Api {
+                status,
+                code: None,
+                detail: status.canonical_reason().unwrap_or_default().to_owned(),
+            },
+        })
+    }
+
+    async fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T> {
+        Ok(Self::send(request).await?.json().await?)
+    }
+
+    /// Gets one page of the feeds the user is subscribed to. Pass `FeedsPage::next_cursor` as the
+    /// `cursor` of the options to get the next page, or use `all_feeds`.
+    pub async fn feeds(&self, options: &GetFeedsOptions) -> Result<FeedsPage> {
+        Self::json(self.request(Method::GET, "api/v1/feeds")?.query(options)).await
+    }
+
+    /// Streams all feeds the user is subscribed to, fetching pages as the stream is polled.
+    pub fn all_feeds(&self, options: GetFeedsOptions) -> impl Stream<Item = Result<Feed>> + '_ {
+        stream::try_unfold(Some(options), move |options| async move {
+            let Some(mut options) = options else {
+                return Ok::<_, Error>(None);
+            };
+            let page = self.feeds(&options).await?;
+            options.cursor = page.next_cursor;
+            let next = options.cursor.is_some().then_some(options);
+            Ok(Some((page.feeds, next)))
+        })
+        .map_ok(|feeds| stream::iter(feeds.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    pub async fn feed(&self, feed_id: Uuid) -> Result<Feed> {
+        Self::json(self.request(Method::GET, &feed_path(feed_id, ""))?).await
+    }
+
+    /// Subscribes to the feed with the url, adding it if no one was subscribed to it yet.
+    pub async fn create_feed(&self, payload: &CreateFeed) -> Result<Feed> {
+        Self::json(self.request(Method::POST, "api/v1/feed")?.json(payload)).await
+    }
+
+    /// Updates the feed for all of its subscribers, which only admins can do.
+    pub async fn update_feed(&self, feed_id: Uuid, payload: &UpdateFeed) -> Result<Feed> {
+        Self::json(
+            self.request(Method::PATCH, &feed_path(feed_id, ""))?
+                .json(payload),
+        )
+        .await
+    }
+
+    /// Unsubscribes from the feed.
+    pub async fn delete_feed(&self, feed_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &feed_path(feed_id, ""))?).await?;
+        Ok(())
+    }
+
+    /// Marks all entries of the feed as read.
+    pub async fn mark_feed_read(&self, feed_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::POST, &feed_path(feed_id, "/read"))?).await?;
+        Ok(())
+    }
+
+    pub async fn set_feed_folder(
+        &self,
+        feed_id: Uuid,
+        payload: &SetFolder,
+    ) -> Result<Subscription> {
+        Self::json(
+            self.request(Method::POST, &feed_path(feed_id, "/folder"))?
+                .json(payload),
+        )
+        .await
+    }
+
+    /// Queues a crawl of the feed without waiting for it to finish.
+    pub async fn crawl_feed(&self, feed_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::POST, &feed_path(feed_id, "/crawl"))?).await?;
+        Ok(())
+    }
+
+    /// Gets the crawl history of the feed, latest first.
+    pub async fn feed_crawls(
+        &self,
+        feed_id: Uuid,
+        options: &GetCrawlLogsOptions,
+    ) -> Result<Vec<CrawlLog>> {
+        Self::json(
+            self.request(Method::GET, &feed_path(feed_id, "/crawls"))?
+                .query(options),
+        )
+        .await
+    }
+
+    /// Gets one page of the entries of the feed, latest first.
+    pub async fn feed_entries(
+        &self,
+        feed_id: Uuid,
+        options: &GetEntriesOptions,
+    ) -> Result<Vec<Entry>> {
+        Self::json(
+            self.request(Method::GET, &feed_path(feed_id, "/entries"))?
+                .query(options),
+        )
+        .await
+    }
+
+    pub async fn folders(&self) -> Result<Vec<Folder>> {
+        Self::json(self.request(Method::GET, "api/v1/folders")?).await
+    }
+
+    /// Gets one page of the entries of the feeds the user is subscribed to, latest first. Pass the
+    /// `published_at` and `entry_id` of the last entry as `published_before` and `id_before` to
+    /// get the next page, or use `all_entries`.
+    pub async fn entries(&self, options: &GetEntriesOptions) -> Result<Vec<Entry>> {
+        Self::json(self.request(Method::GET, "api/v1/entries")?.query(options)).await
+    }
+
+    /// Streams all entries matching the options, fetching pages as the stream is polled.
+    pub fn all_entries(
+        &self,
+        options: GetEntriesOptions,
+    ) -> impl Stream<Item = Result<Entry>> + '_ {
+        stream::try_unfold(Some(options), move |options| async move {
+            let Some(mut options) = options else {
+                return Ok::<_, Error>(None);
+            };
+            let entries = self.entries(&options).await?;
+            let limit = options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE);
+            let next = match entries.last() {
+                Some(last) if entries.len() as i64 >= limit => {
+                    options.published_before = Some(last.published_at);
+                    options.id_before = Some(last.entry_id);
+                    Some(options)
+                }
+                _ => None,
+            };
+            Ok(Some((entries, next)))
+        })
+        .map_ok(|entries| stream::iter(entries.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// Searches the entries of the feeds the user is subscribed to for the options' `q`, best
+    /// matches first. Paginated with `offset`.
+    pub async fn search_entries(
+        &self,
+        options: &GetEntriesOptions,
+    ) -> Result<Vec<EntrySearchResult>> {
+        Self::json(self.request(Method::GET, "api/v1/entries")?.query(options)).await
+    }
+
+    /// Marks all entries published before the time as read.
+    pub async fn mark_entries_read(&self, payload: &MarkReadBefore) -> Result<()> {
+        Self::send(
+            self.request(Method::POST, "api/v1/entries/read")?
+                .json(payload),
+        )
+        .await?;
+        Ok(())
+    }
+
+    pub async fn entry(&self, entry_id: Uuid) -> Result<Entry> {
+        Self::json(self.request(Method::GET, &entry_path(entry_id, ""))?).await
+    }
+
+    pub async fn create_entry(&self, payload: &CreateEntry) -> Result<Entry> {
+        Self::json(self.request(Method::POST, "api/v1/entry")?.json(payload)).await
+    }
+
+    /// Updates an entry the user added with `create_entry`. Crawled entries can't be changed.
+    pub async fn update_entry(&self, entry_id: Uuid, payload: &UpdateEntry) -> Result<Entry> {
+        Self::json(
+            self.request(Method::PATCH, &entry_path(entry_id, ""))?
+                .json(payload),
+        )
+        .await
+    }
+
+    /// Deletes an entry the user added with `create_entry`. Crawled entries can't be deleted.
+    pub async fn delete_entry(&self, entry_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::DELETE, &entry_path(entry_id, ""))?).await?;
+        Ok(())
+    }
+
+    pub async fn mark_entry_read(&self, entry_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::POST, &entry_path(entry_id, "/read"))?).await?;
+        Ok(())
+    }
+
+    pub async fn mark_entry_unread(&self, entry_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::POST, &entry_path(entry_id, "/unread"))?).await?;
+        Ok(())
+    }
+
+    pub async fn star_entry(&self, entry_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::POST, &entry_path(entry_id, "/star"))?).await?;
+        Ok(())
+    }
+
+    pub async fn unstar_entry(&self, entry_id: Uuid) -> Result<()> {
+        Self::send(self.request(Method::POST, &entry_path(entry_id, "/unstar"))?).await?;
+        Ok(())
+    }
+}
+
+fn feed_path(feed_id: Uuid, suffix: &str) -> String {
+    format!("api/v1/feed/{}{}", Base62Uuid::from(feed_id), suffix)
+}
+
+fn entry_path(entry_id: Uuid, suffix: &str) -> String {
+    format!("api/v1/entry/{}{}", Base62Uuid::from(entry_id), suffix)
+}
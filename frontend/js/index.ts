@@ -13,5 +13,16 @@ declare global {
 
 window.htmx = htmx;
 
+// Error responses are not swapped by default, but the server renders HTML errors (see
+// `error::negotiate_error_response`) for htmx requests that are meant to be shown in the target.
+document.body.addEventListener('htmx:beforeSwap', (event) => {
+  const { detail } = event as CustomEvent;
+  const contentType = detail.xhr.getResponseHeader('Content-Type') ?? '';
+  if (detail.xhr.status >= 400 && contentType.startsWith('text/html')) {
+    detail.shouldSwap = true;
+    detail.isError = false;
+  }
+});
+
 // eslint-disable-next-line import/first
 import 'htmx.org/dist/ext/sse';
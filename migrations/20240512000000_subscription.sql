@@ -0,0 +1,14 @@
+-- Feeds are crawled once per url, but each user only sees the feeds they are subscribed to.
+create table if not exists "subscription" (
+    user_id uuid not null references "users" (user_id) on delete cascade,
+    feed_id uuid not null references "feed" (feed_id) on delete cascade,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz,
+    primary key (user_id, feed_id)
+);
+create index on "subscription" (feed_id);
+select trigger_updated_at('"subscription"');
+
+-- Before subscriptions existed, every user saw every feed, so keep it that way for existing users.
+insert into "subscription" (user_id, feed_id)
+select user_id, feed_id from "users" cross join "feed" where feed.deleted_at is null;
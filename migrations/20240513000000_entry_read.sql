@@ -0,0 +1,8 @@
+-- Tracks which entries each user has read. Entries without a row here are unread for that user.
+create table if not exists "entry_read" (
+    user_id uuid not null references "users" (user_id) on delete cascade,
+    entry_id uuid not null references "entry" (entry_id) on delete cascade,
+    created_at timestamptz not null default now(),
+    primary key (user_id, entry_id)
+);
+create index on "entry_read" (entry_id);
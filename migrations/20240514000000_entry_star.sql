@@ -0,0 +1,9 @@
+-- Entries starred by a user. Starred entries stay visible to the user even after they unsubscribe
+-- or the feed is deleted, so anything that purges old entries or their content must skip them.
+create table if not exists "entry_star" (
+    user_id uuid not null references "users" (user_id) on delete cascade,
+    entry_id uuid not null references "entry" (entry_id) on delete cascade,
+    created_at timestamptz not null default now(),
+    primary key (user_id, entry_id)
+);
+create index on "entry_star" (entry_id);
@@ -0,0 +1,13 @@
+-- User-defined folders that group the feeds a user is subscribed to.
+create table if not exists "folder" (
+    folder_id uuid primary key default uuid_generate_v1mc(),
+    user_id uuid not null references "users" (user_id) on delete cascade,
+    name text not null,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+create unique index on "folder" (user_id, name);
+select trigger_updated_at('"folder"');
+
+alter table "subscription" add column folder_id uuid references "folder" (folder_id) on delete set null;
+create index on "subscription" (folder_id);
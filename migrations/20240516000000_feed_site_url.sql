@@ -0,0 +1,2 @@
+-- The url of the website a feed belongs to (the `htmlUrl` of the feed in OPML).
+alter table "feed" add column site_url varchar(2048) default null;
@@ -0,0 +1,46 @@
+-- Full-text search index over entry titles, descriptions and the text extracted from the entry's
+-- page by the entry crawler. Kept in its own table so that the (large) extracted text isn't
+-- fetched with every entry.
+create or replace function entry_search_vector(title text, description text, content_text text)
+    returns tsvector as
+$$
+begin
+    return setweight(to_tsvector('english', coalesce(title, '')), 'A')
+        || setweight(to_tsvector('english', coalesce(description, '')), 'B')
+        || setweight(to_tsvector('english', coalesce(content_text, '')), 'C');
+end;
+$$ language plpgsql immutable;
+
+create table if not exists "entry_search" (
+    entry_id uuid primary key references "entry" (entry_id) on delete cascade,
+    content_text text,
+    search_vector tsvector not null,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+create index on "entry_search" using gin (search_vector);
+select trigger_updated_at('"entry_search"');
+
+-- Keep the search vector up to date when an entry's title or description changes.
+create or replace function update_entry_search()
+    returns trigger as
+$$
+begin
+    insert into entry_search (entry_id, search_vector)
+    values (NEW.entry_id, entry_search_vector(NEW.title, NEW.description, null))
+    on conflict (entry_id) do update set
+        search_vector = entry_search_vector(NEW.title, NEW.description, entry_search.content_text);
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger update_entry_search
+    after insert or update of title, description
+    on "entry"
+    for each row
+execute function update_entry_search();
+
+-- Content text of existing entries is only on disk, so it has to be indexed separately with the
+-- `index-content` CLI command.
+insert into entry_search (entry_id, search_vector)
+select entry_id, entry_search_vector(title, description, null) from entry;
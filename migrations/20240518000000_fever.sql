@@ -0,0 +1,38 @@
+-- Integer ids for entries, feeds and folders, for APIs (like Fever) whose clients can't handle
+-- UUIDs. UUIDs are unique across tables, so one mapping table is shared by all three.
+create table if not exists "numeric_id" (
+    numeric_id bigint generated always as identity primary key,
+    id uuid not null unique
+);
+
+create or replace function create_numeric_id()
+    returns trigger as
+$$
+begin
+    execute format('insert into numeric_id (id) values ($1.%I) on conflict do nothing', TG_ARGV[0])
+    using NEW;
+    return NEW;
+end;
+$$ language plpgsql;
+
+create trigger create_numeric_id after insert on "entry"
+    for each row execute function create_numeric_id('entry_id');
+create trigger create_numeric_id after insert on "feed"
+    for each row execute function create_numeric_id('feed_id');
+create trigger create_numeric_id after insert on "folder"
+    for each row execute function create_numeric_id('folder_id');
+
+insert into numeric_id (id) select feed_id from feed order by created_at;
+insert into numeric_id (id) select folder_id from folder order by created_at;
+insert into numeric_id (id) select entry_id from entry order by created_at;
+
+-- Fever clients authenticate with the md5 hash of "email:password". Users set a separate password
+-- for Fever clients, and only the resulting hash is stored.
+create table if not exists "fever_api_key" (
+    user_id uuid primary key references "users" (user_id) on delete cascade,
+    api_key text not null,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+create unique index on "fever_api_key" (api_key);
+select trigger_updated_at('"fever_api_key"');
@@ -0,0 +1,10 @@
+-- Auth tokens handed out to Google Reader API clients by ClientLogin. Only a hash of each token is
+-- stored.
+create table if not exists "greader_token" (
+    token_hash text primary key,
+    user_id uuid not null references "users" (user_id) on delete cascade,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+create index on "greader_token" (user_id);
+select trigger_updated_at('"greader_token"');
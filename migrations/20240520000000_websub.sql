@@ -0,0 +1,14 @@
+-- Subscriptions to the WebSub hubs advertised by feeds, so that hubs push new entries to
+-- crawlnicle instead of it waiting for the next crawl of the feed.
+create table if not exists "websub_subscription" (
+    feed_id uuid primary key references "feed" (feed_id) on delete cascade,
+    hub_url varchar(2048) not null,
+    topic_url varchar(2048) not null,
+    -- shared with the hub to sign the content it pushes
+    secret text not null,
+    -- null until the hub verifies the intent of the subscription
+    lease_expires_at timestamptz,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+select trigger_updated_at('"websub_subscription"');
@@ -0,0 +1,22 @@
+-- A record of every run of the `FeedCrawler` for a feed, whether it crawled the feed or saved
+-- content pushed by its WebSub hub, so that the history of a feed is kept beyond the
+-- `last_crawled_at` and `last_crawl_error` of its latest crawl.
+create table if not exists "crawl_log" (
+    crawl_log_id uuid primary key default uuid_generate_v1mc(),
+    feed_id uuid not null references "feed" (feed_id) on delete cascade,
+    started_at timestamptz not null,
+    duration_ms integer not null,
+    -- whether the content was pushed by the WebSub hub instead of fetched
+    pushed boolean not null default false,
+    -- null if the request failed (or for pushed content)
+    status_code smallint,
+    not_modified boolean not null default false,
+    bytes bigint,
+    new_entries integer not null default 0,
+    updated_entries integer not null default 0,
+    -- the `FeedCrawlerError` variant and its message, if the run failed
+    error varchar(255),
+    error_message text,
+    created_at timestamptz not null default now()
+);
+create index on "crawl_log" (feed_id, started_at desc);
@@ -0,0 +1,11 @@
+-- Per-domain delays between requests, loaded into `DomainLocks` at startup. Rows are added by the
+-- CLI, or by crawlers when a domain rate limits them.
+create table if not exists "domain_delay" (
+    domain varchar(255) primary key,
+    delay_ms integer not null,
+    -- no requests are made to the domain before this time, set from a `Retry-After` header
+    retry_after timestamptz,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+select trigger_updated_at('"domain_delay"');
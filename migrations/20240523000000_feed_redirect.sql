@@ -0,0 +1,14 @@
+-- Urls feeds were fetched from before they permanently redirected to their current url, so that
+-- adding a feed by its old url finds the existing feed.
+create table if not exists "feed_url_alias" (
+    url varchar(2048) primary key,
+    feed_id uuid not null references "feed" (feed_id) on delete cascade,
+    created_at timestamptz not null default now()
+);
+create index on "feed_url_alias" (feed_id);
+
+-- Set when a feed responds with 410 Gone, after which it is no longer crawled.
+alter table "feed" add column inactive_at timestamptz default null;
+
+-- The url the feed permanently redirected to during the crawl, if it did.
+alter table "crawl_log" add column moved_to varchar(2048) default null;
@@ -0,0 +1,5 @@
+-- Feeds that keep failing to crawl are crawled less and less often, and are paused once they have
+-- been failing for long enough, until a user resumes them.
+alter table "feed" add column consecutive_failures integer not null default 0;
+alter table "feed" add column failing_since timestamptz default null;
+alter table "feed" add column paused_at timestamptz default null;
@@ -0,0 +1,15 @@
+-- The next crawl of each active feed. Crawl workers (possibly in several processes) claim due jobs
+-- with `for update skip locked` and hold a lease on them while crawling, which they extend with
+-- heartbeats. A job whose lease expired (e.g. because its worker died) is claimed by another
+-- worker.
+create table if not exists "crawl_job" (
+    feed_id uuid primary key references "feed" (feed_id) on delete cascade,
+    run_at timestamptz not null default now(),
+    -- the worker crawling the feed and until when it holds the job
+    locked_by uuid,
+    locked_until timestamptz,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+create index on "crawl_job" (run_at);
+select trigger_updated_at('"crawl_job"');
@@ -0,0 +1,8 @@
+-- When each domain was last requested, so that processes sharing the database (like the server and
+-- the CLI) wait their turn before requesting a domain. A process claims its turn by moving
+-- `last_request_at` to now, but only if the previous request was long enough ago, see
+-- `DomainRequest::claim`.
+create table if not exists "domain_request" (
+    domain varchar(255) primary key,
+    last_request_at timestamptz not null default now()
+);
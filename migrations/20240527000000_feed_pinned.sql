@@ -0,0 +1,5 @@
+-- Fields of a feed that a user edited are pinned, so that crawls no longer overwrite them with the
+-- values from the feed itself, or adjust the crawl interval to how often the feed is updated.
+alter table "feed" add column title_pinned boolean not null default false;
+alter table "feed" add column description_pinned boolean not null default false;
+alter table "feed" add column crawl_interval_pinned boolean not null default false;
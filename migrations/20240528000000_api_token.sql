@@ -0,0 +1,15 @@
+-- Personal access tokens for the JSON API, sent as `Authorization: Bearer <token>`. Only a hash of
+-- each token is stored.
+create type api_token_scope as enum ('read', 'read_write');
+create table if not exists "api_token" (
+    api_token_id uuid primary key default uuid_generate_v1mc(),
+    user_id uuid not null references "users" (user_id) on delete cascade,
+    name text not null,
+    token_hash text not null unique,
+    scope api_token_scope not null default 'read_write',
+    last_used_at timestamptz,
+    created_at timestamptz not null default now(),
+    updated_at timestamptz
+);
+create index on "api_token" (user_id);
+select trigger_updated_at('"api_token"');
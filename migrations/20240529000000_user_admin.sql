@@ -0,0 +1,2 @@
+-- Feeds are shared by all of their subscribers, so only admins can edit them.
+alter table "users" add column admin boolean not null default false;
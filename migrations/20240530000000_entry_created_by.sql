@@ -0,0 +1,3 @@
+-- The user who added an entry through the API. Entries crawled from a feed are shared by all of
+-- its subscribers, so only entries a user added themselves can be edited or deleted by them.
+alter table "entry" add column created_by uuid references "users" (user_id);
@@ -0,0 +1,2 @@
+-- Google Reader API tokens expire, so that a token leaked from a client doesn't work forever.
+alter table "greader_token" add column expires_at timestamptz not null default now() + interval '30 days';
@@ -1,26 +1,56 @@
+use std::collections::HashMap;
 use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::Utc;
+use bytes::Bytes;
+use chrono::{DateTime, Utc};
+use rand::Rng;
 use reqwest::Client;
+use sqlx::postgres::PgListener;
 use sqlx::PgPool;
-use tokio::sync::{broadcast, mpsc};
-use tokio::time::{interval_at, Instant};
-use tracing::{debug, error, info, instrument};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify};
+use tokio::time::{interval_at, sleep, timeout, Instant};
+use tracing::{debug, error, info, instrument, warn};
+use url::Url;
 use uuid::Uuid;
 
+use crate::actors::entry_crawler::{EntryCrawlPriority, EntryCrawlerHandle};
 use crate::actors::feed_crawler::{FeedCrawlerError, FeedCrawlerHandle, FeedCrawlerHandleMessage};
 use crate::domain_locks::DomainLocks;
-use crate::models::feed::{Feed, GetFeedsOptions};
+use crate::models::crawl_job::{CrawlJob, CRAWL_JOB_DONE_CHANNEL};
+use crate::models::feed::{Feed, GetFeedsOptions, MAX_FAILURE_BACKOFF_MINUTES};
+use crate::models::websub_subscription::WebSubSubscription;
+use crate::robots_txt::RobotsTxt;
 use crate::state::Crawls;
+use crate::uuid::Base62Uuid;
+use crate::websub::WEBSUB_CRAWL_INTERVAL_MINUTES;
+
+/// How long a worker holds a claimed crawl job before another worker may claim it.
+const CRAWL_JOB_LEASE_SECONDS: i32 = 5 * 60;
+/// How often a worker extends the lease on the job it is crawling.
+const CRAWL_JOB_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+/// How often idle workers check for due jobs. Workers are woken up right away when a feed is
+/// scheduled in the same process.
+const CRAWL_JOB_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The senders of the initiators waiting on the next crawl of a feed, e.g. the user that just
+/// added it.
+type CrawlListeners =
+    Arc<Mutex<HashMap<Uuid, Vec<broadcast::Sender<CrawlSchedulerHandleMessage>>>>>;
 
 struct CrawlScheduler {
     receiver: mpsc::Receiver<CrawlSchedulerMessage>,
+    listeners: CrawlListeners,
+    /// Wakes up idle workers when a feed is due to be crawled right away.
+    wake: Arc<Notify>,
     pool: PgPool,
     client: Client,
     domain_locks: DomainLocks,
-    content_dir: String,
+    robots_txt: RobotsTxt,
+    entry_crawler: EntryCrawlerHandle,
     crawls: Crawls,
+    public_url: Url,
 }
 
 #[derive(Debug)]
@@ -30,6 +60,32 @@ enum CrawlSchedulerMessage {
         respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
     },
     Bootstrap {
+        workers: usize,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    },
+    Ingest {
+        feed_id: Uuid,
+        body: Bytes,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    },
+    Unschedule {
+        feed_id: Uuid,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    },
+    Reschedule {
+        feed_id: Uuid,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    },
+    Pause {
+        feed_id: Uuid,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    },
+    Resume {
+        feed_id: Uuid,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    },
+    Crawl {
+        feed_id: Uuid,
         respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
     },
 }
@@ -39,6 +95,16 @@ impl Display for CrawlSchedulerMessage {
         match self {
             CrawlSchedulerMessage::Schedule { feed_id, .. } => write!(f, "Schedule({})", feed_id),
             CrawlSchedulerMessage::Bootstrap { .. } => write!(f, "Bootstrap"),
+            CrawlSchedulerMessage::Ingest { feed_id, .. } => write!(f, "Ingest({})", feed_id),
+            CrawlSchedulerMessage::Unschedule { feed_id, .. } => {
+                write!(f, "Unschedule({})", feed_id)
+            }
+            CrawlSchedulerMessage::Reschedule { feed_id, .. } => {
+                write!(f, "Reschedule({})", feed_id)
+            }
+            CrawlSchedulerMessage::Pause { feed_id, .. } => write!(f, "Pause({})", feed_id),
+            CrawlSchedulerMessage::Resume { feed_id, .. } => write!(f, "Resume({})", feed_id),
+            CrawlSchedulerMessage::Crawl { feed_id, .. } => write!(f, "Crawl({})", feed_id),
         }
     }
 }
@@ -51,127 +117,252 @@ pub enum CrawlSchedulerError {
     FetchFeedError(String),
     #[error("failed to fetch feeds from database: {0}")]
     FetchFeedsError(String),
+    #[error("failed to update feed in database: {0}")]
+    UpdateFeedError(String),
+    #[error("failed to update crawl job in database: {0}")]
+    UpdateCrawlJobError(String),
+    #[error("feed is not active")]
+    InactiveFeed,
     #[error("failed to crawl feed: {0}")]
     FeedCrawlerError(FeedCrawlerError),
 }
 pub type CrawlSchedulerResult<T, E = CrawlSchedulerError> = ::std::result::Result<T, E>;
 
 impl CrawlScheduler {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         receiver: mpsc::Receiver<CrawlSchedulerMessage>,
         pool: PgPool,
         client: Client,
         domain_locks: DomainLocks,
-        content_dir: String,
+        robots_txt: RobotsTxt,
+        entry_crawler: EntryCrawlerHandle,
         crawls: Crawls,
+        public_url: Url,
     ) -> Self {
         CrawlScheduler {
             receiver,
+            listeners: Arc::new(Mutex::new(HashMap::new())),
+            wake: Arc::new(Notify::new()),
             pool,
             client,
             domain_locks,
-            content_dir,
+            robots_txt,
+            entry_crawler,
             crawls,
+            public_url,
         }
     }
 
+    /// Adds a crawl job for every active feed that does not have one yet (e.g. feeds added before
+    /// there was a job queue), then starts `workers` crawl workers.
     #[instrument(skip_all)]
-    async fn bootstrap(
-        &self,
-        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
-    ) -> CrawlSchedulerResult<()> {
-        debug!("scheduling crawlers");
+    async fn bootstrap(&mut self, workers: usize) -> CrawlSchedulerResult<()> {
+        debug!("scheduling crawl jobs");
         let mut options = GetFeedsOptions::default();
         loop {
-            info!("fetching feeds before: {:?}", options.before);
-            let feeds = match Feed::get_all(&self.pool, &options).await {
-                Err(err) => {
-                    return Err(CrawlSchedulerError::FetchFeedsError(err.to_string()));
-                }
-                Ok(feeds) if feeds.is_empty() => {
+            info!("fetching feeds after: {:?}", options.cursor);
+            let feeds = Feed::get_all(&self.pool, &options)
+                .await
+                .map_err(|err| CrawlSchedulerError::FetchFeedsError(err.to_string()))?;
+            info!("found {} feeds", feeds.len());
+            let next_cursor = options.next_cursor(&feeds);
+
+            for feed in feeds.into_iter().filter(|feed| feed.is_active()) {
+                let run_at = feed.next_crawl_time().unwrap_or_else(Utc::now);
+                CrawlJob::create(&self.pool, feed.feed_id, run_at)
+                    .await
+                    .map_err(|err| CrawlSchedulerError::UpdateCrawlJobError(err.to_string()))?;
+            }
+
+            match next_cursor {
+                Some(cursor) => options.cursor = Some(cursor),
+                None => {
                     info!("no more feeds found");
                     break;
                 }
-                Ok(feeds) => feeds,
-            };
-            info!("found {} feeds", feeds.len());
-            options.before = feeds.last().map(|f| f.created_at);
-
-            for feed in feeds.into_iter() {
-                self.spawn_crawler_loop(feed, respond_to.clone());
             }
         }
-        debug!("done scheduling crawlers");
+        debug!("done scheduling crawl jobs");
+
+        tokio::spawn(listen_for_completed_crawls(
+            self.pool.clone(),
+            self.listeners.clone(),
+        ));
+        info!(workers, "starting crawl workers");
+        for _ in 0..workers {
+            let worker = CrawlWorker {
+                worker_id: Uuid::from_u128(rand::random()),
+                pool: self.pool.clone(),
+                feed_crawler: FeedCrawlerHandle::new(
+                    self.pool.clone(),
+                    self.client.clone(),
+                    self.domain_locks.clone(),
+                    self.robots_txt.clone(),
+                    self.entry_crawler.clone(),
+                    self.crawls.clone(),
+                    self.public_url.clone(),
+                ),
+                listeners: self.listeners.clone(),
+                wake: self.wake.clone(),
+            };
+            tokio::spawn(worker.run());
+        }
         Ok(())
     }
 
+    /// Adds a crawl job for the feed, unless it already has one.
+    ///
+    /// If the feed is due to be crawled right away (e.g. because it was just added), the results
+    /// of the crawl are sent to `respond_to`.
     #[instrument(skip_all, fields(feed_id = %feed_id))]
     async fn schedule(
-        &self,
+        &mut self,
         feed_id: Uuid,
         respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
     ) -> CrawlSchedulerResult<()> {
         let feed = Feed::get(&self.pool, feed_id)
             .await
             .map_err(|err| CrawlSchedulerError::FetchFeedError(err.to_string()))?;
-        self.spawn_crawler_loop(feed, respond_to);
+        if !feed.is_active() {
+            info!("feed is inactive, not scheduling crawls");
+            return Ok(());
+        }
+        let run_at = feed.next_crawl_time().unwrap_or_else(Utc::now);
+        let due = run_at <= Utc::now();
+        // The listener is added before the job so that it is there even if a worker in another
+        // process crawls the feed right away
+        if due {
+            self.add_listener(feed_id, respond_to.clone()).await;
+        }
+        let created = CrawlJob::create(&self.pool, feed_id, run_at)
+            .await
+            .map_err(|err| CrawlSchedulerError::UpdateCrawlJobError(err.to_string()))?;
+        if !created {
+            debug!("feed is already scheduled");
+            if due {
+                self.remove_listener(feed_id, &respond_to).await;
+            }
+        } else if due {
+            self.wake.notify_one();
+        }
         Ok(())
     }
 
-    #[instrument(skip_all, fields(feed_id = %feed.feed_id))]
-    fn spawn_crawler_loop(
+    /// Stops crawling the feed, e.g. because it was deleted. A crawl that is already in progress
+    /// is finished by its worker.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn unschedule(&mut self, feed_id: Uuid) -> CrawlSchedulerResult<()> {
+        CrawlJob::delete(&self.pool, feed_id)
+            .await
+            .map_err(|err| CrawlSchedulerError::UpdateCrawlJobError(err.to_string()))?;
+        info!("unscheduled feed");
+        Ok(())
+    }
+
+    /// Moves the crawl job of the feed to its next crawl time with its current state in the
+    /// database, e.g. after its url or crawl interval was changed.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn reschedule(&mut self, feed_id: Uuid) -> CrawlSchedulerResult<()> {
+        let feed = Feed::get(&self.pool, feed_id)
+            .await
+            .map_err(|err| CrawlSchedulerError::FetchFeedError(err.to_string()))?;
+        if !feed.is_active() {
+            return self.unschedule(feed_id).await;
+        }
+        let run_at = feed.next_crawl_time().unwrap_or_else(Utc::now);
+        CrawlJob::upsert(&self.pool, feed_id, run_at)
+            .await
+            .map_err(|err| CrawlSchedulerError::UpdateCrawlJobError(err.to_string()))?;
+        if run_at <= Utc::now() {
+            self.wake.notify_one();
+        }
+        info!(%run_at, "rescheduled feed");
+        Ok(())
+    }
+
+    /// Moves the crawl job of the feed to now, so that the next free worker crawls it without
+    /// waiting for its crawl interval. A crawl that is already in progress is not repeated.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn crawl(&mut self, feed_id: Uuid) -> CrawlSchedulerResult<()> {
+        let feed = Feed::get(&self.pool, feed_id)
+            .await
+            .map_err(|err| CrawlSchedulerError::FetchFeedError(err.to_string()))?;
+        if !feed.is_active() {
+            return Err(CrawlSchedulerError::InactiveFeed);
+        }
+        CrawlJob::upsert(&self.pool, feed_id, Utc::now())
+            .await
+            .map_err(|err| CrawlSchedulerError::UpdateCrawlJobError(err.to_string()))?;
+        self.wake.notify_one();
+        info!("queued crawl");
+        Ok(())
+    }
+
+    /// Pauses crawling the feed until it is resumed.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn pause(&mut self, feed_id: Uuid) -> CrawlSchedulerResult<()> {
+        Feed::pause(&self.pool, feed_id)
+            .await
+            .map_err(|err| CrawlSchedulerError::UpdateFeedError(err.to_string()))?;
+        self.unschedule(feed_id).await
+    }
+
+    /// Resumes crawling a feed that was paused (by a user, or after failing for too long) or that
+    /// was gone.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn resume(&mut self, feed_id: Uuid) -> CrawlSchedulerResult<()> {
+        Feed::resume(&self.pool, feed_id)
+            .await
+            .map_err(|err| CrawlSchedulerError::UpdateFeedError(err.to_string()))?;
+        self.reschedule(feed_id).await
+    }
+
+    async fn add_listener(
         &self,
-        feed: Feed,
+        feed_id: Uuid,
         respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
     ) {
-        let crawl_interval = Duration::from_secs(feed.crawl_interval_minutes as u64 * 60);
-        let mut interval = tokio::time::interval(crawl_interval);
-        if let Some(last_crawled_at) = feed.last_crawled_at {
-            if let Ok(duration_since_last_crawl) = (Utc::now() - last_crawled_at).to_std() {
-                if duration_since_last_crawl < crawl_interval {
-                    info!(
-                        "last crawled at {:?}, crawling again in {:?}",
-                        last_crawled_at,
-                        crawl_interval - duration_since_last_crawl
-                    );
-                    interval = interval_at(
-                        Instant::now() + (crawl_interval - duration_since_last_crawl),
-                        crawl_interval,
-                    );
-                }
+        let mut listeners = self.listeners.lock().await;
+        listeners.entry(feed_id).or_default().push(respond_to);
+    }
+
+    async fn remove_listener(
+        &self,
+        feed_id: Uuid,
+        respond_to: &broadcast::Sender<CrawlSchedulerHandleMessage>,
+    ) {
+        let mut listeners = self.listeners.lock().await;
+        if let Some(senders) = listeners.get_mut(&feed_id) {
+            senders.retain(|sender| !sender.same_channel(respond_to));
+            if senders.is_empty() {
+                listeners.remove(&feed_id);
             }
         }
+    }
+
+    /// Saves the content of a feed pushed by its WebSub hub with a new `FeedCrawler`, without
+    /// waiting for it to finish.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    fn ingest(
+        &self,
+        feed_id: Uuid,
+        body: Bytes,
+        respond_to: broadcast::Sender<CrawlSchedulerHandleMessage>,
+    ) {
         let feed_crawler = FeedCrawlerHandle::new(
             self.pool.clone(),
             self.client.clone(),
             self.domain_locks.clone(),
-            self.content_dir.clone(),
+            self.robots_txt.clone(),
+            self.entry_crawler.clone(),
             self.crawls.clone(),
+            self.public_url.clone(),
         );
         tokio::spawn(async move {
-            loop {
-                interval.tick().await;
-                let mut receiver = feed_crawler.crawl(feed.feed_id).await;
-                while let Ok(msg) = receiver.recv().await {
-                    match msg {
-                        FeedCrawlerHandleMessage::Feed(Ok(feed)) => {
-                            let crawl_interval =
-                                Duration::from_secs(feed.crawl_interval_minutes as u64 * 60);
-                            interval = interval_at(Instant::now() + crawl_interval, crawl_interval);
-                            info!(
-                                minutes = feed.crawl_interval_minutes,
-                                "updated crawl interval"
-                            );
-                            let _ = respond_to.send(CrawlSchedulerHandleMessage::FeedCrawler(
-                                FeedCrawlerHandleMessage::Feed(Ok(feed)),
-                            ));
-                        }
-                        result => {
-                            let _ =
-                                respond_to.send(CrawlSchedulerHandleMessage::FeedCrawler(result));
-                        }
-                    }
-                }
+            let mut receiver = feed_crawler.ingest(feed_id, body).await;
+            while let Ok(msg) = receiver.recv().await {
+                let _ = respond_to.send(CrawlSchedulerHandleMessage::FeedCrawler(msg));
             }
         });
     }
@@ -179,8 +370,11 @@ impl CrawlScheduler {
     #[instrument(skip_all, fields(msg = %msg))]
     async fn handle_message(&mut self, msg: CrawlSchedulerMessage) {
         match msg {
-            CrawlSchedulerMessage::Bootstrap { respond_to } => {
-                let result = self.bootstrap(respond_to.clone()).await;
+            CrawlSchedulerMessage::Bootstrap {
+                workers,
+                respond_to,
+            } => {
+                let result = self.bootstrap(workers).await;
                 if let Err(err) = &result {
                     error!("failed to bootstrap: {}", err);
                 }
@@ -202,6 +396,78 @@ impl CrawlScheduler {
                 // response, and that is ok
                 let _ = respond_to.send(CrawlSchedulerHandleMessage::Schedule(result));
             }
+            CrawlSchedulerMessage::Ingest {
+                feed_id,
+                body,
+                respond_to,
+            } => {
+                self.ingest(feed_id, body, respond_to);
+            }
+            CrawlSchedulerMessage::Unschedule {
+                feed_id,
+                respond_to,
+            } => {
+                let result = self.unschedule(feed_id).await;
+                if let Err(err) = &result {
+                    error!("failed to unschedule: {}", err);
+                }
+
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(CrawlSchedulerHandleMessage::Unschedule(result));
+            }
+            CrawlSchedulerMessage::Reschedule {
+                feed_id,
+                respond_to,
+            } => {
+                let result = self.reschedule(feed_id).await;
+                if let Err(err) = &result {
+                    error!("failed to reschedule: {}", err);
+                }
+
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(CrawlSchedulerHandleMessage::Reschedule(result));
+            }
+            CrawlSchedulerMessage::Pause {
+                feed_id,
+                respond_to,
+            } => {
+                let result = self.pause(feed_id).await;
+                if let Err(err) = &result {
+                    error!("failed to pause: {}", err);
+                }
+
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(CrawlSchedulerHandleMessage::Pause(result));
+            }
+            CrawlSchedulerMessage::Resume {
+                feed_id,
+                respond_to,
+            } => {
+                let result = self.resume(feed_id).await;
+                if let Err(err) = &result {
+                    error!("failed to resume: {}", err);
+                }
+
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(CrawlSchedulerHandleMessage::Resume(result));
+            }
+            CrawlSchedulerMessage::Crawl {
+                feed_id,
+                respond_to,
+            } => {
+                let result = self.crawl(feed_id).await;
+                if let Err(err) = &result {
+                    error!("failed to queue crawl: {}", err);
+                }
+
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(CrawlSchedulerHandleMessage::Crawl(result));
+            }
         }
     }
 
@@ -214,17 +480,214 @@ impl CrawlScheduler {
     }
 }
 
+/// A long-lived async task that repeatedly claims the crawl job that is due next and crawls its
+/// feed, one feed at a time.
+struct CrawlWorker {
+    worker_id: Uuid,
+    pool: PgPool,
+    feed_crawler: FeedCrawlerHandle,
+    listeners: CrawlListeners,
+    wake: Arc<Notify>,
+}
+
+impl CrawlWorker {
+    #[instrument(skip_all, fields(worker_id = %self.worker_id))]
+    async fn run(self) {
+        debug!("starting crawl worker");
+        loop {
+            match CrawlJob::claim(&self.pool, self.worker_id, CRAWL_JOB_LEASE_SECONDS).await {
+                Ok(Some(job)) => self.crawl(job.feed_id).await,
+                Ok(None) => {
+                    let _ = timeout(CRAWL_JOB_POLL_INTERVAL, self.wake.notified()).await;
+                }
+                Err(err) => {
+                    error!("failed to claim crawl job: {}", err);
+                    sleep(CRAWL_JOB_POLL_INTERVAL).await;
+                }
+            }
+        }
+    }
+
+    /// Crawls the feed of a claimed job while extending the lease on the job, then schedules the
+    /// next crawl of the feed.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn crawl(&self, feed_id: Uuid) {
+        let listeners = {
+            let mut listeners = self.listeners.lock().await;
+            listeners.remove(&feed_id).unwrap_or_default()
+        };
+        let mut heartbeat = interval_at(
+            Instant::now() + CRAWL_JOB_HEARTBEAT_INTERVAL,
+            CRAWL_JOB_HEARTBEAT_INTERVAL,
+        );
+        // If the feed can't be loaded after the crawl, it is retried like a failing feed
+        let mut run_at =
+            Some(Utc::now() + chrono::Duration::minutes(MAX_FAILURE_BACKOFF_MINUTES as i64));
+        // Users waiting on the crawl get its entries scraped first
+        let priority = if listeners.is_empty() {
+            EntryCrawlPriority::Scheduled
+        } else {
+            EntryCrawlPriority::User
+        };
+        let mut receiver = self.feed_crawler.crawl(feed_id, priority).await;
+        loop {
+            let msg = tokio::select! {
+                msg = receiver.recv() => msg,
+                _ = heartbeat.tick() => {
+                    match CrawlJob::heartbeat(
+                        &self.pool,
+                        feed_id,
+                        self.worker_id,
+                        CRAWL_JOB_LEASE_SECONDS,
+                    )
+                    .await
+                    {
+                        Ok(true) => debug!("extended crawl job lease"),
+                        Ok(false) => warn!("crawl job was unscheduled or claimed by another worker"),
+                        Err(err) => error!("failed to extend crawl job lease: {}", err),
+                    }
+                    continue;
+                }
+            };
+            let Ok(msg) = msg else {
+                break;
+            };
+            let feed_result = match &msg {
+                FeedCrawlerHandleMessage::Feed(result) => Some(result.clone()),
+                _ => None,
+            };
+            for listener in &listeners {
+                let _ = listener.send(CrawlSchedulerHandleMessage::FeedCrawler(msg.clone()));
+            }
+            if let Some(result) = feed_result {
+                // Failed crawls are recorded on the feed as well, which is needed to back off from
+                // it
+                let feed = match result {
+                    Ok(feed) => Some(feed),
+                    Err(_) => Feed::get(&self.pool, feed_id).await.ok(),
+                };
+                if let Some(feed) = feed {
+                    run_at = next_run_at(&self.pool, &feed).await;
+                }
+                // The new entries of the feed are scraped after the feed crawl is done, so the
+                // job is completed right away and only the listeners wait on them
+                if !listeners.is_empty() {
+                    tokio::spawn(async move {
+                        while let Ok(msg) = receiver.recv().await {
+                            for listener in &listeners {
+                                let _ = listener
+                                    .send(CrawlSchedulerHandleMessage::FeedCrawler(msg.clone()));
+                            }
+                        }
+                    });
+                }
+                break;
+            }
+        }
+        if let Err(err) = CrawlJob::complete(&self.pool, feed_id, self.worker_id, run_at).await {
+            error!("failed to complete crawl job: {}", err);
+        }
+    }
+}
+
+/// Listens for crawls completed by workers in any process and sends the crawled feed to the
+/// listeners waiting on them in this process. Listeners of crawls run by this process already
+/// got the results from the worker.
+async fn listen_for_completed_crawls(pool: PgPool, listeners: CrawlListeners) {
+    let mut listener = match PgListener::connect_with(&pool).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("failed to connect crawl job listener: {}", err);
+            return;
+        }
+    };
+    if let Err(err) = listener.listen(CRAWL_JOB_DONE_CHANNEL).await {
+        error!("failed to listen for completed crawl jobs: {}", err);
+        return;
+    }
+    loop {
+        let notification = match listener.recv().await {
+            Ok(notification) => notification,
+            Err(err) => {
+                error!("failed to receive completed crawl job: {}", err);
+                sleep(CRAWL_JOB_POLL_INTERVAL).await;
+                continue;
+            }
+        };
+        let Ok(feed_id) = Uuid::parse_str(notification.payload()) else {
+            continue;
+        };
+        let Some(senders) = listeners.lock().await.remove(&feed_id) else {
+            continue;
+        };
+        let result = Feed::get(&pool, feed_id)
+            .await
+            .map_err(|_| FeedCrawlerError::GetFeedError(Base62Uuid::from(feed_id)));
+        for sender in senders {
+            let _ = sender.send(CrawlSchedulerHandleMessage::FeedCrawler(
+                FeedCrawlerHandleMessage::Feed(result.clone()),
+            ));
+        }
+    }
+}
+
+/// When to crawl the feed next, or `None` if it is no longer crawled because it is gone, paused,
+/// or was merged into the feed it moved to.
+async fn next_run_at(pool: &PgPool, feed: &Feed) -> Option<DateTime<Utc>> {
+    if !feed.is_active() {
+        info!("feed is no longer active, stopping crawls");
+        return None;
+    }
+    let crawl_interval = next_crawl_interval(pool, feed).await;
+    info!(
+        minutes = crawl_interval.as_secs() / 60,
+        consecutive_failures = feed.consecutive_failures,
+        "updated crawl interval"
+    );
+    chrono::Duration::from_std(crawl_interval)
+        .ok()
+        .map(|crawl_interval| Utc::now() + crawl_interval)
+}
+
+/// How long to wait before crawling the feed again.
+///
+/// Failing feeds are backed off exponentially, with jitter so that feeds that started failing at
+/// the same time (like all feeds of a site that went down) are not all retried at once. New
+/// entries of feeds with an active WebSub subscription are pushed by the hub, so those feeds only
+/// need to be polled occasionally in case the hub misses one.
+async fn next_crawl_interval(pool: &PgPool, feed: &Feed) -> Duration {
+    let mut crawl_interval_minutes = feed.backoff_interval_minutes() as f64;
+    if feed.consecutive_failures > 0 {
+        crawl_interval_minutes *= rand::thread_rng().gen_range(0.5..=1.0);
+    }
+    if WebSubSubscription::get(pool, feed.feed_id)
+        .await
+        .is_ok_and(|subscription| subscription.is_active())
+    {
+        crawl_interval_minutes = crawl_interval_minutes.max(WEBSUB_CRAWL_INTERVAL_MINUTES as f64);
+    }
+    Duration::from_secs_f64(crawl_interval_minutes * 60.0)
+}
+
 /// The `CrawlSchedulerHandle` is used to initialize and communicate with a `CrawlScheduler` actor.
 ///
-/// Spawns an async task separate from the main web server that fetches all feeds from the database
-/// and then spawns a long-lived async task for each feed that repeatedly crawls the feed at the
-/// interval specified by each feeds' `crawl_interval_minutes`.
+/// Spawns an async task separate from the main web server that manages the `crawl_job` table,
+/// which holds the next crawl of every active feed. On bootstrap, a job is added for every feed
+/// that doesn't have one, due at the feed's `next_crawl_time` (or right away if it was never
+/// crawled), and the given number of crawl workers are started.
+///
+/// Workers claim due jobs with `for update skip locked`, so several processes can run workers
+/// against the same database without crawling a feed twice, and a process that only serves the web
+/// app can run without any workers. Claimed jobs are leased to their worker and the lease is
+/// extended while the feed is crawled, so that the job is picked up again by another worker if its
+/// worker dies. Since the queue lives in the database, scheduled crawls survive restarts.
 ///
-/// Initially, all feeds will immediately be crawled unless the `last_crawled_at` timestamp set in
-/// the database is less than the current time minus its `crawl_interval` in which case the crawl
-/// will be scheduled in the future.
+/// After each crawl, the job is moved to the next crawl time based on the result of the crawl.
+/// Feeds that have an active WebSub subscription are crawled less often since their hub pushes new
+/// entries.
 ///
-/// After each crawl, the interval may be updated based on the result of the crawl.
+/// Feeds that are deleted, edited, paused or resumed must be unscheduled, rescheduled, paused or
+/// resumed through this handle so that their job is removed or moved.
 #[derive(Clone)]
 pub struct CrawlSchedulerHandle {
     sender: mpsc::Sender<CrawlSchedulerMessage>,
@@ -238,6 +701,11 @@ pub struct CrawlSchedulerHandle {
 pub enum CrawlSchedulerHandleMessage {
     Bootstrap(CrawlSchedulerResult<()>),
     Schedule(CrawlSchedulerResult<()>),
+    Unschedule(CrawlSchedulerResult<()>),
+    Reschedule(CrawlSchedulerResult<()>),
+    Pause(CrawlSchedulerResult<()>),
+    Resume(CrawlSchedulerResult<()>),
+    Crawl(CrawlSchedulerResult<()>),
     FeedCrawler(FeedCrawlerHandleMessage),
 }
 
@@ -247,23 +715,40 @@ impl CrawlSchedulerHandle {
         pool: PgPool,
         client: Client,
         domain_locks: DomainLocks,
-        content_dir: String,
+        robots_txt: RobotsTxt,
+        entry_crawler: EntryCrawlerHandle,
         crawls: Crawls,
+        public_url: Url,
     ) -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut scheduler =
-            CrawlScheduler::new(receiver, pool, client, domain_locks, content_dir, crawls);
+        let mut scheduler = CrawlScheduler::new(
+            receiver,
+            pool,
+            client,
+            domain_locks,
+            robots_txt,
+            entry_crawler,
+            crawls,
+            public_url,
+        );
         tokio::spawn(async move { scheduler.run().await });
 
         Self { sender }
     }
 
-    /// Sends a `CrawlSchedulerMessage::Bootstrap` message to the running `CrawlScheduler` actor.
+    /// Sends a `CrawlSchedulerMessage::Bootstrap` message to the running `CrawlScheduler` actor,
+    /// which starts `workers` crawl workers in this process.
     ///
     /// Listen to the result of the scheduling via the returned `broadcast::Receiver`.
-    pub async fn bootstrap(&self) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+    pub async fn bootstrap(
+        &self,
+        workers: usize,
+    ) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
         let (sender, receiver) = broadcast::channel(8);
-        let msg = CrawlSchedulerMessage::Bootstrap { respond_to: sender };
+        let msg = CrawlSchedulerMessage::Bootstrap {
+            workers,
+            respond_to: sender,
+        };
 
         self.sender
             .send(msg)
@@ -291,4 +776,118 @@ impl CrawlSchedulerHandle {
             .expect("crawl scheduler task has died");
         receiver
     }
+
+    /// Sends a `CrawlSchedulerMessage::Ingest` message to the running `CrawlScheduler` actor with
+    /// the content of a feed pushed by its WebSub hub.
+    ///
+    /// Listen to the result of saving the feed via the returned `broadcast::Receiver`.
+    pub async fn ingest(
+        &self,
+        feed_id: Uuid,
+        body: Bytes,
+    ) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = CrawlSchedulerMessage::Ingest {
+            feed_id,
+            body,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("crawl scheduler task has died");
+        receiver
+    }
+
+    /// Sends a `CrawlSchedulerMessage::Unschedule` message to the running `CrawlScheduler` actor.
+    ///
+    /// Listen to the result of the unscheduling via the returned `broadcast::Receiver`.
+    pub async fn unschedule(
+        &self,
+        feed_id: Uuid,
+    ) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = CrawlSchedulerMessage::Unschedule {
+            feed_id,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("crawl scheduler task has died");
+        receiver
+    }
+
+    /// Sends a `CrawlSchedulerMessage::Reschedule` message to the running `CrawlScheduler` actor.
+    ///
+    /// Listen to the result of the rescheduling via the returned `broadcast::Receiver`.
+    pub async fn reschedule(
+        &self,
+        feed_id: Uuid,
+    ) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = CrawlSchedulerMessage::Reschedule {
+            feed_id,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("crawl scheduler task has died");
+        receiver
+    }
+
+    /// Sends a `CrawlSchedulerMessage::Pause` message to the running `CrawlScheduler` actor.
+    ///
+    /// Listen to the result of pausing via the returned `broadcast::Receiver`.
+    pub async fn pause(&self, feed_id: Uuid) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = CrawlSchedulerMessage::Pause {
+            feed_id,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("crawl scheduler task has died");
+        receiver
+    }
+
+    /// Sends a `CrawlSchedulerMessage::Resume` message to the running `CrawlScheduler` actor.
+    ///
+    /// Listen to the result of resuming via the returned `broadcast::Receiver`.
+    pub async fn resume(&self, feed_id: Uuid) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = CrawlSchedulerMessage::Resume {
+            feed_id,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("crawl scheduler task has died");
+        receiver
+    }
+
+    /// Sends a `CrawlSchedulerMessage::Crawl` message to the running `CrawlScheduler` actor.
+    ///
+    /// Listen to whether the crawl was queued via the returned `broadcast::Receiver`.
+    pub async fn crawl(&self, feed_id: Uuid) -> broadcast::Receiver<CrawlSchedulerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = CrawlSchedulerMessage::Crawl {
+            feed_id,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("crawl scheduler task has died");
+        receiver
+    }
 }
@@ -1,3 +1,4 @@
+use std::collections::{HashSet, VecDeque};
 use std::fmt::{self, Display, Formatter};
 use std::fs;
 use std::path::Path;
@@ -8,14 +9,26 @@ use readability::extractor;
 use reqwest::Client;
 use sqlx::PgPool;
 use tokio::sync::{broadcast, mpsc};
-use tracing::{debug, info, instrument};
+use tokio::task::JoinSet;
+use tracing::{debug, error, info, instrument};
 use url::Url;
 
 use crate::domain_locks::DomainLocks;
 use crate::models::entry::Entry;
+use crate::robots_txt::RobotsTxt;
 
-/// The `EntryCrawler` actor fetches an entry url, extracts the content, and saves the content to
-/// the file system and any associated metadata to the database.
+/// How many entries are scraped at once, across all feeds, unless configured otherwise.
+pub const DEFAULT_ENTRY_CRAWL_CONCURRENCY: usize = 8;
+
+/// The `EntryCrawler` actor is a pool of tasks shared by all feed crawlers that fetch entry urls,
+/// extract the content, and save the content to the file system and any associated metadata to the
+/// database.
+///
+/// Entries are queued and scraped by at most `concurrency` tasks at once. Only one entry per domain
+/// is scraped at a time (the `DomainLocks` would make any others wait anyway), so that a feed with
+/// many new entries does not take up every task while entries of other domains could be scraped.
+/// Entries crawled for a user waiting on them (e.g. of a feed they just added) are scraped before
+/// entries of scheduled crawls.
 ///
 /// It receives `EntryCrawlerMessage` messages via the `receiver` channel. It communicates back to
 /// the sender of those messages via the `respond_to` channel on the `EntryCrawlerMessage`.
@@ -23,16 +36,46 @@ use crate::models::entry::Entry;
 /// `EntryCrawler` should not be instantiated directly. Instead, use the `EntryCrawlerHandle`.
 struct EntryCrawler {
     receiver: mpsc::Receiver<EntryCrawlerMessage>,
+    scraper: EntryScraper,
+    concurrency: usize,
+    user_queue: VecDeque<QueuedEntry>,
+    scheduled_queue: VecDeque<QueuedEntry>,
+    /// The domains of the entries being scraped.
+    busy_domains: HashSet<String>,
+    /// The running scraping tasks, which return the domain of their entry.
+    running: JoinSet<String>,
+}
+
+/// Fetches and saves the content of a single entry. Cloned into each scraping task.
+#[derive(Clone)]
+struct EntryScraper {
     pool: PgPool,
     client: Client,
     domain_locks: DomainLocks,
+    robots_txt: RobotsTxt,
     content_dir: String,
 }
 
+/// Who is waiting on an entry crawl, which decides how soon it is scraped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryCrawlPriority {
+    /// A user is waiting on the crawl, e.g. after adding or importing a feed.
+    User,
+    /// The crawl was scheduled, or the feed was pushed by its WebSub hub.
+    Scheduled,
+}
+
+struct QueuedEntry {
+    domain: String,
+    entry: Entry,
+    respond_to: broadcast::Sender<EntryCrawlerHandleMessage>,
+}
+
 #[derive(Debug)]
 enum EntryCrawlerMessage {
     Crawl {
         entry: Entry,
+        priority: EntryCrawlPriority,
         respond_to: broadcast::Sender<EntryCrawlerHandleMessage>,
     },
 }
@@ -65,20 +108,111 @@ pub type EntryCrawlerResult<T, E = EntryCrawlerError> = ::std::result::Result<T,
 impl EntryCrawler {
     fn new(
         receiver: mpsc::Receiver<EntryCrawlerMessage>,
-        pool: PgPool,
-        client: Client,
-        domain_locks: DomainLocks,
-        content_dir: String,
+        scraper: EntryScraper,
+        concurrency: usize,
     ) -> Self {
         EntryCrawler {
             receiver,
-            pool,
-            client,
-            domain_locks,
-            content_dir,
+            scraper,
+            concurrency: concurrency.max(1),
+            user_queue: VecDeque::new(),
+            scheduled_queue: VecDeque::new(),
+            busy_domains: HashSet::new(),
+            running: JoinSet::new(),
+        }
+    }
+
+    /// Starts scraping queued entries, user crawls first, until `concurrency` entries are being
+    /// scraped or every queued entry's domain is already being scraped.
+    fn dispatch(&mut self) {
+        while self.running.len() < self.concurrency {
+            let Some(queued) = next_entry(&mut self.user_queue, &self.busy_domains)
+                .or_else(|| next_entry(&mut self.scheduled_queue, &self.busy_domains))
+            else {
+                break;
+            };
+            self.busy_domains.insert(queued.domain.clone());
+            let scraper = self.scraper.clone();
+            self.running.spawn(async move {
+                let QueuedEntry {
+                    domain,
+                    entry,
+                    respond_to,
+                } = queued;
+                // scraped in its own task so that the domain is freed even if scraping panics
+                let url = entry.url.clone();
+                let result = tokio::spawn(async move { scraper.crawl_entry(entry).await })
+                    .await
+                    .unwrap_or_else(|err| {
+                        error!(url, "entry scraping task failed: {}", err);
+                        Err(EntryCrawlerError::ExtractError(url))
+                    });
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(EntryCrawlerHandleMessage::Entry(result));
+                domain
+            });
+        }
+    }
+
+    #[instrument(skip_all, fields(msg = %msg))]
+    fn handle_message(&mut self, msg: EntryCrawlerMessage) {
+        match msg {
+            EntryCrawlerMessage::Crawl {
+                entry,
+                priority,
+                respond_to,
+            } => {
+                let domain = Url::parse(&entry.url)
+                    .ok()
+                    .and_then(|url| url.domain().map(str::to_owned))
+                    .unwrap_or_else(|| entry.url.clone());
+                let queued = QueuedEntry {
+                    domain,
+                    entry,
+                    respond_to,
+                };
+                match priority {
+                    EntryCrawlPriority::User => self.user_queue.push_back(queued),
+                    EntryCrawlPriority::Scheduled => self.scheduled_queue.push_back(queued),
+                }
+            }
+        }
+    }
+
+    #[instrument(skip_all)]
+    async fn run(&mut self) {
+        debug!(concurrency = self.concurrency, "starting entry crawler");
+        loop {
+            tokio::select! {
+                msg = self.receiver.recv() => match msg {
+                    Some(msg) => self.handle_message(msg),
+                    None => break,
+                },
+                Some(result) = self.running.join_next() => match result {
+                    Ok(domain) => {
+                        self.busy_domains.remove(&domain);
+                    }
+                    Err(err) => error!("entry crawl task failed: {}", err),
+                },
+            }
+            self.dispatch();
         }
     }
+}
+
+/// Removes the first entry from the queue whose domain is not being scraped already.
+fn next_entry(
+    queue: &mut VecDeque<QueuedEntry>,
+    busy_domains: &HashSet<String>,
+) -> Option<QueuedEntry> {
+    let index = queue
+        .iter()
+        .position(|queued| !busy_domains.contains(&queued.domain))?;
+    queue.remove(index)
+}
 
+impl EntryScraper {
     #[instrument(skip_all, fields(entry = %entry.url))]
     async fn crawl_entry(&self, entry: Entry) -> EntryCrawlerResult<Entry> {
         info!("starting fetch");
@@ -88,19 +222,38 @@ impl EntryCrawler {
         let domain = url
             .domain()
             .ok_or(EntryCrawlerError::InvalidUrl(entry.url.clone()))?;
-        let bytes = self
+        if !self
+            .robots_txt
+            .is_allowed(&self.client, &self.domain_locks, &url)
+            .await
+        {
+            info!("robots.txt disallows fetching entry, using feed content instead");
+            return self.save_feed_content(entry);
+        }
+        let resp = self
             .domain_locks
             .run_request(domain, async {
                 self.client
                     .get(url.clone())
                     .send()
                     .await
-                    .map_err(|_| EntryCrawlerError::FetchError(entry.url.clone()))?
-                    .bytes()
-                    .await
                     .map_err(|_| EntryCrawlerError::FetchError(entry.url.clone()))
             })
             .await?;
+        if let Err(err) = self
+            .domain_locks
+            .back_off_if_rate_limited(&self.pool, domain, resp.status(), resp.headers())
+            .await
+        {
+            error!("failed to save domain delay: {}", err);
+        }
+        if !resp.status().is_success() {
+            return Err(EntryCrawlerError::FetchError(entry.url.clone()));
+        }
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|_| EntryCrawlerError::FetchError(entry.url.clone()))?;
         info!("fetched entry");
         let article = extractor::extract(&mut bytes.reader(), &url)
             .map_err(|_| EntryCrawlerError::ExtractError(entry.url.clone()))?;
@@ -119,37 +272,37 @@ impl EntryCrawler {
         info!("sanitized content");
         fs::write(content_dir.join(format!("{}.html", id)), content)
             .map_err(|_| EntryCrawlerError::SaveContentError(entry.url.clone()))?;
-        fs::write(content_dir.join(format!("{}.txt", id)), article.text)
+        fs::write(content_dir.join(format!("{}.txt", id)), &article.text)
             .map_err(|_| EntryCrawlerError::SaveContentError(entry.url.clone()))?;
         info!("saved content to filesystem");
+        Entry::update_content_text(&self.pool, id, &article.text)
+            .await
+            .map_err(|_| EntryCrawlerError::SaveContentError(entry.url.clone()))?;
+        info!("indexed content for search");
         Ok(entry)
     }
 
-    #[instrument(skip_all, fields(msg = %msg))]
-    async fn handle_message(&mut self, msg: EntryCrawlerMessage) {
-        match msg {
-            EntryCrawlerMessage::Crawl { entry, respond_to } => {
-                let result = self.crawl_entry(entry).await;
-                // ignore the result since the initiator may have cancelled waiting for the
-                // response, and that is ok
-                let _ = respond_to.send(EntryCrawlerHandleMessage::Entry(result));
-            }
-        }
-    }
-
-    #[instrument(skip_all)]
-    async fn run(&mut self) {
-        debug!("starting entry crawler");
-        while let Some(msg) = self.receiver.recv().await {
-            self.handle_message(msg).await;
+    /// Saves the description the feed provided for the entry as its content, for entries whose
+    /// page may not be scraped. The description is already indexed for search.
+    fn save_feed_content(&self, entry: Entry) -> EntryCrawlerResult<Entry> {
+        if let Some(description) = &entry.description {
+            let content_dir = Path::new(&self.content_dir);
+            fs::write(
+                content_dir.join(format!("{}.html", entry.entry_id)),
+                clean(description),
+            )
+            .map_err(|_| EntryCrawlerError::SaveContentError(entry.url.clone()))?;
+            info!("saved feed content to filesystem");
         }
+        Ok(entry)
     }
 }
 
 /// The `EntryCrawlerHandle` is used to initialize and communicate with a `EntryCrawler` actor.
 ///
-/// The `EntryCrawler` actor fetches a feed url, parses it, and saves it to the database. It runs
-/// as a separate asynchronous task from the main web server and communicates via channels.
+/// The `EntryCrawler` actor scrapes the content of entries, with up to `concurrency` entries at
+/// once. It runs as a separate asynchronous task from the main web server and communicates via
+/// channels. One handle is created per process and shared by all feed crawlers.
 #[derive(Clone)]
 pub struct EntryCrawlerHandle {
     sender: mpsc::Sender<EntryCrawlerMessage>,
@@ -170,22 +323,37 @@ impl EntryCrawlerHandle {
         pool: PgPool,
         client: Client,
         domain_locks: DomainLocks,
+        robots_txt: RobotsTxt,
         content_dir: String,
+        concurrency: usize,
     ) -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut crawler = EntryCrawler::new(receiver, pool, client, domain_locks, content_dir);
+        let scraper = EntryScraper {
+            pool,
+            client,
+            domain_locks,
+            robots_txt,
+            content_dir,
+        };
+        let mut crawler = EntryCrawler::new(receiver, scraper, concurrency);
         tokio::spawn(async move { crawler.run().await });
 
         Self { sender }
     }
 
-    /// Sends a `EntryCrawlerMessage::Crawl` message to the running `EntryCrawler` actor.
+    /// Sends a `EntryCrawlerMessage::Crawl` message to the running `EntryCrawler` actor, which
+    /// queues the entry to be scraped.
     ///
     /// Listen to the result of the crawl via the returned `broadcast::Receiver`.
-    pub async fn crawl(&self, entry: Entry) -> broadcast::Receiver<EntryCrawlerHandleMessage> {
+    pub async fn crawl(
+        &self,
+        entry: Entry,
+        priority: EntryCrawlPriority,
+    ) -> broadcast::Receiver<EntryCrawlerHandleMessage> {
         let (sender, receiver) = broadcast::channel(8);
         let msg = EntryCrawlerMessage::Crawl {
             entry,
+            priority,
             respond_to: sender,
         };
 
@@ -1,12 +1,15 @@
 use std::cmp::Ordering;
 use std::fmt::{self, Display, Formatter};
+use std::time::Instant;
 
+use bytes::Bytes;
 use chrono::{Duration, Utc};
-use feed_rs::parser;
+use feed_rs::{model, parser};
+use once_cell::sync::Lazy;
 use reqwest::StatusCode;
 use reqwest::{
     header::{self, HeaderMap},
-    Client,
+    redirect, Client, Response,
 };
 use sqlx::PgPool;
 use tokio::sync::{broadcast, mpsc};
@@ -15,13 +18,34 @@ use url::Url;
 use uuid::Uuid;
 
 use crate::actors::entry_crawler::{
-    EntryCrawlerHandle, EntryCrawlerHandleMessage, EntryCrawlerResult,
+    EntryCrawlPriority, EntryCrawlerHandle, EntryCrawlerHandleMessage, EntryCrawlerResult,
 };
 use crate::domain_locks::DomainLocks;
+use crate::models::crawl_log::{CrawlLog, CreateCrawlLog, CRAWL_LOG_RETENTION_DAYS};
 use crate::models::entry::{CreateEntry, Entry};
-use crate::models::feed::{Feed, MAX_CRAWL_INTERVAL_MINUTES, MIN_CRAWL_INTERVAL_MINUTES};
+use crate::models::feed::{
+    Feed, AUTO_PAUSE_AFTER_FAILING_DAYS, MAX_CRAWL_INTERVAL_MINUTES, MIN_CRAWL_INTERVAL_MINUTES,
+};
+use crate::models::feed_url_alias::FeedUrlAlias;
+use crate::models::websub_subscription::WebSubSubscription;
+use crate::robots_txt::RobotsTxt;
 use crate::state::Crawls;
 use crate::uuid::Base62Uuid;
+use crate::websub::{self, WEBSUB_RENEW_BEFORE};
+use crate::USER_AGENT;
+
+/// The most redirects followed when fetching a feed.
+pub const MAX_REDIRECTS: usize = 10;
+
+/// Feeds are fetched with a client that does not follow redirects itself, so that
+/// `FeedCrawler::fetch_feed` can tell permanent redirects apart from temporary ones.
+static FEED_CLIENT: Lazy<Client> = Lazy::new(|| {
+    Client::builder()
+        .user_agent(USER_AGENT)
+        .redirect(redirect::Policy::none())
+        .build()
+        .expect("failed to build feed client")
+});
 
 /// The `FeedCrawler` actor fetches a feed url, parses it, and saves it to the database.
 ///
@@ -34,14 +58,22 @@ struct FeedCrawler {
     pool: PgPool,
     client: Client,
     domain_locks: DomainLocks,
-    content_dir: String,
+    robots_txt: RobotsTxt,
+    entry_crawler: EntryCrawlerHandle,
     crawls: Crawls,
+    public_url: Url,
 }
 
 #[derive(Debug)]
 enum FeedCrawlerMessage {
     Crawl {
         feed_id: Uuid,
+        priority: EntryCrawlPriority,
+        respond_to: broadcast::Sender<FeedCrawlerHandleMessage>,
+    },
+    Ingest {
+        feed_id: Uuid,
+        body: Bytes,
         respond_to: broadcast::Sender<FeedCrawlerHandleMessage>,
     },
 }
@@ -50,6 +82,7 @@ impl Display for FeedCrawlerMessage {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             FeedCrawlerMessage::Crawl { feed_id, .. } => write!(f, "Crawl({})", feed_id),
+            FeedCrawlerMessage::Ingest { feed_id, .. } => write!(f, "Ingest({})", feed_id),
         }
     }
 }
@@ -62,6 +95,8 @@ pub enum FeedCrawlerError {
     InvalidUrl(String),
     #[error("failed to fetch feed: {0}")]
     FetchError(Url),
+    #[error("robots.txt disallows fetching feed: {0}")]
+    DisallowedByRobotsTxt(Url),
     #[error("failed to parse feed: {0}")]
     ParseError(Url),
     #[error("failed to find feed in database: {0}")]
@@ -70,25 +105,50 @@ pub enum FeedCrawlerError {
     CreateFeedError(Url),
     #[error("failed to create feed entries: {0}")]
     CreateFeedEntriesError(Url),
+    #[error("feed is not active: {0}")]
+    InactiveFeed(Base62Uuid),
 }
 pub type FeedCrawlerResult<T, E = FeedCrawlerError> = ::std::result::Result<T, E>;
 
+impl FeedCrawlerError {
+    /// The name of the variant, which is saved in the crawl log of the feed.
+    pub fn variant(&self) -> &'static str {
+        use FeedCrawlerError::*;
+
+        match self {
+            InvalidUrl(..) => "InvalidUrl",
+            FetchError(..) => "FetchError",
+            DisallowedByRobotsTxt(..) => "DisallowedByRobotsTxt",
+            ParseError(..) => "ParseError",
+            GetFeedError(..) => "GetFeedError",
+            CreateFeedError(..) => "CreateFeedError",
+            CreateFeedEntriesError(..) => "CreateFeedEntriesError",
+            InactiveFeed(..) => "InactiveFeed",
+        }
+    }
+}
+
 impl FeedCrawler {
+    #[allow(clippy::too_many_arguments)]
     fn new(
         receiver: mpsc::Receiver<FeedCrawlerMessage>,
         pool: PgPool,
         client: Client,
         domain_locks: DomainLocks,
-        content_dir: String,
+        robots_txt: RobotsTxt,
+        entry_crawler: EntryCrawlerHandle,
         crawls: Crawls,
+        public_url: Url,
     ) -> Self {
         FeedCrawler {
             receiver,
             pool,
             client,
             domain_locks,
-            content_dir,
+            robots_txt,
+            entry_crawler,
             crawls,
+            public_url,
         }
     }
 
@@ -96,17 +156,16 @@ impl FeedCrawler {
     async fn crawl_feed(
         &self,
         feed_id: Uuid,
+        priority: EntryCrawlPriority,
+        crawl_log: &mut CreateCrawlLog,
         respond_to: broadcast::Sender<FeedCrawlerHandleMessage>,
     ) -> FeedCrawlerResult<Feed> {
         let mut feed = Feed::get(&self.pool, feed_id)
             .await
             .map_err(|_| FeedCrawlerError::GetFeedError(Base62Uuid::from(feed_id)))?;
         info!("got feed from db");
-        let url =
+        let mut url =
             Url::parse(&feed.url).map_err(|_| FeedCrawlerError::InvalidUrl(feed.url.clone()))?;
-        let domain = url
-            .domain()
-            .ok_or(FeedCrawlerError::InvalidUrl(feed.url.clone()))?;
         let mut headers = HeaderMap::new();
         if let Some(etag) = &feed.etag_header {
             if let Ok(etag) = etag.parse() {
@@ -126,18 +185,40 @@ impl FeedCrawler {
             }
         }
 
+        if self.robots_txt.check_feeds()
+            && !self
+                .robots_txt
+                .is_allowed(&self.client, &self.domain_locks, &url)
+                .await
+        {
+            return Err(FeedCrawlerError::DisallowedByRobotsTxt(url));
+        }
+
         info!(url=%url, "starting fetch");
-        let resp = self
-            .domain_locks
-            .run_request(domain, async {
-                self.client
-                    .get(url.clone())
-                    .headers(headers)
-                    .send()
-                    .await
-                    .map_err(|_| FeedCrawlerError::FetchError(url.clone()))
-            })
-            .await?;
+        let (resp, moved_to) = self.fetch_feed(&url, headers).await?;
+        crawl_log.status_code = Some(resp.status().as_u16() as i16);
+        if let Some(moved_to) = moved_to {
+            info!(%moved_to, "feed moved permanently");
+            crawl_log.moved_to = Some(moved_to.to_string());
+            match Feed::get_by_url(&self.pool, moved_to.as_str()).await {
+                Ok(existing) if existing.feed_id != feed_id => {
+                    info!(into_feed_id = %existing.feed_id, "feed moved to an existing feed");
+                    Feed::merge_into(&self.pool, feed_id, existing.feed_id)
+                        .await
+                        .map_err(|_| FeedCrawlerError::CreateFeedError(moved_to.clone()))?;
+                    return Feed::get(&self.pool, feed_id)
+                        .await
+                        .map_err(|_| FeedCrawlerError::GetFeedError(Base62Uuid::from(feed_id)));
+                }
+                _ => {}
+            }
+            FeedUrlAlias::create(&self.pool, feed_id, &feed.url)
+                .await
+                .map_err(|_| FeedCrawlerError::CreateFeedError(moved_to.clone()))?;
+            feed.url = moved_to.to_string();
+            url = moved_to;
+        }
+        crawl_log.not_modified = resp.status() == StatusCode::NOT_MODIFIED;
         let headers = resp.headers();
         if let Some(etag) = headers.get(header::ETAG) {
             if let Ok(etag) = etag.to_str() {
@@ -171,6 +252,10 @@ impl FeedCrawler {
             warn!("feed returned non-successful status");
             feed.last_crawled_at = Some(Utc::now());
             feed.last_crawl_error = resp.status().canonical_reason().map(|s| s.to_string());
+            if resp.status() == StatusCode::GONE {
+                info!("feed is gone, marking it inactive");
+                feed.inactive_at = Some(Utc::now());
+            }
             let feed = feed
                 .save(&self.pool)
                 .await
@@ -183,22 +268,189 @@ impl FeedCrawler {
             .bytes()
             .await
             .map_err(|_| FeedCrawlerError::FetchError(url.clone()))?;
+        crawl_log.bytes = Some(bytes.len() as i64);
 
         let parsed_feed =
             parser::parse(&bytes[..]).map_err(|_| FeedCrawlerError::ParseError(url.clone()))?;
         info!("parsed feed");
+        self.update_websub_subscription(feed_id, &url, &parsed_feed)
+            .await;
+        self.save_feed(feed, url, parsed_feed, priority, crawl_log, respond_to)
+            .await
+    }
+
+    /// Fetches the feed, following up to `MAX_REDIRECTS` redirects. Each request waits its turn
+    /// for the domain it is made to.
+    ///
+    /// Also returns the url the feed permanently moved to, which is the last url redirected to
+    /// before the first temporary redirect in the chain (if any redirect was permanent at all).
+    async fn fetch_feed(
+        &self,
+        url: &Url,
+        headers: HeaderMap,
+    ) -> FeedCrawlerResult<(Response, Option<Url>)> {
+        let mut url = url.clone();
+        let mut moved_to = None;
+        let mut permanent = true;
+        for _ in 0..=MAX_REDIRECTS {
+            let domain = url
+                .domain()
+                .ok_or(FeedCrawlerError::InvalidUrl(url.to_string()))?;
+            let resp = self
+                .domain_locks
+                .run_request(domain, async {
+                    FEED_CLIENT
+                        .get(url.clone())
+                        .headers(headers.clone())
+                        .send()
+                        .await
+                        .map_err(|_| FeedCrawlerError::FetchError(url.clone()))
+                })
+                .await?;
+            if let Err(err) = self
+                .domain_locks
+                .back_off_if_rate_limited(&self.pool, domain, resp.status(), resp.headers())
+                .await
+            {
+                error!("failed to save domain delay: {}", err);
+            }
+            let status = resp.status();
+            if !status.is_redirection() || status == StatusCode::NOT_MODIFIED {
+                return Ok((resp, moved_to));
+            }
+            let location = resp
+                .headers()
+                .get(header::LOCATION)
+                .and_then(|location| location.to_str().ok())
+                .and_then(|location| url.join(location).ok())
+                .ok_or(FeedCrawlerError::FetchError(url.clone()))?;
+            debug!(%status, %location, "following redirect");
+            permanent &= matches!(
+                status,
+                StatusCode::MOVED_PERMANENTLY | StatusCode::PERMANENT_REDIRECT
+            );
+            if permanent {
+                moved_to = Some(location.clone());
+            }
+            url = location;
+        }
+        Err(FeedCrawlerError::FetchError(url))
+    }
+
+    /// Saves content of the feed that was pushed by its WebSub hub the same way as a crawled one.
+    ///
+    /// Content pushed for feeds that are deleted, paused or gone is ignored, since hubs may keep
+    /// pushing until their lease expires.
+    #[instrument(skip_all, fields(feed_id = %feed_id))]
+    async fn ingest_feed(
+        &self,
+        feed_id: Uuid,
+        body: Bytes,
+        crawl_log: &mut CreateCrawlLog,
+        respond_to: broadcast::Sender<FeedCrawlerHandleMessage>,
+    ) -> FeedCrawlerResult<Feed> {
+        crawl_log.pushed = true;
+        crawl_log.bytes = Some(body.len() as i64);
+        let feed = Feed::get(&self.pool, feed_id)
+            .await
+            .map_err(|_| FeedCrawlerError::GetFeedError(Base62Uuid::from(feed_id)))?;
+        info!("got feed from db");
+        if !feed.is_active() {
+            return Err(FeedCrawlerError::InactiveFeed(Base62Uuid::from(feed_id)));
+        }
+        let url =
+            Url::parse(&feed.url).map_err(|_| FeedCrawlerError::InvalidUrl(feed.url.clone()))?;
+        let parsed_feed =
+            parser::parse(&body[..]).map_err(|_| FeedCrawlerError::ParseError(url.clone()))?;
+        info!("parsed pushed feed");
+        self.save_feed(
+            feed,
+            url,
+            parsed_feed,
+            EntryCrawlPriority::Scheduled,
+            crawl_log,
+            respond_to,
+        )
+        .await
+    }
+
+    /// Subscribes to the WebSub hub advertised by the feed (if any), or renews the existing
+    /// subscription. Failures are only logged since the feed is still crawled without one.
+    async fn update_websub_subscription(
+        &self,
+        feed_id: Uuid,
+        url: &Url,
+        parsed_feed: &model::Feed,
+    ) {
+        let link = |rel: &str| {
+            parsed_feed
+                .links
+                .iter()
+                .find(|link| link.rel.as_deref() == Some(rel))
+                .and_then(|link| url.join(&link.href).ok())
+        };
+        let subscription = WebSubSubscription::get(&self.pool, feed_id).await.ok();
+        let Some(hub_url) = link("hub") else {
+            if subscription.is_some() {
+                info!("feed no longer advertises a websub hub");
+                if let Err(err) = WebSubSubscription::delete(&self.pool, feed_id).await {
+                    error!("failed to delete websub subscription: {}", err);
+                }
+            }
+            return;
+        };
+        let topic_url = link("self").unwrap_or_else(|| url.clone());
+        if subscription.is_some_and(|subscription| {
+            !subscription.needs_renewal(hub_url.as_str(), topic_url.as_str(), WEBSUB_RENEW_BEFORE)
+        }) {
+            return;
+        }
+        if let Err(err) = websub::subscribe(
+            &self.pool,
+            &self.client,
+            &self.public_url,
+            feed_id,
+            &hub_url,
+            &topic_url,
+        )
+        .await
+        {
+            warn!(%hub_url, "failed to subscribe to websub hub: {}", err);
+        }
+    }
+
+    async fn save_feed(
+        &self,
+        mut feed: Feed,
+        url: Url,
+        parsed_feed: model::Feed,
+        priority: EntryCrawlPriority,
+        crawl_log: &mut CreateCrawlLog,
+        respond_to: broadcast::Sender<FeedCrawlerHandleMessage>,
+    ) -> FeedCrawlerResult<Feed> {
         feed.url = url.to_string();
         feed.feed_type = parsed_feed.feed_type.into();
         feed.last_crawled_at = Some(Utc::now());
         feed.last_crawl_error = None;
-        if let Some(title) = parsed_feed.title {
+        // Fields edited by a user are pinned and not overwritten by the feed
+        if let Some(title) = parsed_feed.title.filter(|_| !feed.title_pinned) {
             feed.title = Some(title.content);
         }
-        if let Some(description) = parsed_feed.description {
+        if let Some(description) = parsed_feed.description.filter(|_| !feed.description_pinned) {
             feed.description = Some(description.content);
         }
+        if let Some(link) = parsed_feed
+            .links
+            .iter()
+            .find(|link| matches!(link.rel.as_deref(), None | Some("alternate")))
+        {
+            feed.site_url = Some(link.href.clone());
+        }
         let last_entry_published_at = parsed_feed.entries.iter().filter_map(|e| e.published).max();
-        if let Some(prev_last_entry_published_at) = feed.last_entry_published_at {
+        if let Some(prev_last_entry_published_at) = feed
+            .last_entry_published_at
+            .filter(|_| !feed.crawl_interval_pinned)
+        {
             if let Some(published_at) = last_entry_published_at {
                 let time_since_last_entry = if published_at == prev_last_entry_published_at {
                     // No new entry since last crawl, compare current time to last publish instead
@@ -211,7 +463,7 @@ impl FeedCrawler {
                     .cmp(&Duration::minutes(feed.crawl_interval_minutes.into()))
                 {
                     Ordering::Greater => {
-                        feed.crawl_interval_minutes = i32::max(
+                        feed.crawl_interval_minutes = i32::min(
                             (feed.crawl_interval_minutes as f32 * 1.2).ceil() as i32,
                             MAX_CRAWL_INTERVAL_MINUTES,
                         );
@@ -254,6 +506,7 @@ impl FeedCrawler {
                     description: entry.summary.map(|s| s.content),
                     feed_id: feed.feed_id,
                     published_at,
+                    created_by: None,
                 };
                 payload.push(entry);
             } else {
@@ -267,30 +520,96 @@ impl FeedCrawler {
             .into_iter()
             .partition::<Vec<_>, _>(|entry| entry.updated_at.is_none());
         info!(new = new.len(), updated = updated.len(), "saved entries");
+        crawl_log.new_entries = new.len() as i32;
+        crawl_log.updated_entries = updated.len() as i32;
 
+        // The new entries are scraped by the shared entry crawler after the crawl of the feed has
+        // finished. Their results are still sent to `respond_to` as they come in.
+        let mut entry_receivers = Vec::with_capacity(new.len());
         for entry in new {
-            let entry_crawler = EntryCrawlerHandle::new(
-                self.pool.clone(),
-                self.client.clone(),
-                self.domain_locks.clone(),
-                self.content_dir.clone(),
-            );
-            let mut entry_receiver = entry_crawler.crawl(entry).await;
-            while let Ok(EntryCrawlerHandleMessage::Entry(result)) = entry_receiver.recv().await {
-                let _ = respond_to.send(FeedCrawlerHandleMessage::Entry(result));
-            }
+            entry_receivers.push(self.entry_crawler.crawl(entry, priority).await);
         }
+        tokio::spawn(async move {
+            for mut entry_receiver in entry_receivers {
+                while let Ok(EntryCrawlerHandleMessage::Entry(result)) = entry_receiver.recv().await
+                {
+                    let _ = respond_to.send(FeedCrawlerHandleMessage::Entry(result));
+                }
+            }
+        });
         Ok(feed)
     }
 
+    /// Records the run of the crawler in the feed's crawl history, and deletes history that is
+    /// older than `CRAWL_LOG_RETENTION_DAYS`.
+    async fn save_crawl_log(
+        &self,
+        mut crawl_log: CreateCrawlLog,
+        elapsed: std::time::Duration,
+        result: &FeedCrawlerResult<Feed>,
+    ) {
+        crawl_log.duration_ms = i32::try_from(elapsed.as_millis()).unwrap_or(i32::MAX);
+        if let Err(error) = result {
+            crawl_log.error = Some(error.variant().to_string());
+            crawl_log.error_message = Some(error.to_string());
+        }
+        let feed_id = crawl_log.feed_id;
+        if let Err(err) = CrawlLog::create(&self.pool, crawl_log).await {
+            error!("failed to save crawl log: {}", err);
+        }
+        let retention = Duration::days(CRAWL_LOG_RETENTION_DAYS);
+        if let Err(err) = CrawlLog::delete_before(&self.pool, feed_id, Utc::now() - retention).await
+        {
+            error!("failed to delete old crawl logs: {}", err);
+        }
+    }
+
+    /// Records whether the crawl failed, either with an error or an unsuccessful response from
+    /// the feed, and returns the result with the feed's updated failures.
+    async fn record_crawl(
+        &self,
+        feed_id: Uuid,
+        result: FeedCrawlerResult<Feed>,
+    ) -> FeedCrawlerResult<Feed> {
+        let failed = result
+            .as_ref()
+            .map_or(true, |feed| feed.last_crawl_error.is_some());
+        match Feed::record_crawl(&self.pool, feed_id, failed, AUTO_PAUSE_AFTER_FAILING_DAYS).await {
+            Ok(feed) => {
+                if failed && feed.paused_at.is_some() {
+                    warn!(
+                        failing_since = ?feed.failing_since,
+                        "feed has been failing for too long, pausing it"
+                    );
+                }
+                result.map(|_| feed)
+            }
+            Err(err) => {
+                error!("failed to record feed crawl failures: {}", err);
+                result
+            }
+        }
+    }
+
     #[instrument(skip_all, fields(msg = %msg))]
     async fn handle_message(&mut self, msg: FeedCrawlerMessage) {
         match msg {
             FeedCrawlerMessage::Crawl {
                 feed_id,
+                priority,
                 respond_to,
             } => {
-                let result = self.crawl_feed(feed_id, respond_to.clone()).await;
+                let mut crawl_log = CreateCrawlLog {
+                    feed_id,
+                    started_at: Utc::now(),
+                    ..Default::default()
+                };
+                let start = Instant::now();
+                let result = self
+                    .crawl_feed(feed_id, priority, &mut crawl_log, respond_to.clone())
+                    .await;
+                self.save_crawl_log(crawl_log, start.elapsed(), &result)
+                    .await;
                 {
                     let mut crawls = self.crawls.lock().await;
                     crawls.remove(&feed_id);
@@ -302,6 +621,31 @@ impl FeedCrawler {
                         Err(e) => error!("failed to update feed last_crawl_error: {}", e),
                     }
                 }
+                let result = self.record_crawl(feed_id, result).await;
+
+                // ignore the result since the initiator may have cancelled waiting for the
+                // response, and that is ok
+                let _ = respond_to.send(FeedCrawlerHandleMessage::Feed(result));
+            }
+            FeedCrawlerMessage::Ingest {
+                feed_id,
+                body,
+                respond_to,
+            } => {
+                let mut crawl_log = CreateCrawlLog {
+                    feed_id,
+                    started_at: Utc::now(),
+                    ..Default::default()
+                };
+                let start = Instant::now();
+                let result = self
+                    .ingest_feed(feed_id, body, &mut crawl_log, respond_to.clone())
+                    .await;
+                self.save_crawl_log(crawl_log, start.elapsed(), &result)
+                    .await;
+                if let Err(error) = &result {
+                    error!("failed to ingest pushed feed: {}", error);
+                }
 
                 // ignore the result since the initiator may have cancelled waiting for the
                 // response, and that is ok
@@ -345,24 +689,65 @@ impl FeedCrawlerHandle {
         pool: PgPool,
         client: Client,
         domain_locks: DomainLocks,
-        content_dir: String,
+        robots_txt: RobotsTxt,
+        entry_crawler: EntryCrawlerHandle,
         crawls: Crawls,
+        public_url: Url,
     ) -> Self {
         let (sender, receiver) = mpsc::channel(8);
-        let mut crawler =
-            FeedCrawler::new(receiver, pool, client, domain_locks, content_dir, crawls);
+        let mut crawler = FeedCrawler::new(
+            receiver,
+            pool,
+            client,
+            domain_locks,
+            robots_txt,
+            entry_crawler,
+            crawls,
+            public_url,
+        );
         tokio::spawn(async move { crawler.run().await });
 
         Self { sender }
     }
 
-    /// Sends a `FeedCrawlerMessage::Crawl` message to the running `FeedCrawler` actor.
+    /// Sends a `FeedCrawlerMessage::Crawl` message to the running `FeedCrawler` actor. The new
+    /// entries of the feed are scraped with the given `priority`.
     ///
-    /// Listen to the result of the crawl via the returned `broadcast::Receiver`.
-    pub async fn crawl(&self, feed_id: Uuid) -> broadcast::Receiver<FeedCrawlerHandleMessage> {
+    /// Listen to the result of the crawl via the returned `broadcast::Receiver`. The result of the
+    /// feed is sent as soon as its entries are saved, followed by the results of scraping each new
+    /// entry.
+    pub async fn crawl(
+        &self,
+        feed_id: Uuid,
+        priority: EntryCrawlPriority,
+    ) -> broadcast::Receiver<FeedCrawlerHandleMessage> {
         let (sender, receiver) = broadcast::channel(8);
         let msg = FeedCrawlerMessage::Crawl {
             feed_id,
+            priority,
+            respond_to: sender,
+        };
+
+        self.sender
+            .send(msg)
+            .await
+            .expect("feed crawler task has died");
+        receiver
+    }
+
+    /// Sends a `FeedCrawlerMessage::Ingest` message to the running `FeedCrawler` actor with the
+    /// content of the feed pushed by its WebSub hub.
+    ///
+    /// Listen to the result of saving the feed via the returned `broadcast::Receiver`.
+    pub async fn ingest(
+        &self,
+        feed_id: Uuid,
+        body: Bytes,
+    ) -> broadcast::Receiver<FeedCrawlerHandleMessage> {
+        let (sender, receiver) = broadcast::channel(8);
+        let msg = FeedCrawlerMessage::Ingest {
+            feed_id,
+            body,
             respond_to: sender,
         };
 
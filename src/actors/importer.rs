@@ -11,12 +11,15 @@ use uuid::Uuid;
 
 use crate::actors::crawl_scheduler::{CrawlSchedulerHandle, CrawlSchedulerHandleMessage};
 use crate::error::Error;
-use crate::models::feed::{Feed, CreateFeed};
+use crate::models::feed::{CreateFeed, Feed, FeedAddition};
+use crate::models::folder::{CreateFolder, Folder};
+use crate::models::subscription::Subscription;
 use crate::state::Imports;
 use crate::uuid::Base62Uuid;
 
 /// The `Importer` actor parses OPML bytes, loops through the document to find all feed URLs, then
-/// creates a DB entry for each and initiates a new crawl if the feed is new.
+/// creates a DB entry for each, subscribes the importing user to it (in a folder named after the
+/// enclosing outlines, if any), and initiates a new crawl if the feed is new.
 ///
 /// It receives `ImporterMessage` messages via the `receiver` channel. It communicates back to
 /// the sender of those messages via the `respond_to` channel on the `ImporterMessage`.
@@ -33,6 +36,7 @@ struct Importer {
 enum ImporterMessage {
     Import {
         import_id: Uuid,
+        user_id: Uuid,
         file_name: Option<String>,
         bytes: Bytes,
         respond_to: broadcast::Sender<ImporterHandleMessage>,
@@ -49,6 +53,15 @@ impl Display for ImporterMessage {
     }
 }
 
+/// A feed outline found in an OPML document.
+#[derive(Debug)]
+struct OutlineFeed {
+    url: String,
+    title: Option<String>,
+    /// Names of the enclosing outlines joined with " / ", if the feed is nested in any.
+    folder: Option<String>,
+}
+
 async fn listen_to_crawl(
     feed_id: Uuid,
     crawl_scheduler: CrawlSchedulerHandle,
@@ -87,10 +100,11 @@ impl Importer {
         }
     }
 
-    #[instrument(skip_all, fields(import_id = %import_id, file_name = ?file_name))]
+    #[instrument(skip_all, fields(import_id = %import_id, user_id = %user_id, file_name = ?file_name))]
     async fn import_opml(
         &self,
         import_id: Uuid,
+        user_id: Uuid,
         file_name: Option<String>,
         bytes: Bytes,
         respond_to: broadcast::Sender<ImporterHandleMessage>,
@@ -99,27 +113,51 @@ impl Importer {
             ImporterError::InvalidOPML(file_name.unwrap_or(Base62Uuid::from(import_id).to_string()))
         })?;
         let mut crawls = JoinSet::new();
-        for url in Self::gather_feed_urls(document.body.outlines) {
-            let feed = Feed::create(
+        for OutlineFeed { url, title, folder } in Self::gather_feeds(document.body.outlines, None) {
+            let (feed, addition) = match Feed::create_or_restore(
                 &self.pool,
                 CreateFeed {
                     url: url.clone(),
+                    title,
                     ..Default::default()
                 },
             )
-            .await;
-            if let Err(Error::Sqlx(sqlx::error::Error::Database(err))) = feed {
-                if err.is_unique_violation() {
+            .await
+            {
+                Ok(result) => result,
+                Err(_) => {
+                    let _ = respond_to.send(ImporterHandleMessage::CreateFeedError(url));
+                    continue;
+                }
+            };
+            if let Err(err) = Subscription::create(&self.pool, user_id, feed.feed_id).await {
+                if let Error::Conflict(_) = err {
                     let _ = respond_to.send(ImporterHandleMessage::AlreadyImported(url));
+                    continue;
                 }
-            } else if let Ok(feed) = feed {
-                crawls.spawn(listen_to_crawl(
-                    feed.feed_id,
-                    self.crawl_scheduler.clone(),
-                    respond_to.clone(),
-                ));
-            } else {
                 let _ = respond_to.send(ImporterHandleMessage::CreateFeedError(url));
+                continue;
+            }
+            if let Some(name) = folder {
+                if let Err(err) = self.add_to_folder(user_id, feed.feed_id, name).await {
+                    error!(%url, %err, "failed to add imported feed to folder");
+                }
+            }
+            match addition {
+                FeedAddition::Created => {
+                    crawls.spawn(listen_to_crawl(
+                        feed.feed_id,
+                        self.crawl_scheduler.clone(),
+                        respond_to.clone(),
+                    ));
+                }
+                FeedAddition::Restored => {
+                    let _ = self.crawl_scheduler.schedule(feed.feed_id).await;
+                    let _ = respond_to.send(ImporterHandleMessage::Subscribed(feed));
+                }
+                FeedAddition::Existing => {
+                    let _ = respond_to.send(ImporterHandleMessage::Subscribed(feed));
+                }
             }
         }
 
@@ -128,15 +166,41 @@ impl Importer {
         Ok(())
     }
 
-    fn gather_feed_urls(outlines: Vec<opml::Outline>) -> Vec<String> {
-        let mut urls = Vec::new();
+    async fn add_to_folder(&self, user_id: Uuid, feed_id: Uuid, name: String) -> Result<(), Error> {
+        let folder = Folder::get_or_create(&self.pool, user_id, CreateFolder { name }).await?;
+        Subscription::set_folder(&self.pool, user_id, feed_id, Some(folder.folder_id)).await?;
+        Ok(())
+    }
+
+    /// Flattens the nested outlines into a list of feeds, keeping the names of the outlines each
+    /// feed was nested in as its folder.
+    fn gather_feeds(outlines: Vec<opml::Outline>, folder: Option<&str>) -> Vec<OutlineFeed> {
+        let mut feeds = Vec::new();
         for outline in outlines.into_iter() {
+            let name = outline
+                .title
+                .filter(|title| !title.is_empty())
+                .or_else(|| Some(outline.text).filter(|text| !text.is_empty()));
             if let Some(url) = outline.xml_url {
-                urls.push(url);
+                feeds.push(OutlineFeed {
+                    url,
+                    title: name,
+                    folder: folder.map(String::from),
+                });
+                feeds.append(&mut Self::gather_feeds(outline.outlines, folder));
+            } else {
+                let child_folder = match (folder, name) {
+                    (Some(parent), Some(name)) => Some(format!("{} / {}", parent, name)),
+                    (None, Some(name)) => Some(name),
+                    (parent, None) => parent.map(String::from),
+                };
+                feeds.append(&mut Self::gather_feeds(
+                    outline.outlines,
+                    child_folder.as_deref(),
+                ));
             }
-            urls.append(&mut Self::gather_feed_urls(outline.outlines));
         }
-        urls
+        feeds
     }
 
     #[instrument(skip_all, fields(msg = %msg))]
@@ -144,12 +208,13 @@ impl Importer {
         match msg {
             ImporterMessage::Import {
                 import_id,
+                user_id,
                 file_name,
                 bytes,
                 respond_to,
             } => {
                 let result = self
-                    .import_opml(import_id, file_name, bytes, respond_to.clone())
+                    .import_opml(import_id, user_id, file_name, bytes, respond_to.clone())
                     .await;
                 {
                     let mut imports = self.imports.lock().await;
@@ -175,7 +240,8 @@ impl Importer {
 /// The `ImporterHandle` is used to initialize and communicate with a `Importer` actor.
 ///
 /// The `Importer` actor parses OPML bytes, loops through the document to find all feed URLs, then
-/// creates a DB entry for each and initiates a new crawl if the feed is new.
+/// creates a DB entry for each, subscribes the importing user to it (in a folder named after the
+/// enclosing outlines, if any), and initiates a new crawl if the feed is new.
 #[derive(Clone)]
 pub struct ImporterHandle {
     sender: mpsc::Sender<ImporterMessage>,
@@ -185,12 +251,15 @@ pub struct ImporterHandle {
 /// `ImporterHandle`.
 ///
 /// `ImporterHandleMessage::Import` contains the result of importing the OPML file.
+/// `ImporterHandleMessage::Subscribed` contains a feed that already existed (added by another
+/// user) that the importing user is now subscribed to.
 #[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone)]
 pub enum ImporterHandleMessage {
     Import(ImporterResult<()>),
     CreateFeedError(String),
     AlreadyImported(String),
+    Subscribed(Feed),
     CrawlScheduler(CrawlSchedulerHandleMessage),
 }
 
@@ -210,12 +279,14 @@ impl ImporterHandle {
     pub async fn import(
         &self,
         import_id: Uuid,
+        user_id: Uuid,
         file_name: Option<String>,
         bytes: Bytes,
     ) -> broadcast::Receiver<ImporterHandleMessage> {
         let (sender, receiver) = broadcast::channel(8);
         let msg = ImporterMessage::Import {
             import_id,
+            user_id,
             file_name,
             bytes,
             respond_to: sender,
@@ -225,3 +296,97 @@ impl ImporterHandle {
         receiver
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gather(body: &str) -> Vec<(String, Option<String>, Option<String>)> {
+        let document = OPML::from_str(&format!(
+            r#"<opml version="2.0"><head/><body>{}</body></opml>"#,
+            body
+        ))
+        .unwrap();
+        Importer::gather_feeds(document.body.outlines, None)
+            .into_iter()
+            .map(|feed| (feed.url, feed.title, feed.folder))
+            .collect()
+    }
+
+    #[test]
+    fn joins_nested_outline_names_into_folder() {
+        let feeds = gather(
+            r#"<outline text="A">
+                <outline text="B">
+                    <outline text="Nested" xmlUrl="https://example.com/nested.xml"/>
+                </outline>
+                <outline text="Top" xmlUrl="https://example.com/top.xml"/>
+            </outline>
+            <outline text="Loose" xmlUrl="https://example.com/loose.xml"/>"#,
+        );
+        assert_eq!(
+            feeds,
+            vec![
+                (
+                    "https://example.com/nested.xml".to_string(),
+                    Some("Nested".to_string()),
+                    Some("A / B".to_string())
+                ),
+                (
+                    "https://example.com/top.xml".to_string(),
+                    Some("Top".to_string()),
+                    Some("A".to_string())
+                ),
+                (
+                    "https://example.com/loose.xml".to_string(),
+                    Some("Loose".to_string()),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn falls_back_from_title_to_text() {
+        let feeds = gather(
+            r#"<outline text="Text" title="Title" xmlUrl="https://example.com/1.xml"/>
+            <outline text="Text" title="" xmlUrl="https://example.com/2.xml"/>
+            <outline text="" xmlUrl="https://example.com/3.xml"/>
+            <outline text="" title="Folder">
+                <outline text="Feed" xmlUrl="https://example.com/4.xml"/>
+            </outline>"#,
+        );
+        let names: Vec<_> = feeds
+            .into_iter()
+            .map(|(_, title, folder)| (title, folder))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                (Some("Title".to_string()), None),
+                (Some("Text".to_string()), None),
+                (None, None),
+                (Some("Feed".to_string()), Some("Folder".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_outlines_without_xml_url() {
+        let feeds = gather(
+            r#"<outline text="Empty folder"/>
+            <outline text="Link" htmlUrl="https://example.com/"/>
+            <outline text="">
+                <outline text="Unnamed" xmlUrl="https://example.com/unnamed.xml"/>
+            </outline>"#,
+        );
+        assert_eq!(
+            feeds,
+            vec![(
+                "https://example.com/unnamed.xml".to_string(),
+                Some("Unnamed".to_string()),
+                None
+            )]
+        );
+    }
+}
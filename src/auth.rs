@@ -1,12 +1,17 @@
 use anyhow::Context;
 use async_trait::async_trait;
+use axum::extract::{Request, State};
+use axum::http::header;
+use axum::middleware::Next;
+use axum::response::Response;
 use axum_login::{AuthUser, AuthnBackend, UserId};
 use password_auth;
 use serde::Deserialize;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::{error::Result, models::user::User};
+use crate::error::{Error, Result};
+use crate::models::{api_token::ApiToken, user::User};
 
 pub async fn generate_hash(password: String) -> Result<String> {
     // Argon2 hashing is designed to be computationally intensive,
@@ -93,3 +98,41 @@ impl AuthnBackend for Backend {
 }
 
 pub type AuthSession = axum_login::AuthSession<Backend>;
+
+/// Middleware that requires every request to be made by a user, either logged in with a session
+/// or authenticated with an `Authorization: Bearer <token>` header, where the token is an
+/// `ApiToken`.
+///
+/// The user of the token is set on the `AuthSession` of the request, without logging them in, so
+/// handlers don't need to know how the user authenticated.
+pub async fn require_api_user(
+    State(pool): State<PgPool>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_owned());
+    if let Some(token) = token {
+        let api_token = ApiToken::authenticate(&pool, &token).await?;
+        if !api_token.scope.allows(request.method()) {
+            return Err(Error::Forbidden("token is read-only"));
+        }
+        let user = api_token.get_user(&pool).await?;
+        let auth = request
+            .extensions_mut()
+            .get_mut::<AuthSession>()
+            .ok_or(Error::InternalServerError)?;
+        auth.user = Some(user);
+    } else if request
+        .extensions()
+        .get::<AuthSession>()
+        .is_none_or(|auth| auth.user.is_none())
+    {
+        return Err(Error::Unauthorized);
+    }
+    Ok(next.run(request).await)
+}
@@ -2,19 +2,28 @@ use anyhow::Result;
 use chrono::Utc;
 use clap::{Args, Parser, Subcommand};
 use dotenvy::dotenv;
-use lib::actors::feed_crawler::FeedCrawlerHandle;
+use lib::actors::entry_crawler::{
+    EntryCrawlPriority, EntryCrawlerHandle, DEFAULT_ENTRY_CRAWL_CONCURRENCY,
+};
+use lib::actors::feed_crawler::{FeedCrawlerHandle, FeedCrawlerHandleMessage};
 use lib::domain_locks::DomainLocks;
+use lib::exporter::export_opml;
 use reqwest::Client;
 use sqlx::postgres::PgPoolOptions;
 use std::collections::HashMap;
 use std::env;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{error, info};
 use uuid::Uuid;
 
+use lib::models::domain_delay::DomainDelay;
 use lib::models::entry::{CreateEntry, Entry};
 use lib::models::feed::{CreateFeed, Feed, FeedType};
+use lib::models::subscription::Subscription;
+use lib::models::user::User;
+use lib::robots_txt::RobotsTxt;
 use lib::uuid::Base62Uuid;
 use lib::USER_AGENT;
 
@@ -34,6 +43,10 @@ enum Commands {
     DeleteFeed(DeleteFeed),
     AddEntry(AddEntry),
     DeleteEntry(DeleteEntry),
+    ExportOpml(ExportOpml),
+    IndexContent(IndexContent),
+    SetDomainDelay(SetDomainDelay),
+    SetAdmin(SetAdmin),
 }
 
 #[derive(Args)]
@@ -58,6 +71,9 @@ struct AddFeed {
     /// description of the feed
     #[arg(short, long)]
     description: Option<String>,
+    /// id of a user to subscribe to the feed
+    #[arg(long)]
+    user_id: Option<Uuid>,
 }
 
 #[derive(Args)]
@@ -91,6 +107,39 @@ struct DeleteEntry {
     id: Uuid,
 }
 
+#[derive(Args)]
+/// Export the feeds a user is subscribed to as an OPML document
+struct ExportOpml {
+    /// id of the user whose feeds to export
+    user_id: Uuid,
+    /// file to write the OPML document to (defaults to stdout)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+/// Index the content text of all crawled entries in `CONTENT_DIR` for full-text search
+struct IndexContent;
+
+#[derive(Args)]
+/// Set the delay between requests to a domain (applied when the server is restarted)
+struct SetDomainDelay {
+    /// domain to set the delay for (e.g. "example.com")
+    domain: String,
+    /// delay between requests in milliseconds
+    delay_ms: i32,
+}
+
+#[derive(Args)]
+/// Grant a user admin rights, which allow editing, pausing and resuming shared feeds
+struct SetAdmin {
+    /// email of the user
+    email: String,
+    /// revoke admin rights instead
+    #[arg(long)]
+    revoke: bool,
+}
+
 #[tokio::main]
 pub async fn main() -> Result<()> {
     dotenv().ok();
@@ -117,6 +166,10 @@ pub async fn main() -> Result<()> {
             )
             .await?;
             info!("Created feed with id {}", Base62Uuid::from(feed.feed_id));
+            if let Some(user_id) = args.user_id {
+                Subscription::create(&pool, user_id, feed.feed_id).await?;
+                info!("Subscribed user {} to feed", Base62Uuid::from(user_id));
+            }
         }
         Commands::DeleteFeed(args) => {
             Feed::delete(&pool, args.id).await?;
@@ -131,6 +184,7 @@ pub async fn main() -> Result<()> {
                     description: args.description,
                     feed_id: args.feed_id,
                     published_at: Utc::now(),
+                    created_by: None,
                 },
             )
             .await?;
@@ -140,20 +194,96 @@ pub async fn main() -> Result<()> {
             Entry::delete(&pool, args.id).await?;
             info!("Deleted entry with id {}", Base62Uuid::from(args.id));
         }
+        Commands::ExportOpml(args) => {
+            let document = export_opml(&pool, args.user_id).await?;
+            match args.output {
+                Some(path) => {
+                    tokio::fs::write(&path, document).await?;
+                    info!("Exported feeds to {}", path.display());
+                }
+                None => println!("{}", document),
+            }
+        }
+        Commands::SetDomainDelay(SetDomainDelay { domain, delay_ms }) => {
+            DomainDelay::upsert(&pool, &domain, delay_ms, None).await?;
+            info!("Set delay for domain {} to {} ms", domain, delay_ms);
+        }
+        Commands::SetAdmin(SetAdmin { email, revoke }) => {
+            let user = User::set_admin(&pool, email, !revoke).await?;
+            info!("Set admin of user {} to {}", user.email, user.admin);
+        }
+        Commands::IndexContent(_) => {
+            let mut indexed = 0;
+            let mut dir = tokio::fs::read_dir(env::var("CONTENT_DIR")?).await?;
+            while let Some(file) = dir.next_entry().await? {
+                let path = file.path();
+                if path.extension().and_then(|ext| ext.to_str()) != Some("txt") {
+                    continue;
+                }
+                let Some(entry_id) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| Uuid::parse_str(stem).ok())
+                else {
+                    continue;
+                };
+                let content_text = tokio::fs::read_to_string(&path).await?;
+                Entry::update_content_text(&pool, entry_id, &content_text).await?;
+                indexed += 1;
+            }
+            info!("Indexed content of {} entries", indexed);
+        }
         Commands::Crawl(CrawlFeed { id }) => {
             info!("Crawling feed {}...", Base62Uuid::from(id));
             let client = Client::builder().user_agent(USER_AGENT).build()?;
-            // NOTE: this is not the same DomainLocks as the one used in the server so, if the
-            // server is running, it will *not* serialize same-domain requests with it.
-            let domain_locks = DomainLocks::new();
-            let feed_crawler = FeedCrawlerHandle::new(
+            // Unless the server also uses shared domain locks, same-domain requests are *not*
+            // serialized with a running server.
+            let domain_locks = if env::var("SHARED_DOMAIN_LOCKS")
+                .is_ok_and(|shared_domain_locks| shared_domain_locks == "true")
+            {
+                DomainLocks::shared(pool.clone())
+            } else {
+                DomainLocks::new()
+            };
+            let robots_txt = RobotsTxt::new(
+                env::var("ROBOTS_TXT_CHECK_FEEDS").is_ok_and(|check_feeds| check_feeds == "true"),
+            );
+            let entry_crawler = EntryCrawlerHandle::new(
                 pool.clone(),
                 client.clone(),
                 domain_locks.clone(),
+                robots_txt.clone(),
                 env::var("CONTENT_DIR")?,
+                env::var("ENTRY_CRAWL_CONCURRENCY")
+                    .ok()
+                    .and_then(|concurrency| concurrency.parse().ok())
+                    .unwrap_or(DEFAULT_ENTRY_CRAWL_CONCURRENCY),
+            );
+            let feed_crawler = FeedCrawlerHandle::new(
+                pool.clone(),
+                client.clone(),
+                domain_locks.clone(),
+                robots_txt,
+                entry_crawler,
                 crawls.clone(),
+                env::var("PUBLIC_URL")?.parse()?,
             );
-            let _ = feed_crawler.crawl(id).await;
+            // wait for the new entries to be scraped as well, which happens after the feed crawl
+            let mut receiver = feed_crawler.crawl(id, EntryCrawlPriority::User).await;
+            while let Ok(msg) = receiver.recv().await {
+                match msg {
+                    FeedCrawlerHandleMessage::Feed(Ok(feed)) => info!("Crawled feed {}", feed.url),
+                    FeedCrawlerHandleMessage::Feed(Err(err)) => {
+                        error!("Failed to crawl feed: {}", err)
+                    }
+                    FeedCrawlerHandleMessage::Entry(Ok(entry)) => {
+                        info!("Crawled entry {}", entry.url)
+                    }
+                    FeedCrawlerHandleMessage::Entry(Err(err)) => {
+                        error!("Failed to crawl entry: {}", err)
+                    }
+                }
+            }
         }
     }
 
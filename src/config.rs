@@ -51,6 +51,21 @@ pub struct Config {
     pub max_mem_log_size: usize,
     #[clap(long, env, default_value = "./content")]
     pub content_dir: String,
+    /// Also check robots.txt before fetching feeds, not only before scraping entry pages
+    #[clap(long, env, default_value = "false")]
+    pub robots_txt_check_feeds: bool,
+    /// How many feeds this process crawls at once. Set to 0 for a process that only serves the web
+    /// app while other processes crawl.
+    #[clap(long, env, default_value = "4")]
+    pub crawl_workers: usize,
+    /// How many entry pages this process scrapes at once, across all feeds
+    #[clap(long, env, default_value = "8")]
+    pub entry_crawl_concurrency: usize,
+    /// Serialize requests to each domain with every other process using the same database (like
+    /// the CLI or other crawler processes), not only within this process. Each request then also
+    /// queries the database.
+    #[clap(long, env, default_value = "false")]
+    pub shared_domain_locks: bool,
     #[clap(long, env)]
     pub smtp_server: String,
     #[clap(long, env)]
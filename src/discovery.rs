@@ -0,0 +1,245 @@
+use feed_rs::parser;
+use reqwest::Client;
+use tracing::{debug, info};
+use url::Url;
+
+use crate::domain_locks::DomainLocks;
+
+/// Feed types advertised in `<link rel="alternate" type="...">` tags of HTML pages.
+pub const FEED_LINK_TYPES: [&str; 3] = [
+    "application/rss+xml",
+    "application/atom+xml",
+    "application/feed+json",
+];
+
+/// Paths that are commonly used for feeds, tried in order when a page doesn't link to any feeds.
+pub const COMMON_FEED_PATHS: [&str; 7] = [
+    "/feed",
+    "/rss",
+    "/feed.xml",
+    "/rss.xml",
+    "/atom.xml",
+    "/index.xml",
+    "/feed.json",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscoveredFeed {
+    pub url: Url,
+    pub title: Option<String>,
+}
+
+/// Finds the feeds for a URL that may be a feed itself or a web page (like a blog homepage).
+///
+/// If the URL is a feed, then only that feed is returned. Otherwise, the feeds linked from the
+/// page's `<link rel="alternate">` tags are returned. If there are none, then common feed paths
+/// are requested on the site and the first one that parses as a feed is returned.
+///
+/// Only an error fetching the passed URL is returned, all other failures are ignored so that an
+/// empty list is returned when no feeds could be found.
+pub async fn discover_feeds(
+    client: &Client,
+    domain_locks: &DomainLocks,
+    url: &Url,
+) -> Result<Vec<DiscoveredFeed>, reqwest::Error> {
+    let domain = url.domain().unwrap_or_default();
+    info!(%url, "discovering feeds");
+    let resp = domain_locks
+        .run_request(domain, async {
+            client.get(url.clone()).send().await?.error_for_status()
+        })
+        .await?;
+    let url = resp.url().clone();
+    let bytes = resp.bytes().await?;
+
+    if let Ok(feed) = parser::parse(&bytes[..]) {
+        debug!(%url, "url is a feed");
+        return Ok(vec![DiscoveredFeed {
+            url,
+            title: feed.title.map(|title| title.content),
+        }]);
+    }
+
+    let html = String::from_utf8_lossy(&bytes);
+    let mut feeds: Vec<DiscoveredFeed> = Vec::new();
+    for feed in find_feed_links(&html, &url) {
+        if !feeds.iter().any(|found| found.url == feed.url) {
+            feeds.push(feed);
+        }
+    }
+    if !feeds.is_empty() {
+        debug!(%url, count = feeds.len(), "found feed links in page");
+        return Ok(feeds);
+    }
+
+    for path in COMMON_FEED_PATHS {
+        let Ok(feed_url) = url.join(path) else {
+            continue;
+        };
+        if let Some(feed) = probe_feed(client, domain_locks, &feed_url).await {
+            debug!(url = %feed.url, "found feed at common path");
+            return Ok(vec![feed]);
+        }
+    }
+    Ok(feeds)
+}
+
+async fn probe_feed(
+    client: &Client,
+    domain_locks: &DomainLocks,
+    url: &Url,
+) -> Option<DiscoveredFeed> {
+    let domain = url.domain().unwrap_or_default();
+    let resp = domain_locks
+        .run_request(domain, async {
+            client.get(url.clone()).send().await?.error_for_status()
+        })
+        .await
+        .ok()?;
+    let url = resp.url().clone();
+    let bytes = resp.bytes().await.ok()?;
+    let feed = parser::parse(&bytes[..]).ok()?;
+    Some(DiscoveredFeed {
+        url,
+        title: feed.title.map(|title| title.content),
+    })
+}
+
+/// Returns the feeds in the `<link rel="alternate">` tags of the HTML, with their `href` resolved
+/// against `base`.
+///
+/// This is not a full HTML parser, it only scans for `<link>` tags and reads their attributes,
+/// which is all that is needed to find feed links in the `<head>` of a page.
+pub fn find_feed_links(html: &str, base: &Url) -> Vec<DiscoveredFeed> {
+    let lowercase = html.to_ascii_lowercase();
+    let end = lowercase.find("</head>").unwrap_or(lowercase.len());
+    let mut feeds = Vec::new();
+    let mut position = 0;
+    while let Some(start) = lowercase[position..end].find("<link") {
+        let tag_start = position + start + "<link".len();
+        let tag_end = lowercase[tag_start..]
+            .find('>')
+            .map(|tag_end| tag_start + tag_end)
+            .unwrap_or(lowercase.len());
+        position = tag_end.min(end);
+        let attributes = parse_attributes(&html[tag_start..tag_end]);
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.as_str())
+        };
+        let is_alternate = attribute("rel").is_some_and(|rel| {
+            rel.split_ascii_whitespace()
+                .any(|rel| rel.eq_ignore_ascii_case("alternate"))
+        });
+        let is_feed = attribute("type").is_some_and(|link_type| {
+            FEED_LINK_TYPES
+                .iter()
+                .any(|feed_type| link_type.trim().eq_ignore_ascii_case(feed_type))
+        });
+        if !is_alternate || !is_feed {
+            continue;
+        }
+        if let Some(url) = attribute("href").and_then(|href| base.join(href.trim()).ok()) {
+            feeds.push(DiscoveredFeed {
+                url,
+                title: attribute("title")
+                    .map(|title| title.trim().to_string())
+                    .filter(|title| !title.is_empty()),
+            });
+        }
+    }
+    feeds
+}
+
+/// Parses the attributes of an HTML tag into lowercase names and their (unescaped) values.
+fn parse_attributes(tag: &str) -> Vec<(String, String)> {
+    let mut attributes = Vec::new();
+    let mut chars = tag.char_indices().peekable();
+    loop {
+        while chars
+            .next_if(|(_, c)| c.is_whitespace() || *c == '/')
+            .is_some()
+        {}
+        let Some(&(name_start, _)) = chars.peek() else {
+            break;
+        };
+        let mut name_end = tag.len();
+        while let Some(&(index, c)) = chars.peek() {
+            if c.is_whitespace() || c == '=' || c == '/' {
+                name_end = index;
+                break;
+            }
+            chars.next();
+        }
+        let name = tag[name_start..name_end].to_ascii_lowercase();
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+        let mut value = String::new();
+        if chars.next_if(|(_, c)| *c == '=').is_some() {
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+            match chars.next_if(|(_, c)| *c == '"' || *c == '\'') {
+                Some((_, quote)) => {
+                    for (_, c) in chars.by_ref() {
+                        if c == quote {
+                            break;
+                        }
+                        value.push(c);
+                    }
+                }
+                None => {
+                    while let Some((_, c)) = chars.next_if(|(_, c)| !c.is_whitespace()) {
+                        value.push(c);
+                    }
+                }
+            }
+        }
+        attributes.push((name, unescape(&value)));
+    }
+    attributes
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_feed_links_in_head() {
+        let base = Url::parse("https://example.com/blog/").unwrap();
+        let html = r#"<html><head>
+            <link rel="stylesheet" href="/style.css">
+            <link rel="alternate" type="application/rss+xml" title="Posts" href="rss.xml">
+            <LINK REL=alternate TYPE="application/atom+xml" HREF='https://feeds.example.com/atom?a=1&amp;b=2' />
+            <link rel="alternate" type="text/html" hreflang="fr" href="/fr/">
+            </head><body><link rel="alternate" type="application/feed+json" href="/ignored.json"></body></html>"#;
+        assert_eq!(
+            find_feed_links(html, &base),
+            vec![
+                DiscoveredFeed {
+                    url: Url::parse("https://example.com/blog/rss.xml").unwrap(),
+                    title: Some("Posts".to_string()),
+                },
+                DiscoveredFeed {
+                    url: Url::parse("https://feeds.example.com/atom?a=1&b=2").unwrap(),
+                    title: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_no_feed_links_without_alternate_rel() {
+        let base = Url::parse("https://example.com/").unwrap();
+        let html = r#"<link type="application/rss+xml" href="/feed"><link rel="alternate">"#;
+        assert_eq!(find_feed_links(html, &base), vec![]);
+    }
+}
@@ -2,76 +2,289 @@ use std::collections::HashMap;
 use std::future::Future;
 use std::sync::Arc;
 
+use chrono::{DateTime, Utc};
+use reqwest::header::{self, HeaderMap};
+use reqwest::StatusCode;
+use sqlx::{Executor, PgPool, Postgres};
 use tokio::sync::Mutex;
 use tokio::time::{sleep, Duration, Instant};
-use tracing::debug;
+use tracing::{debug, warn};
 
-pub type DomainLocksMap = Arc<Mutex<HashMap<String, Arc<Mutex<Instant>>>>>;
+use crate::error::Result;
+use crate::models::domain_delay::DomainDelay;
+use crate::models::domain_request::DomainRequest;
 
-// TODO: make this configurable per domain and then load into a cache at startup
-// bonus points if I also make it changeable at runtime, for example, if a domain returns a 429,
-// then I can increase it and make sure it is saved back to the configuration for the next startup.
+pub type DomainLocksMap = Arc<Mutex<HashMap<String, Arc<Mutex<DomainLock>>>>>;
+
+/// The delay between requests to a domain, unless a different one is set for the domain in the
+/// `domain_delay` table.
 pub const DOMAIN_LOCK_DURATION: Duration = Duration::from_secs(1);
+/// When a domain rate limits crawlnicle, its delay is doubled up to this maximum.
+pub const MAX_DOMAIN_LOCK_DURATION: Duration = Duration::from_secs(60);
+/// The longest `Retry-After` that is honored, so that a domain asking for an absurd wait isn't
+/// skipped forever (and the wait can't overflow an `Instant`).
+pub const MAX_RETRY_AFTER: Duration = Duration::from_secs(6 * 60 * 60);
+/// How long shared locks wait before checking again when another process requested the domain
+/// right before them.
+const SHARED_CLAIM_RETRY_DELAY: Duration = Duration::from_millis(50);
+
+/// The state of requests to a single domain.
+#[derive(Debug)]
+pub struct DomainLock {
+    /// No request to the domain may be made before this time.
+    next_request: Instant,
+    /// How long to wait after a request to the domain before making the next one.
+    delay: Duration,
+}
+
+impl DomainLock {
+    fn new(delay: Duration) -> Self {
+        Self {
+            next_request: Instant::now(),
+            delay,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct DomainLocks {
     map: DomainLocksMap,
+    /// Set for locks that are shared with other processes through the database.
+    pool: Option<PgPool>,
 }
 
 /// A mechanism to serialize multiple async tasks requesting a single domain. To prevent
 /// overloading servers with too many requests run in parallel at once, crawlnicle will only
-/// request a domain once a second. All async tasks that wish to scrape a feed or entry must use
-/// the `run_request` method on this struct to wait their turn.
+/// request a domain once a second (or at the delay set for the domain in the database). All async
+/// tasks that wish to scrape a feed or entry must use the `run_request` method on this struct to
+/// wait their turn.
+///
+/// Contains a map of domain names to a lock containing the time the next request to that domain
+/// may be made and the delay between requests to that domain.
 ///
-/// Contains a map of domain names to a lock containing the timestamp of the last request to that
-/// domain.
+/// The map only serializes requests made by the same process. Locks created with `shared` also
+/// wait their turn with other processes using the same database (like the CLI, or several crawler
+/// processes): they record each request in the `domain_request` table, and only make it once no
+/// other process recorded one within the domain's delay. No database lock or connection is held
+/// while waiting or during the request.
 impl DomainLocks {
     pub fn new() -> Self {
         Self {
             map: Arc::new(Mutex::new(HashMap::new())),
+            pool: None,
         }
     }
 
+    /// Creates domain locks that are shared with every other process that uses shared domain locks
+    /// on the same database.
+    pub fn shared(pool: PgPool) -> Self {
+        Self {
+            map: Arc::new(Mutex::new(HashMap::new())),
+            pool: Some(pool),
+        }
+    }
+
+    async fn get_lock(&self, domain: &str) -> Arc<Mutex<DomainLock>> {
+        let mut map = self.map.lock().await;
+        map.entry(domain.to_owned())
+            .or_insert_with(|| Arc::new(Mutex::new(DomainLock::new(DOMAIN_LOCK_DURATION))))
+            .clone()
+    }
+
+    /// Loads the per-domain delays saved in the database, including any `Retry-After` that has
+    /// not passed yet.
+    pub async fn load(&self, db: impl Executor<'_, Database = Postgres>) -> Result<()> {
+        let domain_delays = DomainDelay::get_all(db).await?;
+        for domain_delay in domain_delays {
+            let lock = self.get_lock(&domain_delay.domain).await;
+            let mut lock = lock.lock().await;
+            lock.delay = Duration::from_millis(domain_delay.delay_ms.max(0) as u64);
+            if let Some(Ok(retry_after)) = domain_delay
+                .retry_after
+                .map(|retry_after| (retry_after - Utc::now()).to_std())
+            {
+                lock.next_request = Instant::now() + retry_after.min(MAX_RETRY_AFTER);
+            }
+        }
+        Ok(())
+    }
+
     /// Run the passed function `f` while holding a lock that gives exclusive access to the passed
-    /// domain. If another task running `run_request` currently has the lock to the 
+    /// domain. If another task running `run_request` currently has the lock to the
     /// `DomainLocksMap` or the lock to the domain passed, then this function will wait until that
-    /// other task is done. Once it has access to the lock, if it has been less than one second 
-    /// since the last request to the domain, then this function will sleep until one second has 
-    /// passed before calling `f`.
+    /// other task is done. Once it has access to the lock, if it has been less than the domain's
+    /// delay since the last request to the domain (or its `Retry-After` has not passed yet), then
+    /// this function will sleep until then before calling `f`.
+    ///
+    /// Shared locks then also wait until the domain's delay has passed since the last request any
+    /// process made to it, and record the request before calling `f`. If the database can't be
+    /// reached, only the in-process lock is used.
     pub async fn run_request<F, T>(&self, domain: &str, f: F) -> T
     where
         F: Future<Output = T>,
     {
-        let domain_last_request = {
-            let mut map = self.map.lock().await;
-            map.entry(domain.to_owned())
-                .or_insert_with(|| Arc::new(Mutex::new(Instant::now() - DOMAIN_LOCK_DURATION)))
-                .clone()
-        };
-
-        let mut domain_last_request = domain_last_request.lock().await;
-
-        let elapsed = domain_last_request.elapsed();
-        if elapsed < DOMAIN_LOCK_DURATION {
-            let sleep_duration = DOMAIN_LOCK_DURATION - elapsed;
+        let domain_lock = self.get_lock(domain).await;
+        let mut domain_lock = domain_lock.lock().await;
+
+        sleep_until_next_request(domain, domain_lock.next_request).await;
+        if let Some(pool) = &self.pool {
+            if let Err(err) = wait_shared(pool, domain, domain_lock.delay).await {
+                warn!(domain, "failed to wait for shared domain lock: {}", err);
+            }
+        }
+
+        let result = f.await;
+
+        domain_lock.next_request = Instant::now() + domain_lock.delay;
+
+        result
+    }
+
+    /// Raises the delay between requests to the domain to at least `delay` (up to
+    /// `MAX_DOMAIN_LOCK_DURATION`), like the `Crawl-delay` a site asks for in its robots.txt.
+    pub async fn set_min_delay(&self, domain: &str, delay: Duration) {
+        let delay = delay.min(MAX_DOMAIN_LOCK_DURATION);
+        let domain_lock = self.get_lock(domain).await;
+        let mut domain_lock = domain_lock.lock().await;
+        if delay > domain_lock.delay {
             debug!(
                 domain,
-                duration = format!("{} ms", sleep_duration.as_millis()),
-                "sleeping before requesting domain",
+                delay = format!("{} ms", delay.as_millis()),
+                "raising domain delay",
             );
-            sleep(DOMAIN_LOCK_DURATION - elapsed).await;
+            domain_lock.delay = delay;
         }
+    }
 
-        let result = f.await;
+    /// Backs off from the domain if the response status shows crawlnicle is being rate limited:
+    /// a 429, or a 503 with a `Retry-After` header.
+    ///
+    /// The delay for the domain is doubled (up to `MAX_DOMAIN_LOCK_DURATION`), and the next request
+    /// to the domain waits until its `Retry-After` has passed. Both are saved to the database so
+    /// that they still apply after a restart. Returns whether the domain was rate limiting.
+    pub async fn back_off_if_rate_limited(
+        &self,
+        db: impl Executor<'_, Database = Postgres>,
+        domain: &str,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<bool> {
+        let retry_after = headers
+            .get(header::RETRY_AFTER)
+            .and_then(|retry_after| retry_after.to_str().ok())
+            .and_then(parse_retry_after);
+        if !(status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some()))
+        {
+            return Ok(false);
+        }
 
-        *domain_last_request = Instant::now(); // Update the time of the last request.
+        let domain_lock = self.get_lock(domain).await;
+        let mut domain_lock = domain_lock.lock().await;
+        domain_lock.delay = (domain_lock.delay * 2).min(MAX_DOMAIN_LOCK_DURATION);
+        let retry_after = retry_after.unwrap_or(domain_lock.delay);
+        domain_lock.next_request = domain_lock.next_request.max(Instant::now() + retry_after);
+        warn!(
+            domain,
+            %status,
+            delay = format!("{} ms", domain_lock.delay.as_millis()),
+            retry_after = format!("{} s", retry_after.as_secs()),
+            "domain is rate limiting, backing off",
+        );
+        DomainDelay::upsert(
+            db,
+            domain,
+            domain_lock.delay.as_millis() as i32,
+            chrono::Duration::from_std(retry_after)
+                .ok()
+                .map(|retry_after| Utc::now() + retry_after),
+        )
+        .await?;
+        Ok(true)
+    }
+}
 
-        result
+async fn sleep_until_next_request(domain: &str, next_request: Instant) {
+    let now = Instant::now();
+    if next_request > now {
+        let sleep_duration = next_request - now;
+        debug!(
+            domain,
+            duration = format!("{} ms", sleep_duration.as_millis()),
+            "sleeping before requesting domain",
+        );
+        sleep(sleep_duration).await;
+    }
+}
+
+/// Waits until the domain may be requested according to the requests of all processes, then
+/// records the request. If another process recorded a request to the domain in the meantime, this
+/// waits for its turn again.
+async fn wait_shared(pool: &PgPool, domain: &str, delay: Duration) -> Result<()> {
+    let delay_ms = delay.as_millis() as i32;
+    loop {
+        let next_request_at = DomainRequest::next_request_at(pool, domain, delay_ms).await?;
+        let wait = next_request_at
+            .and_then(|next_request_at| (next_request_at - Utc::now()).to_std().ok())
+            .unwrap_or(Duration::ZERO);
+        sleep_until_next_request(domain, Instant::now() + wait).await;
+        if DomainRequest::claim(pool, domain, delay_ms).await? {
+            return Ok(());
+        }
+        sleep(SHARED_CLAIM_RETRY_DELAY).await;
     }
 }
 
+/// Parses a `Retry-After` header value, which is either a number of seconds or an HTTP date.
+/// Waits longer than `MAX_RETRY_AFTER` are shortened to it.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let retry_after = match value.trim().parse::<u64>() {
+        Ok(seconds) => Duration::from_secs(seconds),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value.trim()).ok()?;
+            (date.with_timezone(&Utc) - Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(retry_after.min(MAX_RETRY_AFTER))
+}
+
 impl Default for DomainLocks {
     fn default() -> Self {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn parses_retry_after_http_date() {
+        let date = (Utc::now() + chrono::Duration::seconds(120)).to_rfc2822();
+        let retry_after = parse_retry_after(&date).unwrap();
+        assert!(retry_after > Duration::from_secs(110) && retry_after <= Duration::from_secs(120));
+        assert_eq!(
+            parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn clamps_huge_retry_after() {
+        assert_eq!(
+            parse_retry_after(&u64::MAX.to_string()),
+            Some(MAX_RETRY_AFTER)
+        );
+        let date = (Utc::now() + chrono::Duration::days(365)).to_rfc2822();
+        assert_eq!(parse_retry_after(&date), Some(MAX_RETRY_AFTER));
+    }
+}
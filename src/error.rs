@@ -1,12 +1,25 @@
 use axum::extract::multipart::MultipartError;
-use axum::http::StatusCode;
+use axum::extract::{Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::Next;
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_with::DisplayFromStr;
+use axum_extra::headers::HeaderMapExt;
+use axum_extra::TypedHeader;
 use tracing::error;
 use uuid::Uuid;
 use validator::ValidationErrors;
 
+use crate::auth::AuthSession;
+use crate::config::Config;
+use crate::headers::Accept;
+use crate::htmx::{HXRequest, HXTarget};
+use crate::partials::error::{error_message, error_page};
+use crate::partials::layout::Layout;
+
+/// The `type` of problem details responses is this prefix followed by the error's `Error::code`.
+pub const PROBLEM_TYPE_PREFIX: &str = "urn:crawlnicle:error:";
+
 /// An API-friendly error type.
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -24,6 +37,9 @@ pub enum Error {
     #[error("an internal server error occurred")]
     Reqwest(#[from] reqwest::Error),
 
+    #[error("an internal server error occurred")]
+    Opml(#[from] opml::Error),
+
     #[error("validation error in request body")]
     InvalidEntity(#[from] ValidationErrors),
 
@@ -49,43 +65,89 @@ pub enum Error {
     Unauthorized,
 
     #[error("bad request: {0}")]
-    BadRequest(&'static str)
+    BadRequest(&'static str),
+
+    #[error("forbidden: {0}")]
+    Forbidden(&'static str),
+
+    #[error("conflict: {0}")]
+    Conflict(&'static str),
 }
 
 pub type Result<T, E = Error> = ::std::result::Result<T, E>;
 
+/// What an error response was made from. Kept in the extensions of the response so that
+/// `negotiate_error_response` can render it as HTML instead.
+#[derive(Debug, Clone)]
+pub struct ErrorDetails {
+    pub status: StatusCode,
+    pub code: &'static str,
+    pub detail: String,
+}
+
 impl IntoResponse for Error {
+    /// Responds with RFC 7807 problem details as `application/problem+json`.
     fn into_response(self) -> Response {
-        #[serde_with::serde_as]
         #[serde_with::skip_serializing_none]
         #[derive(serde::Serialize)]
-        struct ErrorResponse<'a> {
-            // Serialize the `Display` output as the error message
-            #[serde_as(as = "DisplayFromStr")]
-            message: &'a Error,
-
+        struct ProblemDetails<'a> {
+            #[serde(rename = "type")]
+            problem_type: String,
+            title: &'a str,
+            status: u16,
+            detail: &'a str,
             errors: Option<&'a ValidationErrors>,
         }
 
+        error!("API error: {:?}", self);
+
+        let status = self.status_code();
+        let details = ErrorDetails {
+            status,
+            code: self.code(),
+            detail: self.to_string(),
+        };
         let errors = match &self {
             Error::InvalidEntity(errors) => Some(errors),
             _ => None,
         };
-
-        error!("API error: {:?}", self);
-
-        (
-            self.status_code(),
-            Json(ErrorResponse {
-                message: &self,
+        let mut response = (
+            status,
+            [(header::CONTENT_TYPE, "application/problem+json")],
+            Json(ProblemDetails {
+                problem_type: format!("{}{}", PROBLEM_TYPE_PREFIX, details.code),
+                title: status.canonical_reason().unwrap_or("Error"),
+                status: status.as_u16(),
+                detail: &details.detail,
                 errors,
             }),
         )
-            .into_response()
+            .into_response();
+        response.extensions_mut().insert(details);
+        response
     }
 }
 
 impl Error {
+    /// A stable code for the kind of error, which API clients can match on instead of the message.
+    pub fn code(&self) -> &'static str {
+        use Error::*;
+
+        match self {
+            Sqlx(_) | Anyhow(_) | Reqwest(_) | InternalServerError => "internal",
+            Opml(_) => "opml",
+            InvalidEntity(_) => "validation",
+            Upload(_) => "upload",
+            NoFile => "no-file",
+            NotFoundUuid(_, _) | NotFoundString(_, _) => "not-found",
+            RelationNotFound(_) => "relation-not-found",
+            Unauthorized => "unauthorized",
+            BadRequest(_) => "bad-request",
+            Forbidden(_) => "forbidden",
+            Conflict(_) => "conflict",
+        }
+    }
+
     fn status_code(&self) -> StatusCode {
         use Error::*;
 
@@ -93,7 +155,9 @@ impl Error {
             NotFoundUuid(_, _) | NotFoundString(_, _) => StatusCode::NOT_FOUND,
             Unauthorized => StatusCode::UNAUTHORIZED,
             BadRequest(_) => StatusCode::BAD_REQUEST,
-            InternalServerError | Sqlx(_) | Anyhow(_) | Reqwest(_) => {
+            Forbidden(_) => StatusCode::FORBIDDEN,
+            Conflict(_) => StatusCode::CONFLICT,
+            InternalServerError | Sqlx(_) | Anyhow(_) | Reqwest(_) | Opml(_) => {
                 StatusCode::INTERNAL_SERVER_ERROR
             }
             InvalidEntity(_) | RelationNotFound(_) | NoFile => StatusCode::UNPROCESSABLE_ENTITY,
@@ -101,3 +165,52 @@ impl Error {
         }
     }
 }
+
+/// Middleware that renders error responses as HTML for browsers and htmx, which would otherwise
+/// get the same problem details as API clients.
+///
+/// htmx requests get just the error message to swap into their target, or the error page without
+/// the layout when they target the main content. Other requests that accept HTML get the full
+/// error page.
+pub async fn negotiate_error_response(
+    State(config): State<Config>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let headers = request.headers();
+    let hx_request = headers
+        .typed_get::<HXRequest>()
+        .is_some_and(|hx_request| hx_request.is_true());
+    let hx_target = headers.typed_get::<HXTarget>();
+    let accept = headers.typed_get::<Accept>();
+    let user = request
+        .extensions()
+        .get::<AuthSession>()
+        .and_then(|auth| auth.user.clone());
+
+    let response = next.run(request).await;
+    let Some(details) = response.extensions().get::<ErrorDetails>().cloned() else {
+        return response;
+    };
+    if !hx_request && accept != Some(Accept::TextHtml) {
+        return response;
+    }
+
+    let targets_main_content = hx_target
+        .as_ref()
+        .is_some_and(|hx_target| hx_target.target == "main-content");
+    let mut response = if hx_request && !targets_main_content {
+        error_message(&details).into_response()
+    } else {
+        Layout {
+            title: config.title,
+            user,
+            ..Default::default()
+        }
+        .with_subtitle("error")
+        .targeted(hx_target.map(TypedHeader))
+        .render(error_page(&details))
+    };
+    *response.status_mut() = details.status;
+    response
+}
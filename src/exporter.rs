@@ -0,0 +1,148 @@
+use chrono::Utc;
+use opml::{Head, Outline, OPML};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::Result;
+use crate::models::subscription::{SubscribedFeed, Subscription};
+
+/// Generates an OPML 2.0 document of all the feeds the user is subscribed to.
+pub async fn export_opml(
+    db: impl Executor<'_, Database = Postgres>,
+    user_id: Uuid,
+) -> Result<String> {
+    let feeds = Subscription::get_all_feeds(db, user_id).await?;
+    Ok(feeds_opml(feeds).to_string()?)
+}
+
+/// Builds the OPML document of the feeds.
+///
+/// Feeds in a folder are nested in an outline for each part of the folder name, so that the
+/// nesting read by the `Importer` is restored when the document is imported again.
+fn feeds_opml(feeds: Vec<SubscribedFeed>) -> OPML {
+    let mut document = OPML {
+        head: Some(Head {
+            title: Some("crawlnicle subscriptions".to_string()),
+            date_created: Some(Utc::now().to_rfc2822()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    for feed in feeds {
+        let outlines = match &feed.folder {
+            Some(folder) => {
+                let path: Vec<&str> = folder.split(" / ").collect();
+                folder_outlines(&mut document.body.outlines, &path)
+            }
+            None => &mut document.body.outlines,
+        };
+        outlines.push(feed_outline(feed));
+    }
+    document
+}
+
+/// Returns the children of the folder outline at the given path, creating any missing outlines.
+fn folder_outlines<'a>(outlines: &'a mut Vec<Outline>, path: &[&str]) -> &'a mut Vec<Outline> {
+    let Some((name, rest)) = path.split_first() else {
+        return outlines;
+    };
+    let index = match outlines
+        .iter()
+        .position(|outline| outline.xml_url.is_none() && outline.text == *name)
+    {
+        Some(index) => index,
+        None => {
+            outlines.push(Outline {
+                text: name.to_string(),
+                title: Some(name.to_string()),
+                ..Default::default()
+            });
+            outlines.len() - 1
+        }
+    };
+    folder_outlines(&mut outlines[index].outlines, rest)
+}
+
+fn feed_outline(feed: SubscribedFeed) -> Outline {
+    Outline {
+        text: feed.title.clone().unwrap_or_else(|| feed.url.clone()),
+        title: feed.title,
+        r#type: Some("rss".to_string()),
+        xml_url: Some(feed.url),
+        html_url: feed.site_url,
+        description: feed.description,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(url: &str, site_url: &str, folder: Option<&str>) -> SubscribedFeed {
+        SubscribedFeed {
+            feed_id: Uuid::new_v4(),
+            title: Some(url.to_string()),
+            url: url.to_string(),
+            site_url: Some(site_url.to_string()),
+            description: None,
+            folder: folder.map(String::from),
+        }
+    }
+
+    /// The `xmlUrl`, `htmlUrl` and enclosing outline names of every feed outline, depth first.
+    fn feed_outlines(
+        outlines: &[Outline],
+        folder: Option<&str>,
+    ) -> Vec<(String, Option<String>, Option<String>)> {
+        let mut feeds = Vec::new();
+        for outline in outlines {
+            if let Some(xml_url) = &outline.xml_url {
+                feeds.push((
+                    xml_url.clone(),
+                    outline.html_url.clone(),
+                    folder.map(String::from),
+                ));
+            } else {
+                let folder = match folder {
+                    Some(parent) => format!("{} / {}", parent, outline.text),
+                    None => outline.text.clone(),
+                };
+                feeds.extend(feed_outlines(&outline.outlines, Some(&folder)));
+            }
+        }
+        feeds
+    }
+
+    #[test]
+    fn round_trips_through_import() {
+        let feeds = vec![
+            feed("https://a.com/feed.xml", "https://a.com/", None),
+            feed("https://b.com/feed.xml", "https://b.com/", Some("News")),
+            feed(
+                "https://c.com/feed.xml",
+                "https://c.com/",
+                Some("News / Tech"),
+            ),
+            feed(
+                "https://d.com/feed.xml",
+                "https://d.com/",
+                Some("News / Tech"),
+            ),
+        ];
+        let xml = feeds_opml(feeds).to_string().unwrap();
+        let document = OPML::from_str(&xml).unwrap();
+
+        let site = |name: &str| Some(format!("https://{}.com/", name));
+        let feed_url = |name: &str| format!("https://{}.com/feed.xml", name);
+        assert_eq!(
+            feed_outlines(&document.body.outlines, None),
+            vec![
+                (feed_url("a"), site("a"), None),
+                (feed_url("b"), site("b"), Some("News".to_string())),
+                (feed_url("c"), site("c"), Some("News / Tech".to_string())),
+                (feed_url("d"), site("d"), Some("News / Tech".to_string())),
+            ]
+        );
+    }
+}
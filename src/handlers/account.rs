@@ -1,15 +1,222 @@
-use axum::response::IntoResponse;
+use axum::extract::{Path, State};
+use axum::response::{Redirect, Response};
+use axum::Form;
+use axum_extra::TypedHeader;
+use maud::html;
+use serde::Deserialize;
+use sqlx::PgPool;
+use validator::Validate;
 
 use crate::auth::AuthSession;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::htmx::HXTarget;
+use crate::models::api_token::{ApiToken, ApiTokenScope, CreateApiToken};
+use crate::models::fever::FeverApiKey;
+use crate::models::greader_token::GReaderToken;
+use crate::partials::layout::Layout;
+use crate::partials::time::{date_time, relative_time};
+use crate::uuid::Base62Uuid;
 
-pub async fn get(auth: AuthSession) -> impl IntoResponse {
-    match auth.user {
-        Some(user) => {
-            format!(
-                "Logged in as: {}",
-                user.name.unwrap_or_else(|| "No name".to_string())
-            )
-        }
-        None => "Not logged in".to_string(),
-    }
+pub async fn get(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+    auth: AuthSession,
+    hx_target: Option<TypedHeader<HXTarget>>,
+    layout: Layout,
+) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let has_fever_api_key = FeverApiKey::exists(&pool, user.user_id).await?;
+    let has_greader_tokens = GReaderToken::exists(&pool, user.user_id).await?;
+    let api_tokens = ApiToken::get_all(&pool, user.user_id).await?;
+    let fever_url = config
+        .public_url
+        .join("fever/")
+        .map_err(anyhow::Error::from)?;
+    Ok(layout
+        .with_subtitle("account")
+        .targeted(hx_target)
+        .render(html! {
+            header { h2 class="mb-4 text-2xl font-medium" { "Account" } }
+            p {
+                "Logged in as: "
+                (user.name.unwrap_or_else(|| "No name".to_string()))
+                " (" (user.email) ")"
+            }
+            section class="mt-6 flex flex-col gap-4 max-w-md" {
+                h3 class="text-xl font-medium" { "Fever API" }
+                p {
+                    "Mobile apps that support the Fever API can connect to "
+                    code { (fever_url) }
+                    " with your email and a password set here (not your account password)."
+                }
+                form action="/account/fever" method="post" class="flex flex-row gap-4 items-end" {
+                    div class="grow" {
+                        label for="fever-password" class="text-sm font-medium text-gray-700" {
+                            @if has_fever_api_key { "New Fever password" } @else { "Fever password" }
+                        }
+                        input
+                            type="password"
+                            id="fever-password"
+                            name="password"
+                            minlength="8"
+                            maxlength="255"
+                            required
+                            class="w-full mt-1 p-2 bg-gray-50 border border-gray-300 shadow-sm rounded-md focus:ring focus:ring-blue-500 focus:border-blue-500 focus:ring-opacity-50";
+                    }
+                    button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Save" }
+                }
+                @if has_fever_api_key {
+                    form action="/account/fever/delete" method="post" {
+                        button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "❌ Disable Fever API" }
+                    }
+                }
+            }
+            section class="mt-6 flex flex-col gap-4 max-w-md" {
+                h3 class="text-xl font-medium" { "Google Reader API" }
+                p {
+                    "Apps that support the Google Reader API (like NetNewsWire or Fluent Reader) can connect to "
+                    code { (config.public_url) }
+                    " with your email and account password."
+                }
+                @if has_greader_tokens {
+                    form action="/account/greader/delete" method="post" {
+                        button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "❌ Sign out Google Reader apps" }
+                    }
+                }
+            }
+            section class="mt-6 flex flex-col gap-4 max-w-md" {
+                h3 class="text-xl font-medium" { "API tokens" }
+                p {
+                    "Scripts can call the JSON API under "
+                    code { "/api/v1" }
+                    " with a token sent as "
+                    code { "Authorization: Bearer <token>" }
+                    ". See the "
+                    a href="/api/v1/docs" { "API docs" }
+                    "."
+                }
+                @if !api_tokens.is_empty() {
+                    ul class="flex flex-col gap-2" {
+                        @for api_token in api_tokens {
+                            li class="flex flex-row gap-4 items-center justify-between" {
+                                div {
+                                    div class="font-medium" { (api_token.name) }
+                                    div class="text-sm text-gray-600" {
+                                        @match api_token.scope {
+                                            ApiTokenScope::Read => "Read-only",
+                                            ApiTokenScope::ReadWrite => "Read and write",
+                                        }
+                                        " · created " (date_time(api_token.created_at))
+                                        " · "
+                                        @if let Some(last_used_at) = api_token.last_used_at {
+                                            "last used " (relative_time(last_used_at))
+                                        } @else {
+                                            "never used"
+                                        }
+                                    }
+                                }
+                                form action=(format!("/account/tokens/{}/delete", Base62Uuid::from(api_token.api_token_id))) method="post" {
+                                    button type="submit" class="py-1 px-2 font-medium rounded-md border border-gray-200" { "❌ Revoke" }
+                                }
+                            }
+                        }
+                    }
+                }
+                form action="/account/tokens" method="post" class="flex flex-row gap-4 items-end" {
+                    div class="grow" {
+                        label for="api-token-name" class="text-sm font-medium text-gray-700" { "Token name" }
+                        input
+                            type="text"
+                            id="api-token-name"
+                            name="name"
+                            maxlength="255"
+                            required
+                            class="w-full mt-1 p-2 bg-gray-50 border border-gray-300 shadow-sm rounded-md focus:ring focus:ring-blue-500 focus:border-blue-500 focus:ring-opacity-50";
+                    }
+                    div {
+                        label for="api-token-scope" class="text-sm font-medium text-gray-700" { "Access" }
+                        select
+                            id="api-token-scope"
+                            name="scope"
+                            class="w-full mt-1 p-2 bg-gray-50 border border-gray-300 shadow-sm rounded-md focus:ring focus:ring-blue-500 focus:border-blue-500 focus:ring-opacity-50"
+                        {
+                            option value="read_write" { "Read and write" }
+                            option value="read" { "Read-only" }
+                        }
+                    }
+                    button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Create" }
+                }
+            }
+        }))
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct FeverPassword {
+    #[validate(length(min = 8, max = 255))]
+    password: String,
+}
+
+pub async fn fever(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Form(fever_password): Form<FeverPassword>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    fever_password.validate()?;
+    FeverApiKey::upsert(&pool, &user, &fever_password.password).await?;
+    Ok(Redirect::to("/account"))
+}
+
+pub async fn delete_fever(State(pool): State<PgPool>, auth: AuthSession) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    FeverApiKey::delete(&pool, user.user_id).await?;
+    Ok(Redirect::to("/account"))
+}
+
+/// Revokes the Google Reader API tokens of the user, so their apps have to log in again.
+pub async fn delete_greader_tokens(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    GReaderToken::delete_all(&pool, user.user_id).await?;
+    Ok(Redirect::to("/account"))
+}
+
+/// Creates an API token and shows it, the only time it can be seen.
+pub async fn create_api_token(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    hx_target: Option<TypedHeader<HXTarget>>,
+    layout: Layout,
+    Form(payload): Form<CreateApiToken>,
+) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let (api_token, token) = ApiToken::create(&pool, user.user_id, payload).await?;
+    Ok(layout
+        .with_subtitle("account")
+        .targeted(hx_target)
+        .render(html! {
+            header { h2 class="mb-4 text-2xl font-medium" { "API token created" } }
+            section class="flex flex-col gap-4 max-w-md" {
+                p {
+                    "Copy the token "
+                    span class="font-medium" { (api_token.name) }
+                    " now, it will not be shown again:"
+                }
+                code class="p-2 bg-gray-50 border border-gray-300 rounded-md break-all" { (token) }
+                a href="/account" { "Back to account" }
+            }
+        }))
+}
+
+pub async fn delete_api_token(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    ApiToken::delete(&pool, user.user_id, id.as_uuid()).await?;
+    Ok(Redirect::to("/account"))
 }
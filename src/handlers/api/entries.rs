@@ -1,27 +1,73 @@
 use axum::extract::Query;
 use axum::extract::State;
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 use axum_extra::TypedHeader;
 use sqlx::PgPool;
 
 use crate::api_response::ApiResponse;
+use crate::auth::AuthSession;
 use crate::error::Error;
 use crate::headers::Accept;
-use crate::models::entry::{Entry, GetEntriesOptions};
+use crate::models::entry::{Entry, EntrySearchResult, GetEntriesOptions};
+use crate::models::entry_read::{EntryRead, MarkReadBefore};
 use crate::partials::entry_list::entry_list;
+use crate::partials::search_result_list::search_result_list;
 
+/// Lists the entries of the feeds the user is subscribed to, latest first, or searches them.
+#[utoipa::path(
+    get,
+    path = "/api/v1/entries",
+    params(GetEntriesOptions),
+    responses(
+        (status = 200, description = "The entries, as HTML unless `Accept: application/json`. Search results (with `q`) are `EntrySearchResult`s", body = [Entry]),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
 pub async fn get(
-    Query(options): Query<GetEntriesOptions>,
+    Query(mut options): Query<GetEntriesOptions>,
     accept: Option<TypedHeader<Accept>>,
     State(pool): State<PgPool>,
-) -> Result<impl IntoResponse, impl IntoResponse> {
-    let entries = Entry::get_all(&pool, &options).await.map_err(Error::from)?;
-    if let Some(TypedHeader(accept)) = accept {
-        if accept == Accept::ApplicationJson {
-            return Ok::<ApiResponse<Vec<Entry>>, Error>(ApiResponse::Json(entries));
+    auth: AuthSession,
+) -> Result<Response, Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    options.user_id = Some(user.user_id);
+    let json = matches!(accept, Some(TypedHeader(Accept::ApplicationJson)));
+    if options.q.as_deref().is_some_and(|q| !q.trim().is_empty()) {
+        let results = Entry::search(&pool, &options).await?;
+        if json {
+            return Ok(ApiResponse::<Vec<EntrySearchResult>>::Json(results).into_response());
         }
+        return Ok(ApiResponse::<()>::Html(
+            search_result_list(results, &options, false).into_string(),
+        )
+        .into_response());
     }
-    Ok(ApiResponse::Html(
-        entry_list(entries, &options, false).into_string(),
-    ))
+    let entries = Entry::get_all(&pool, &options).await?;
+    if json {
+        return Ok(ApiResponse::<Vec<Entry>>::Json(entries).into_response());
+    }
+    Ok(ApiResponse::<()>::Html(entry_list(entries, &options, false).into_string()).into_response())
+}
+
+/// Marks all entries published before the time as read.
+#[utoipa::path(
+    post,
+    path = "/api/v1/entries/read",
+    request_body = MarkReadBefore,
+    responses(
+        (status = 200, description = "Marked the entries as read"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn read(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Json(payload): Json<MarkReadBefore>,
+) -> Result<(), Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    EntryRead::create_all_before(&pool, user.user_id, payload.published_before).await?;
+    Ok(())
 }
@@ -3,21 +3,206 @@ use axum::{
     Json,
 };
 use sqlx::PgPool;
+use validator::Validate;
 
+use crate::auth::AuthSession;
 use crate::error::Error;
-use crate::models::entry::{CreateEntry, Entry};
+use crate::models::entry::{CreateEntry, Entry, UpdateEntry};
+use crate::models::entry_read::EntryRead;
+use crate::models::entry_star::EntryStar;
+use crate::models::subscription::Subscription;
+use crate::models::user::User;
 use crate::uuid::Base62Uuid;
 
+/// Gets an entry of a feed the user is subscribed to, or that the user starred.
+#[utoipa::path(
+    get,
+    path = "/api/v1/entry/{id}",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    responses(
+        (status = 200, body = Entry),
+        (status = 404, description = "Entry not found"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
 pub async fn get(
     State(pool): State<PgPool>,
+    auth: AuthSession,
     Path(id): Path<Base62Uuid>,
 ) -> Result<Json<Entry>, Error> {
-    Ok(Json(Entry::get(&pool, id.as_uuid()).await?))
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    Ok(Json(entry))
 }
 
+/// Adds an entry to a feed the user is subscribed to.
+#[utoipa::path(
+    post,
+    path = "/api/v1/entry",
+    request_body = CreateEntry,
+    responses(
+        (status = 200, body = Entry),
+        (status = 422, description = "Invalid entry, or not subscribed to its feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
 pub async fn post(
     State(pool): State<PgPool>,
-    Json(payload): Json<CreateEntry>,
+    auth: AuthSession,
+    Json(mut payload): Json<CreateEntry>,
 ) -> Result<Json<Entry>, Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, payload.feed_id)
+        .await
+        .map_err(|error| match error {
+            Error::NotFoundUuid(..) => Error::RelationNotFound("feed"),
+            error => error,
+        })?;
+    payload.created_by = Some(user.user_id);
     Ok(Json(Entry::create(&pool, payload).await?))
 }
+
+/// Updates the fields of the entry included in the payload. Entries are shared by all subscribers
+/// of their feed, so only entries the user added themselves can be updated, not crawled ones.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/entry/{id}",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    request_body = UpdateEntry,
+    responses(
+        (status = 200, body = Entry),
+        (status = 403, description = "Entry was not added by the user"),
+        (status = 404, description = "Entry not found"),
+        (status = 422, description = "Invalid update"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn patch(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+    Json(payload): Json<UpdateEntry>,
+) -> Result<Json<Entry>, Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    payload.validate()?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    check_created_by(&entry, &user)?;
+    Ok(Json(Entry::update(&pool, payload.apply(entry)).await?))
+}
+
+/// Deletes the entry for all subscribers of its feed. Only entries the user added themselves can be
+/// deleted, not crawled ones.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/entry/{id}",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    responses(
+        (status = 200, description = "Deleted the entry"),
+        (status = 403, description = "Entry was not added by the user"),
+        (status = 404, description = "Entry not found"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn delete(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<(), Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    check_created_by(&entry, &user)?;
+    Entry::delete(&pool, entry.entry_id).await
+}
+
+/// Entries crawled from a feed are shared by all of its subscribers, so users can only change the
+/// entries they added themselves.
+fn check_created_by(entry: &Entry, user: &User) -> Result<(), Error> {
+    if entry.created_by != Some(user.user_id) {
+        return Err(Error::Forbidden("only entries you added can be changed"));
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/entry/{id}/read",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    responses(
+        (status = 200, description = "Marked the entry as read"),
+        (status = 404, description = "Entry not found"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn read(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<(), Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    EntryRead::create(&pool, user.user_id, entry.entry_id).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/entry/{id}/unread",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    responses(
+        (status = 200, description = "Marked the entry as unread"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn unread(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<(), Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    EntryRead::delete(&pool, user.user_id, id.as_uuid()).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/entry/{id}/star",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    responses(
+        (status = 200, description = "Starred the entry"),
+        (status = 404, description = "Entry not found"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn star(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<(), Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    EntryStar::create(&pool, user.user_id, entry.entry_id).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/entry/{id}/unstar",
+    params(("id" = String, Path, description = "Entry id, base62-encoded")),
+    responses(
+        (status = 200, description = "Unstarred the entry"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn unstar(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<(), Error> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    EntryStar::delete(&pool, user.user_id, id.as_uuid()).await
+}
@@ -1,24 +1,296 @@
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
+use axum_extra::TypedHeader;
+use reqwest::Client;
 use sqlx::PgPool;
+use tracing::error;
 
+use crate::actors::crawl_scheduler::{
+    CrawlSchedulerError, CrawlSchedulerHandle, CrawlSchedulerHandleMessage,
+};
+use crate::api_response::ApiResponse;
+use crate::auth::AuthSession;
+use crate::config::Config;
 use crate::error::{Error, Result};
-use crate::models::feed::{CreateFeed, Feed};
+use crate::handlers::api::entries;
+use crate::headers::Accept;
+use crate::models::crawl_log::{CrawlLog, GetCrawlLogsOptions};
+use crate::models::entry::GetEntriesOptions;
+use crate::models::entry_read::EntryRead;
+use crate::models::feed::{CreateFeed, Feed, UpdateFeed};
+use crate::models::folder::{CreateFolder, Folder};
+use crate::models::subscription::{SetFolder, Subscription};
+use crate::partials::crawl_log_table::crawl_log_table;
 use crate::uuid::Base62Uuid;
+use crate::websub;
 
-pub async fn get(State(pool): State<PgPool>, Path(id): Path<Base62Uuid>) -> Result<Json<Feed>> {
+/// Gets a feed the user is subscribed to.
+#[utoipa::path(
+    get,
+    path = "/api/v1/feed/{id}",
+    params(("id" = String, Path, description = "Feed id, base62-encoded")),
+    responses(
+        (status = 200, body = Feed),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn get(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Json<Feed>> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
     Ok(Json(Feed::get(&pool, id.as_uuid()).await?))
 }
 
+/// Subscribes to the feed with the url, adding it if no one was subscribed to it yet.
+#[utoipa::path(
+    post,
+    path = "/api/v1/feed",
+    request_body = CreateFeed,
+    responses(
+        (status = 200, description = "The feed, which the user is now subscribed to", body = Feed),
+        (status = 409, description = "Already subscribed to the feed"),
+        (status = 422, description = "Invalid feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
 pub async fn post(
     State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    auth: AuthSession,
     Json(payload): Json<CreateFeed>,
 ) -> Result<Json<Feed>, Error> {
-    Ok(Json(Feed::create(&pool, payload).await?))
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let (feed, addition) = Feed::create_or_restore(&pool, payload).await?;
+    Subscription::create(&pool, user.user_id, feed.feed_id).await?;
+    if addition.needs_schedule() {
+        let _ = crawl_scheduler.schedule(feed.feed_id).await;
+    }
+    Ok(Json(feed))
+}
+
+/// Updates the fields of the feed included in the payload. Changes to the url or crawl interval
+/// reschedule the feed. Only admins can update feeds, since they are shared by all subscribers.
+#[utoipa::path(
+    patch,
+    path = "/api/v1/feed/{id}",
+    params(("id" = String, Path, description = "Feed id, base62-encoded")),
+    request_body = UpdateFeed,
+    responses(
+        (status = 200, body = Feed),
+        (status = 403, description = "Not an admin"),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 422, description = "Invalid update"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn patch(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+    Json(payload): Json<UpdateFeed>,
+) -> Result<Json<Feed>> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if !user.admin {
+        return Err(Error::Forbidden("only admins can edit feeds"));
+    }
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let reschedule = payload.affects_schedule();
+    let feed = Feed::update(&pool, id.as_uuid(), payload).await?;
+    if reschedule {
+        let _ = crawl_scheduler.reschedule(feed.feed_id).await;
+    }
+    Ok(Json(feed))
+}
+
+/// Unsubscribes from the feed. The feed is deleted once no one is subscribed to it anymore.
+#[utoipa::path(
+    delete,
+    path = "/api/v1/feed/{id}",
+    params(("id" = String, Path, description = "Feed id, base62-encoded")),
+    responses(
+        (status = 200, description = "Unsubscribed from the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn delete(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    State(client): State<Client>,
+    State(config): State<Config>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<()> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if Subscription::delete(&pool, user.user_id, id.as_uuid()).await?
+        && Feed::delete_if_unsubscribed(&pool, id.as_uuid()).await?
+    {
+        let _ = crawl_scheduler.unschedule(id.as_uuid()).await;
+        if let Err(err) =
+            websub::unsubscribe(&pool, &client, &config.public_url, id.as_uuid()).await
+        {
+            error!("failed to unsubscribe from websub hub: {}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Marks all entries of the feed as read.
+#[utoipa::path(
+    post,
+    path = "/api/v1/feed/{id}/read",
+    params(("id" = String, Path, description = "Feed id, base62-encoded")),
+    responses(
+        (status = 200, description = "Marked all entries of the feed as read"),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn read(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<()> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    EntryRead::create_all_in_feed(&pool, user.user_id, id.as_uuid()).await?;
+    Ok(())
 }
 
-pub async fn delete(State(pool): State<PgPool>, Path(id): Path<Base62Uuid>) -> Result<()> {
-    Feed::delete(&pool, id.as_uuid()).await
+/// Moves the feed into a folder of the user.
+#[utoipa::path(
+    post,
+    path = "/api/v1/feed/{id}/folder",
+    params(("id" = String, Path, description = "Feed id, base62-encoded")),
+    request_body = SetFolder,
+    responses(
+        (status = 200, body = Subscription),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn folder(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+    Json(payload): Json<SetFolder>,
+) -> Result<Json<Subscription>> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let folder_id = match payload.folder {
+        Some(name) => Some(
+            Folder::get_or_create(&pool, user.user_id, CreateFolder { name })
+                .await?
+                .folder_id,
+        ),
+        None => None,
+    };
+    Ok(Json(
+        Subscription::set_folder(&pool, user.user_id, id.as_uuid(), folder_id).await?,
+    ))
+}
+
+/// The crawl history of the feed, latest first.
+#[utoipa::path(
+    get,
+    path = "/api/v1/feed/{id}/crawls",
+    params(("id" = String, Path, description = "Feed id, base62-encoded"), GetCrawlLogsOptions),
+    responses(
+        (status = 200, description = "The crawl logs, as HTML unless `Accept: application/json`", body = [CrawlLog]),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn crawls(
+    Query(options): Query<GetCrawlLogsOptions>,
+    accept: Option<TypedHeader<Accept>>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<impl IntoResponse> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let crawl_logs = CrawlLog::get_all(&pool, id.as_uuid(), &options).await?;
+    if let Some(TypedHeader(accept)) = accept {
+        if accept == Accept::ApplicationJson {
+            return Ok(ApiResponse::Json(crawl_logs));
+        }
+    }
+    Ok(ApiResponse::Html(
+        crawl_log_table(&crawl_logs).into_string(),
+    ))
+}
+
+/// The entries of the feed, latest first. Takes the same options as `/api/v1/entries`.
+#[utoipa::path(
+    get,
+    path = "/api/v1/feed/{id}/entries",
+    params(("id" = String, Path, description = "Feed id, base62-encoded"), GetEntriesOptions),
+    responses(
+        (status = 200, description = "The entries, as HTML unless `Accept: application/json`", body = [Entry]),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "entries"
+)]
+pub async fn entries(
+    Query(mut options): Query<GetEntriesOptions>,
+    accept: Option<TypedHeader<Accept>>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Response> {
+    let user = auth.user.as_ref().ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    options.feed_id = Some(id.as_uuid());
+    entries::get(Query(options), accept, State(pool), auth).await
+}
+
+/// Queues a crawl of the feed without waiting for its crawl interval. The crawl runs in the
+/// background, its result shows up in `/api/v1/feed/:id/crawls`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/feed/{id}/crawl",
+    params(("id" = String, Path, description = "Feed id, base62-encoded")),
+    responses(
+        (status = 202, description = "The crawl was queued"),
+        (status = 400, description = "The feed is paused or gone"),
+        (status = 404, description = "Not subscribed to the feed"),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
+pub async fn crawl(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<StatusCode> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let mut receiver = crawl_scheduler.crawl(id.as_uuid()).await;
+    match receiver.recv().await {
+        Ok(CrawlSchedulerHandleMessage::Crawl(Err(CrawlSchedulerError::InactiveFeed))) => {
+            Err(Error::BadRequest("feed is not active"))
+        }
+        Ok(CrawlSchedulerHandleMessage::Crawl(Err(err))) => {
+            error!("failed to queue crawl: {}", err);
+            Err(Error::InternalServerError)
+        }
+        _ => Ok(StatusCode::ACCEPTED),
+    }
 }
@@ -5,20 +5,38 @@ use axum_extra::TypedHeader;
 use sqlx::PgPool;
 
 use crate::api_response::ApiResponse;
+use crate::auth::AuthSession;
 use crate::error::Error;
 use crate::headers::Accept;
-use crate::models::feed::{Feed, GetFeedsOptions};
+use crate::models::feed::{Feed, FeedsPage, GetFeedsOptions};
 use crate::partials::feed_list::feed_list;
 
+/// Lists the feeds the user is subscribed to, a page at a time.
+#[utoipa::path(
+    get,
+    path = "/api/v1/feeds",
+    params(GetFeedsOptions),
+    responses(
+        (status = 200, description = "A page of the feeds the user is subscribed to, as HTML unless `Accept: application/json`", body = FeedsPage),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "feeds"
+)]
 pub async fn get(
-    Query(options): Query<GetFeedsOptions>,
+    Query(mut options): Query<GetFeedsOptions>,
     accept: Option<TypedHeader<Accept>>,
     State(pool): State<PgPool>,
+    auth: AuthSession,
 ) -> Result<impl IntoResponse, impl IntoResponse> {
-    let feeds = Feed::get_all(&pool, &options).await.map_err(Error::from)?;
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    options.user_id = Some(user.user_id);
+    let feeds = Feed::get_all(&pool, &options).await?;
     if let Some(TypedHeader(accept)) = accept {
         if accept == Accept::ApplicationJson {
-            return Ok::<ApiResponse<Vec<Feed>>, Error>(ApiResponse::Json(feeds));
+            return Ok::<ApiResponse<FeedsPage>, Error>(ApiResponse::Json(FeedsPage {
+                next_cursor: options.next_cursor(&feeds),
+                feeds,
+            }));
         }
     }
     Ok(ApiResponse::Html(
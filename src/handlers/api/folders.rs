@@ -0,0 +1,21 @@
+use axum::{extract::State, Json};
+use sqlx::PgPool;
+
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
+use crate::models::folder::Folder;
+
+/// Lists the folders of the user.
+#[utoipa::path(
+    get,
+    path = "/api/v1/folders",
+    responses(
+        (status = 200, body = [Folder]),
+        (status = 401, description = "Not logged in and no valid API token"),
+    ),
+    tag = "folders"
+)]
+pub async fn get(State(pool): State<PgPool>, auth: AuthSession) -> Result<Json<Vec<Folder>>> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Ok(Json(Folder::get_all(&pool, user.user_id).await?))
+}
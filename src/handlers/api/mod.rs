@@ -1,4 +1,6 @@
+pub mod entries;
+pub mod entry;
 pub mod feed;
 pub mod feeds;
-pub mod entry;
-pub mod entries;
+pub mod folders;
+pub mod openapi;
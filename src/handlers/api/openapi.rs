@@ -0,0 +1,130 @@
+use axum::Json;
+use maud::{html, Markup, DOCTYPE};
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::handlers::api;
+use crate::models::crawl_log::CrawlLog;
+use crate::models::entry::{CreateEntry, Entry, EntrySearchResult, UpdateEntry};
+use crate::models::entry_read::MarkReadBefore;
+use crate::models::feed::{CreateFeed, Feed, FeedType, FeedsPage, GetFeedsSort, UpdateFeed};
+use crate::models::folder::Folder;
+use crate::models::subscription::{SetFolder, Subscription};
+
+/// The OpenAPI document of the JSON API, generated from the `utoipa::path` attributes of the
+/// handlers in `handlers::api` and the `ToSchema` models they take and return.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "crawlnicle API",
+        description = "Errors are RFC 7807 problem details (`application/problem+json`) whose \
+            `type` is `urn:crawlnicle:error:` followed by a stable code, e.g. `not-found`, \
+            `unauthorized`, `forbidden`, `conflict`, `bad-request`, `validation`, or `internal`."
+    ),
+    paths(
+        api::feeds::get,
+        api::feed::get,
+        api::feed::post,
+        api::feed::patch,
+        api::feed::delete,
+        api::feed::read,
+        api::feed::folder,
+        api::feed::crawls,
+        api::feed::crawl,
+        api::feed::entries,
+        api::folders::get,
+        api::entries::get,
+        api::entries::read,
+        api::entry::get,
+        api::entry::post,
+        api::entry::patch,
+        api::entry::delete,
+        api::entry::read,
+        api::entry::unread,
+        api::entry::star,
+        api::entry::unstar,
+    ),
+    components(schemas(
+        CrawlLog,
+        CreateEntry,
+        CreateFeed,
+        Entry,
+        EntrySearchResult,
+        Feed,
+        FeedType,
+        FeedsPage,
+        Folder,
+        GetFeedsSort,
+        MarkReadBefore,
+        SetFolder,
+        Subscription,
+        UpdateEntry,
+        UpdateFeed,
+    )),
+    modifiers(&ApiTokenSecurity),
+    security(("api_token" = [])),
+    tags(
+        (name = "feeds", description = "Feeds the user is subscribed to"),
+        (name = "entries", description = "Entries of the feeds the user is subscribed to"),
+        (name = "folders", description = "Folders that group the user's feeds"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Adds the personal access tokens created on the account page as a security scheme.
+struct ApiTokenSecurity;
+
+impl Modify for ApiTokenSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "api_token",
+                SecurityScheme::Http(
+                    HttpBuilder::new()
+                        .scheme(HttpAuthScheme::Bearer)
+                        .description(Some("A personal access token from the account page"))
+                        .build(),
+                ),
+            );
+        }
+    }
+}
+
+pub async fn get() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Browsable docs of the OpenAPI document, rendered by Redoc.
+///
+/// Redoc is copied into `static/vendor` by the frontend build, rather than loaded from a CDN.
+pub async fn docs() -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang="en" {
+            head {
+                meta charset="utf-8";
+                meta name="viewport" content="width=device-width, initial-scale=1";
+                title { "crawlnicle API" }
+            }
+            body {
+                redoc spec-url="/api/v1/openapi.json" {}
+                script src="/static/vendor/redoc.standalone.js" {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_every_schema_it_references() {
+        let json = ApiDoc::openapi().to_json().unwrap();
+        let schemas = ApiDoc::openapi().components.unwrap().schemas;
+        for reference in json.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(schemas.contains_key(name), "missing schema {}", name);
+        }
+    }
+}
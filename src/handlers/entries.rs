@@ -1,15 +1,32 @@
 use axum::extract::{Query, State};
+use axum::response::Redirect;
+use axum::Form;
 use maud::Markup;
 use sqlx::PgPool;
 
-use crate::error::Result;
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
 use crate::models::entry::{Entry, GetEntriesOptions};
+use crate::models::entry_read::{EntryRead, MarkReadBefore};
 use crate::partials::entry_list::entry_list;
 
 pub async fn get(
-    Query(options): Query<GetEntriesOptions>,
+    Query(mut options): Query<GetEntriesOptions>,
     State(pool): State<PgPool>,
+    auth: AuthSession,
 ) -> Result<Markup> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    options.user_id = Some(user.user_id);
     let entries = Entry::get_all(&pool, &options).await?;
     Ok(entry_list(entries, &options, false))
 }
+
+pub async fn read(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Form(mark_read): Form<MarkReadBefore>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    EntryRead::create_all_before(&pool, user.user_id, mark_read.published_before).await?;
+    Ok(Redirect::to("/"))
+}
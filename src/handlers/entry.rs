@@ -1,15 +1,18 @@
 use std::fs;
 
 use axum::extract::{Path, State};
-use axum::response::Response;
+use axum::response::{Redirect, Response};
 use axum_extra::TypedHeader;
 use maud::{html, PreEscaped};
 use sqlx::PgPool;
 
+use crate::auth::AuthSession;
 use crate::config::Config;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::htmx::HXTarget;
 use crate::models::entry::Entry;
+use crate::models::entry_read::EntryRead;
+use crate::models::entry_star::EntryStar;
 use crate::partials::layout::Layout;
 use crate::partials::time::date_time;
 use crate::uuid::Base62Uuid;
@@ -18,14 +21,26 @@ pub async fn get(
     Path(id): Path<Base62Uuid>,
     State(pool): State<PgPool>,
     State(config): State<Config>,
+    auth: AuthSession,
     hx_target: Option<TypedHeader<HXTarget>>,
     layout: Layout,
 ) -> Result<Response> {
-    let entry = Entry::get(&pool, id.as_uuid()).await?;
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    EntryRead::create(&pool, user.user_id, entry.entry_id).await?;
+    let starred = EntryStar::get(&pool, user.user_id, entry.entry_id)
+        .await?
+        .is_some();
     let content_dir = std::path::Path::new(&config.content_dir);
     let content_path = content_dir.join(format!("{}.html", entry.entry_id));
     let title = entry.title.unwrap_or_else(|| "Untitled Entry".to_string());
     let content = fs::read_to_string(content_path).unwrap_or_else(|_| "No content".to_string());
+    let unread_url = format!("/entry/{}/unread", id);
+    let star_url = if starred {
+        format!("/entry/{}/unstar", id)
+    } else {
+        format!("/entry/{}/star", id)
+    };
     Ok(layout
         .with_subtitle(&title)
         .targeted(hx_target)
@@ -36,13 +51,63 @@ pub async fn get(
                         a href=(entry.url) { (title) }
                     }
                 }
-                div {
+                div class="flex flex-row items-center gap-4" {
                     span class="text-sm text-gray-600" {
                         strong { "Published: " }
                         (date_time(entry.published_at))
                     }
+                    form action=(unread_url) method="post" {
+                        button type="submit" class="py-1 px-2 text-sm font-medium rounded-md border border-gray-200" { "Mark as unread" }
+                    }
+                    form action=(star_url) method="post" {
+                        button type="submit" class="py-1 px-2 text-sm font-medium rounded-md border border-gray-200" {
+                            @if starred { "★ Unstar" } @else { "☆ Star" }
+                        }
+                    }
                 }
                 (PreEscaped(content))
             }
         }))
 }
+
+pub async fn read(
+    Path(id): Path<Base62Uuid>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    EntryRead::create(&pool, user.user_id, entry.entry_id).await?;
+    Ok(Redirect::to("/"))
+}
+
+pub async fn unread(
+    Path(id): Path<Base62Uuid>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    EntryRead::delete(&pool, user.user_id, id.as_uuid()).await?;
+    Ok(Redirect::to("/"))
+}
+
+pub async fn star(
+    Path(id): Path<Base62Uuid>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let entry = Entry::get_for_user(&pool, id.as_uuid(), user.user_id).await?;
+    EntryStar::create(&pool, user.user_id, entry.entry_id).await?;
+    Ok(Redirect::to(&format!("/entry/{}", id)))
+}
+
+pub async fn unstar(
+    Path(id): Path<Base62Uuid>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    EntryStar::delete(&pool, user.user_id, id.as_uuid()).await?;
+    Ok(Redirect::to(&format!("/entry/{}", id)))
+}
@@ -0,0 +1,24 @@
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use sqlx::PgPool;
+
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
+use crate::exporter::export_opml;
+
+pub async fn opml(State(pool): State<PgPool>, auth: AuthSession) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let document = export_opml(&pool, user.user_id).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/x-opml; charset=utf-8"),
+            (
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"crawlnicle.opml\"",
+            ),
+        ],
+        document,
+    )
+        .into_response())
+}
@@ -7,43 +7,91 @@ use axum::response::{IntoResponse, Redirect, Response, Sse};
 use axum::Form;
 use axum_extra::TypedHeader;
 use feed_rs::parser;
-use maud::html;
+use maud::{html, Markup};
+use reqwest::Client;
 use serde::Deserialize;
 use serde_with::{serde_as, NoneAsEmptyString};
 use sqlx::PgPool;
 use tokio_stream::wrappers::BroadcastStream;
 use tokio_stream::StreamExt;
+use tracing::error;
+use url::Url;
 
 use crate::actors::crawl_scheduler::{CrawlSchedulerHandle, CrawlSchedulerHandleMessage};
 use crate::actors::feed_crawler::FeedCrawlerHandleMessage;
+use crate::auth::AuthSession;
+use crate::config::Config;
+use crate::discovery::{discover_feeds, DiscoveredFeed};
+use crate::domain_locks::DomainLocks;
 use crate::error::{Error, Result};
 use crate::htmx::HXTarget;
+use crate::models::crawl_log::{CrawlLog, GetCrawlLogsOptions};
 use crate::models::entry::{Entry, GetEntriesOptions};
-use crate::models::feed::{CreateFeed, Feed};
+use crate::models::entry_read::EntryRead;
+use crate::models::feed::{
+    CreateFeed, Feed, FeedAddition, UpdateFeed, MAX_CRAWL_INTERVAL_MINUTES,
+    MIN_CRAWL_INTERVAL_MINUTES,
+};
+use crate::models::feed_url_alias::FeedUrlAlias;
+use crate::models::folder::{CreateFolder, Folder};
+use crate::models::subscription::{SetFolder, Subscription};
 use crate::partials::add_feed_form::add_feed_form;
+use crate::partials::crawl_log_table::crawl_log_table;
 use crate::partials::entry_link::entry_link;
+use crate::partials::time::relative_time;
 use crate::partials::{entry_list::entry_list, feed_link::feed_link, layout::Layout};
 use crate::state::Crawls;
 use crate::uuid::Base62Uuid;
+use crate::websub;
 
 pub async fn get(
     Path(id): Path<Base62Uuid>,
     State(pool): State<PgPool>,
+    auth: AuthSession,
     hx_target: Option<TypedHeader<HXTarget>>,
     layout: Layout,
 ) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let subscription = Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
     let feed = Feed::get(&pool, id.as_uuid()).await?;
+    let folders = Folder::get_all(&pool, user.user_id).await?;
+    let folder_name = folders
+        .iter()
+        .find(|folder| Some(folder.folder_id) == subscription.folder_id)
+        .map(|folder| folder.name.clone());
     let options = GetEntriesOptions {
         feed_id: Some(feed.feed_id),
+        user_id: Some(user.user_id),
         ..Default::default()
     };
-    let title = feed.title.unwrap_or_else(|| "Untitled Feed".to_string());
+    let title = feed
+        .title
+        .clone()
+        .unwrap_or_else(|| "Untitled Feed".to_string());
     let entries = Entry::get_all(&pool, &options).await?;
+    let crawl_logs =
+        CrawlLog::get_all(&pool, feed.feed_id, &GetCrawlLogsOptions::default()).await?;
+    let aliases = FeedUrlAlias::get_all(&pool, feed.feed_id).await?;
+    let folder_url = format!("/feed/{}/folder", id);
+    let edit_url = format!("/feed/{}/edit", id);
+    let read_url = format!("/feed/{}/read", id);
     let delete_url = format!("/feed/{}/delete", id);
+    let pause_url = format!("/feed/{}/pause", id);
+    let resume_url = format!("/feed/{}/resume", id);
     Ok(layout.with_subtitle(&title).targeted(hx_target).render(html! {
         header class="mb-4 flex flex-row items-center gap-4" {
             h2 class="text-2xl font-medium" { (title) }
-            button class="py-2 px-4 font-medium rounded-md border border-gray-200" { "✏️ Edit feed" }
+            @if user.admin {
+                a href=(edit_url) class="py-2 px-4 font-medium rounded-md border border-gray-200" { "✏️ Edit feed" }
+            }
+            @if user.admin && feed.is_active() {
+                form action=(pause_url) method="post" {
+                    button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "⏸️ Pause crawling" }
+                }
+            }
+            form action=(read_url) method="post" {
+                button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "✔️ Mark all as read" }
+            }
             form action=(delete_url) method="post" {
                 button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "❌ Remove feed" }
             }
@@ -51,6 +99,65 @@ pub async fn get(
         @if let Some(description) = feed.description {
             p class="mb-4" { (description) }
         }
+        @if let Some(inactive_at) = feed.inactive_at {
+            div class="mb-4 flex flex-row items-center gap-4" {
+                p class="text-red-600" {
+                    "This feed was removed by its website (410 Gone) " (relative_time(inactive_at))
+                    " and is no longer crawled."
+                }
+                @if user.admin {
+                    (resume_button(&resume_url))
+                }
+            }
+        } @else if let Some(paused_at) = feed.paused_at {
+            div class="mb-4 flex flex-row items-center gap-4" {
+                p class="text-red-600" {
+                    "Crawling this feed was paused " (relative_time(paused_at))
+                    @if let Some(failing_since) = feed.failing_since {
+                        " because every crawl since " (relative_time(failing_since)) " has failed"
+                    }
+                    @if let Some(last_crawl_error) = &feed.last_crawl_error {
+                        " (" (last_crawl_error) ")"
+                    }
+                    "."
+                }
+                @if user.admin {
+                    (resume_button(&resume_url))
+                }
+            }
+        } @else if feed.consecutive_failures > 0 {
+            p class="mb-4 text-red-600" {
+                "The last " (feed.consecutive_failures) " crawls of this feed failed, so it is crawled less often until a crawl succeeds."
+            }
+        }
+        @for alias in &aliases {
+            p class="mb-4 text-gray-600" {
+                "Moved permanently from " (alias.url) " " (relative_time(alias.created_at)) "."
+            }
+        }
+        form action=(folder_url) method="post" class="flex flex-row items-center gap-2" {
+            label for="folder" { "Folder:" }
+            input
+                type="text"
+                id="folder"
+                name="folder"
+                list="folders"
+                placeholder="No folder"
+                value=[folder_name]
+                class="py-1 px-2 rounded-md border border-gray-200";
+            datalist id="folders" {
+                @for folder in &folders {
+                    option value=(folder.name) {}
+                }
+            }
+            button type="submit" class="py-1 px-2 font-medium rounded-md border border-gray-200" { "Move" }
+        }
+        details class="mt-4" {
+            summary class="cursor-pointer font-medium" { "Crawl history" }
+            div class="mt-2 overflow-x-auto" {
+                (crawl_log_table(&crawl_logs))
+            }
+        }
         hr class="my-4";
         ul id="entry-list" class="list-none flex flex-col gap-4" {
             (entry_list(entries, &options, true))
@@ -58,6 +165,138 @@ pub async fn get(
     }))
 }
 
+const INPUT_CLASS: &str = "w-full mt-1 p-2 bg-gray-50 border border-gray-300 shadow-sm rounded-md focus:ring focus:ring-blue-500 focus:border-blue-500 focus:ring-opacity-50";
+
+pub async fn edit(
+    Path(id): Path<Base62Uuid>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    hx_target: Option<TypedHeader<HXTarget>>,
+    layout: Layout,
+) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if !user.admin {
+        return Err(Error::Forbidden("only admins can edit feeds"));
+    }
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let feed = Feed::get(&pool, id.as_uuid()).await?;
+    let edit_url = format!("/feed/{}/edit", id);
+    let feed_url = format!("/feed/{}", id);
+    let crawl_interval_minutes = feed
+        .crawl_interval_pinned
+        .then_some(feed.crawl_interval_minutes);
+    Ok(layout.with_subtitle("edit feed").targeted(hx_target).render(html! {
+        header class="mb-4" {
+            h2 class="text-2xl font-medium" { "Edit feed" }
+        }
+        form action=(edit_url) method="post" class="flex flex-col gap-4 max-w-2xl" {
+            div {
+                label for="title" class="text-sm font-medium text-gray-700" { "Title" }
+                input
+                    type="text"
+                    id="title"
+                    name="title"
+                    value=[&feed.title]
+                    maxlength="255"
+                    class=(INPUT_CLASS);
+                p class="mt-1 text-sm text-gray-500" {
+                    "Leave empty to use the title of the feed."
+                }
+            }
+            div {
+                label for="url" class="text-sm font-medium text-gray-700" { "URL" }
+                input
+                    type="url"
+                    id="url"
+                    name="url"
+                    value=(feed.url)
+                    required="true"
+                    class=(INPUT_CLASS);
+            }
+            div {
+                label for="description" class="text-sm font-medium text-gray-700" { "Description" }
+                textarea id="description" name="description" rows="3" class=(INPUT_CLASS) {
+                    @if let Some(description) = &feed.description {
+                        (description)
+                    }
+                }
+                p class="mt-1 text-sm text-gray-500" {
+                    "Leave empty to use the description of the feed."
+                }
+            }
+            div {
+                label for="crawl_interval_minutes" class="text-sm font-medium text-gray-700" {
+                    "Crawl interval (minutes)"
+                }
+                input
+                    type="number"
+                    id="crawl_interval_minutes"
+                    name="crawl_interval_minutes"
+                    value=[crawl_interval_minutes]
+                    placeholder=(format!("Automatic (currently {})", feed.crawl_interval_minutes))
+                    min=(MIN_CRAWL_INTERVAL_MINUTES)
+                    max=(MAX_CRAWL_INTERVAL_MINUTES)
+                    class=(INPUT_CLASS);
+                p class="mt-1 text-sm text-gray-500" {
+                    "Leave empty to adjust the interval to how often the feed is updated."
+                }
+            }
+            div class="flex flex-row gap-4" {
+                button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Save" }
+                a href=(feed_url) class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Cancel" }
+            }
+        }
+    }))
+}
+
+#[serde_as]
+#[derive(Deserialize)]
+pub struct EditFeed {
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    title: Option<String>,
+    url: String,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    description: Option<String>,
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    crawl_interval_minutes: Option<i32>,
+}
+
+/// Saves the fields of the edit form that were changed. Unchanged fields are left out so that
+/// they are not pinned. Only admins can edit feeds, since the changes apply to every subscriber.
+pub async fn update(
+    Path(id): Path<Base62Uuid>,
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    auth: AuthSession,
+    Form(edit_feed): Form<EditFeed>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if !user.admin {
+        return Err(Error::Forbidden("only admins can edit feeds"));
+    }
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let feed = Feed::get(&pool, id.as_uuid()).await?;
+    let pinned_crawl_interval_minutes = feed
+        .crawl_interval_pinned
+        .then_some(feed.crawl_interval_minutes);
+    let payload = UpdateFeed {
+        title: (edit_feed.title != feed.title).then_some(edit_feed.title),
+        url: (edit_feed.url != feed.url).then_some(edit_feed.url),
+        description: (edit_feed.description != feed.description).then_some(edit_feed.description),
+        crawl_interval_minutes: (edit_feed.crawl_interval_minutes != pinned_crawl_interval_minutes)
+            .then_some(edit_feed.crawl_interval_minutes),
+    };
+    let reschedule = payload.affects_schedule();
+    Feed::update(&pool, feed.feed_id, payload).await?;
+    if reschedule {
+        let _ = crawl_scheduler.reschedule(feed.feed_id).await;
+    }
+    Ok(Redirect::to(&format!("/feed/{}", id)))
+}
+
 #[serde_as]
 #[derive(Deserialize)]
 pub struct AddFeed {
@@ -82,6 +321,10 @@ pub enum AddFeedError {
     CreateFeedError(String, #[source] Error),
     #[error("feed already exists: {0}")]
     FeedAlreadyExists(String, #[source] Error),
+    #[error("no feeds found at: {0}")]
+    NoFeedsFound(String),
+    #[error("unauthorized")]
+    Unauthorized,
 }
 pub type AddFeedResult<T, E = AddFeedError> = ::std::result::Result<T, E>;
 
@@ -90,10 +333,13 @@ impl AddFeedError {
         use AddFeedError::*;
 
         match self {
-            InvalidUrl(..) | FetchError(..) | ParseError(..) | FeedAlreadyExists(..) => {
-                StatusCode::UNPROCESSABLE_ENTITY
-            }
+            InvalidUrl(..)
+            | FetchError(..)
+            | ParseError(..)
+            | FeedAlreadyExists(..)
+            | NoFeedsFound(..) => StatusCode::UNPROCESSABLE_ENTITY,
             CreateFeedError(..) => StatusCode::INTERNAL_SERVER_ERROR,
+            Unauthorized => StatusCode::UNAUTHORIZED,
         }
     }
 }
@@ -114,13 +360,69 @@ impl IntoResponse for AddFeedError {
     }
 }
 
+/// Lists the feeds found on a page so that the user can choose which one to add.
+fn choose_feed(url: &str, feeds: Vec<DiscoveredFeed>) -> Response {
+    html! {
+        (add_feed_form())
+        ul class="flex flex-col gap-2" {
+            li { "Found " (feeds.len()) " feeds at " (url) ", choose one to add:" }
+            @for feed in feeds {
+                li {
+                    form
+                        action="/feed"
+                        method="post"
+                        hx-post="/feed"
+                        hx-target="#add-feed-form"
+                        hx-swap="outerHTML"
+                        class="flex flex-row gap-2 items-center"
+                    {
+                        input type="hidden" name="url" value=(feed.url);
+                        button type="submit" class="py-1 px-2 font-medium rounded-md border border-gray-200" { "Add" }
+                        span class="overflow-x-hidden whitespace-nowrap text-ellipsis" {
+                            @if let Some(title) = feed.title {
+                                (title) " "
+                            }
+                            span class="text-gray-500" { (feed.url) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    .into_string()
+    .into_response()
+}
+
 pub async fn post(
     State(pool): State<PgPool>,
+    State(client): State<Client>,
+    State(domain_locks): State<DomainLocks>,
     State(crawls): State<Crawls>,
     State(crawl_scheduler): State<CrawlSchedulerHandle>,
-    Form(add_feed): Form<AddFeed>,
+    auth: AuthSession,
+    Form(mut add_feed): Form<AddFeed>,
 ) -> AddFeedResult<Response> {
-    let feed = Feed::create(
+    let user = auth.user.ok_or(AddFeedError::Unauthorized)?;
+
+    // Feeds that were already added by someone don't need to be discovered again. Otherwise, the
+    // URL may be a web page that links to the feed (or feeds) instead of a feed itself.
+    if Feed::get_by_url(&pool, &add_feed.url).await.is_err() {
+        let url = Url::parse(&add_feed.url)
+            .map_err(|err| AddFeedError::InvalidUrl(add_feed.url.clone(), err))?;
+        let mut feeds = discover_feeds(&client, &domain_locks, &url)
+            .await
+            .map_err(|err| AddFeedError::FetchError(add_feed.url.clone(), err))?;
+        if feeds.len() > 1 {
+            return Ok(choose_feed(&add_feed.url, feeds));
+        }
+        let feed = feeds
+            .pop()
+            .ok_or_else(|| AddFeedError::NoFeedsFound(add_feed.url.clone()))?;
+        add_feed.url = feed.url.to_string();
+        add_feed.title = add_feed.title.or(feed.title);
+    }
+
+    let (feed, addition) = Feed::create_or_restore(
         &pool,
         CreateFeed {
             title: add_feed.title,
@@ -129,18 +431,33 @@ pub async fn post(
         },
     )
     .await
-    .map_err(|err| {
-        if let Error::Sqlx(sqlx::error::Error::Database(db_error)) = &err {
-            if let Some(code) = db_error.code() {
-                if let Some(constraint) = db_error.constraint() {
-                    if code == "23505" && constraint == "feed_url_idx" {
-                        return AddFeedError::FeedAlreadyExists(add_feed.url.clone(), err);
-                    }
+    .map_err(|err| AddFeedError::CreateFeedError(add_feed.url.clone(), err))?;
+
+    Subscription::create(&pool, user.user_id, feed.feed_id)
+        .await
+        .map_err(|err| match err {
+            Error::Conflict(_) => AddFeedError::FeedAlreadyExists(add_feed.url.clone(), err),
+            _ => AddFeedError::CreateFeedError(add_feed.url.clone(), err),
+        })?;
+
+    if addition != FeedAddition::Created {
+        // Another user already added this feed, so it already has entries. It only needs to be
+        // scheduled to be crawled again if it had been deleted.
+        if addition.needs_schedule() {
+            let _ = crawl_scheduler.schedule(feed.feed_id).await;
+        }
+        return Ok((
+            StatusCode::CREATED,
+            html! {
+                (add_feed_form())
+                ul class="overflow-x-hidden whitespace-nowrap text-ellipsis" {
+                    li hx-target="#main-content" hx-swap="innerHTML" { "Subscribed to feed: " (feed_link(&feed)) }
                 }
             }
-        }
-        AddFeedError::CreateFeedError(add_feed.url.clone(), err)
-    })?;
+            .into_string(),
+        )
+            .into_response());
+    }
 
     let receiver = crawl_scheduler.schedule(feed.feed_id).await;
     {
@@ -233,7 +550,104 @@ pub async fn stream(
     ))
 }
 
-pub async fn delete(State(pool): State<PgPool>, Path(id): Path<Base62Uuid>) -> Result<Redirect> {
-    Feed::delete(&pool, id.as_uuid()).await?;
+pub async fn delete(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    State(client): State<Client>,
+    State(config): State<Config>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if Subscription::delete(&pool, user.user_id, id.as_uuid()).await?
+        && Feed::delete_if_unsubscribed(&pool, id.as_uuid()).await?
+    {
+        let _ = crawl_scheduler.unschedule(id.as_uuid()).await;
+        if let Err(err) =
+            websub::unsubscribe(&pool, &client, &config.public_url, id.as_uuid()).await
+        {
+            error!("failed to unsubscribe from websub hub: {}", err);
+        }
+    }
     Ok(Redirect::to("/feeds"))
 }
+
+fn resume_button(resume_url: &str) -> Markup {
+    html! {
+        form action=(resume_url) method="post" {
+            button type="submit" class="py-1 px-2 font-medium rounded-md border border-gray-200" { "▶️ Resume crawling" }
+        }
+    }
+}
+
+/// Resumes crawling a feed that was paused after failing for too long, or that was gone. Feeds
+/// are shared by all of their subscribers, so only admins can resume them.
+pub async fn resume(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if !user.admin {
+        return Err(Error::Forbidden("only admins can resume feeds"));
+    }
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let mut receiver = crawl_scheduler.resume(id.as_uuid()).await;
+    if let Ok(CrawlSchedulerHandleMessage::Resume(Err(err))) = receiver.recv().await {
+        error!("failed to resume feed: {}", err);
+        return Err(Error::InternalServerError);
+    }
+    Ok(Redirect::to(&format!("/feed/{}", id)))
+}
+
+/// Pauses crawling a feed until it is resumed. Feeds are shared, so this pauses the feed for all
+/// of its subscribers and only admins can do it.
+pub async fn pause(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    if !user.admin {
+        return Err(Error::Forbidden("only admins can pause feeds"));
+    }
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    let mut receiver = crawl_scheduler.pause(id.as_uuid()).await;
+    if let Ok(CrawlSchedulerHandleMessage::Pause(Err(err))) = receiver.recv().await {
+        error!("failed to pause feed: {}", err);
+        return Err(Error::InternalServerError);
+    }
+    Ok(Redirect::to(&format!("/feed/{}", id)))
+}
+
+pub async fn read(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    Subscription::get(&pool, user.user_id, id.as_uuid()).await?;
+    EntryRead::create_all_in_feed(&pool, user.user_id, id.as_uuid()).await?;
+    Ok(Redirect::to(&format!("/feed/{}", id)))
+}
+
+pub async fn folder(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    Path(id): Path<Base62Uuid>,
+    Form(set_folder): Form<SetFolder>,
+) -> Result<Redirect> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let folder_id = match set_folder.folder {
+        Some(name) => Some(
+            Folder::get_or_create(&pool, user.user_id, CreateFolder { name })
+                .await?
+                .folder_id,
+        ),
+        None => None,
+    };
+    Subscription::set_folder(&pool, user.user_id, id.as_uuid(), folder_id).await?;
+    Ok(Redirect::to(&format!("/feed/{}", id)))
+}
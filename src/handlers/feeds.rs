@@ -4,7 +4,8 @@ use axum_extra::TypedHeader;
 use maud::html;
 use sqlx::PgPool;
 
-use crate::error::Result;
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
 use crate::htmx::HXTarget;
 use crate::models::feed::{Feed, GetFeedsOptions};
 use crate::partials::add_feed_form::add_feed_form;
@@ -14,10 +15,15 @@ use crate::partials::opml_import_form::opml_import_form;
 
 pub async fn get(
     State(pool): State<PgPool>,
+    auth: AuthSession,
     hx_target: Option<TypedHeader<HXTarget>>,
     layout: Layout,
 ) -> Result<Response> {
-    let options = GetFeedsOptions::default();
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let options = GetFeedsOptions {
+        user_id: Some(user.user_id),
+        ..Default::default()
+    };
     let feeds = Feed::get_all(&pool, &options).await?;
     Ok(layout
         .with_subtitle("feeds")
@@ -32,6 +38,7 @@ pub async fn get(
                     h3 class="text-xl font-medium" { "Add Feed" }
                     (add_feed_form())
                     (opml_import_form())
+                    a href="/export/opml" hx-boost="false" class="text-blue-600 hover:underline" { "Export feeds as OPML" }
                 }
             }
         }))
@@ -0,0 +1,281 @@
+use std::fs;
+
+use axum::extract::{Query, State};
+use axum::{Form, Json};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::entry::Entry;
+use crate::models::entry_read::EntryRead;
+use crate::models::entry_star::EntryStar;
+use crate::models::fever::{
+    FeverApiKey, FeverFeed, FeverFeedsGroup, FeverGroup, FeverItem, FEVER_ITEMS_PAGE_SIZE,
+};
+use crate::models::numeric_id::NumericId;
+use crate::models::subscription::Subscription;
+use crate::models::user::User;
+
+const FEVER_API_VERSION: u32 = 3;
+
+/// Parameters of a Fever API request. Clients send some of them in the query string and some in
+/// the form body (always including `api_key`), so both are parsed into this and merged.
+///
+/// Flags like `groups` in `?api&groups` are present with an empty value.
+#[derive(Debug, Default, Deserialize)]
+pub struct FeverParams {
+    api_key: Option<String>,
+    groups: Option<String>,
+    feeds: Option<String>,
+    favicons: Option<String>,
+    items: Option<String>,
+    unread_item_ids: Option<String>,
+    saved_item_ids: Option<String>,
+    since_id: Option<i64>,
+    max_id: Option<i64>,
+    with_ids: Option<String>,
+    mark: Option<String>,
+    #[serde(rename = "as")]
+    mark_as: Option<String>,
+    id: Option<i64>,
+    before: Option<i64>,
+}
+
+impl FeverParams {
+    fn merge(self, other: FeverParams) -> FeverParams {
+        FeverParams {
+            api_key: self.api_key.or(other.api_key),
+            groups: self.groups.or(other.groups),
+            feeds: self.feeds.or(other.feeds),
+            favicons: self.favicons.or(other.favicons),
+            items: self.items.or(other.items),
+            unread_item_ids: self.unread_item_ids.or(other.unread_item_ids),
+            saved_item_ids: self.saved_item_ids.or(other.saved_item_ids),
+            since_id: self.since_id.or(other.since_id),
+            max_id: self.max_id.or(other.max_id),
+            with_ids: self.with_ids.or(other.with_ids),
+            mark: self.mark.or(other.mark),
+            mark_as: self.mark_as.or(other.mark_as),
+            id: self.id.or(other.id),
+            before: self.before.or(other.before),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeverFavicon {
+    id: i64,
+    data: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FeverItemResponse {
+    id: i64,
+    feed_id: i64,
+    title: String,
+    author: String,
+    html: String,
+    url: String,
+    is_saved: u8,
+    is_read: u8,
+    created_on_time: i64,
+}
+
+#[serde_with::skip_serializing_none]
+#[derive(Debug, Default, Serialize)]
+pub struct FeverResponse {
+    api_version: u32,
+    auth: u8,
+    last_refreshed_on_time: Option<i64>,
+    groups: Option<Vec<FeverGroup>>,
+    feeds: Option<Vec<FeverFeed>>,
+    feeds_groups: Option<Vec<FeverFeedsGroup>>,
+    favicons: Option<Vec<FeverFavicon>>,
+    items: Option<Vec<FeverItemResponse>>,
+    total_items: Option<i64>,
+    unread_item_ids: Option<String>,
+    saved_item_ids: Option<String>,
+}
+
+fn join_ids(ids: Vec<i64>) -> String {
+    ids.iter()
+        .map(|id| id.to_string())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn before_time(before: Option<i64>) -> Result<DateTime<Utc>> {
+    match before {
+        Some(before) => {
+            DateTime::from_timestamp(before, 0).ok_or(Error::BadRequest("invalid before time"))
+        }
+        None => Ok(Utc::now()),
+    }
+}
+
+/// Returns the id of the entry with the numeric id, if it is visible to the user.
+async fn item_entry_id(pool: &PgPool, user: &User, id: i64) -> Result<Uuid> {
+    let entry_id = NumericId::get(pool, id).await?;
+    Ok(Entry::get_for_user(pool, entry_id, user.user_id)
+        .await?
+        .entry_id)
+}
+
+/// Handles a `mark` request. Returns true if the unread item ids changed and false if the saved
+/// item ids changed.
+async fn mark(pool: &PgPool, user: &User, params: &FeverParams) -> Result<bool> {
+    let id = params.id.ok_or(Error::BadRequest("missing id"))?;
+    let mark_as = params.mark_as.as_deref().unwrap_or_default();
+    match (params.mark.as_deref().unwrap_or_default(), mark_as) {
+        ("item", "read") => {
+            let entry_id = item_entry_id(pool, user, id).await?;
+            EntryRead::create(pool, user.user_id, entry_id).await?;
+            Ok(true)
+        }
+        ("item", "unread") => {
+            let entry_id = item_entry_id(pool, user, id).await?;
+            EntryRead::delete(pool, user.user_id, entry_id).await?;
+            Ok(true)
+        }
+        ("item", "saved") => {
+            let entry_id = item_entry_id(pool, user, id).await?;
+            EntryStar::create(pool, user.user_id, entry_id).await?;
+            Ok(false)
+        }
+        ("item", "unsaved") => {
+            let entry_id = item_entry_id(pool, user, id).await?;
+            EntryStar::delete(pool, user.user_id, entry_id).await?;
+            Ok(false)
+        }
+        ("feed", "read") => {
+            let feed_id = NumericId::get(pool, id).await?;
+            Subscription::get(pool, user.user_id, feed_id).await?;
+            let before = before_time(params.before)?;
+            EntryRead::create_all_in_feed_before(pool, user.user_id, feed_id, before).await?;
+            Ok(true)
+        }
+        // Group 0 is the "Kindling" super group of all feeds, and -1 is "Sparks", which aren't
+        // supported so is treated the same.
+        ("group", "read") if id <= 0 => {
+            let before = before_time(params.before)?;
+            EntryRead::create_all_before(pool, user.user_id, before).await?;
+            Ok(true)
+        }
+        ("group", "read") => {
+            let folder_id = NumericId::get(pool, id).await?;
+            let before = before_time(params.before)?;
+            EntryRead::create_all_in_folder_before(pool, user.user_id, folder_id, before).await?;
+            Ok(true)
+        }
+        _ => Err(Error::BadRequest("unsupported mark")),
+    }
+}
+
+pub async fn api(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+    Query(query): Query<FeverParams>,
+    body: Option<Form<FeverParams>>,
+) -> Result<Json<FeverResponse>> {
+    let params = match body {
+        Some(Form(body)) => body.merge(query),
+        None => query,
+    };
+    let mut response = FeverResponse {
+        api_version: FEVER_API_VERSION,
+        ..Default::default()
+    };
+    let user = match params.api_key.as_deref() {
+        Some(api_key) => match FeverApiKey::get_user(&pool, api_key).await {
+            Ok(user) => user,
+            Err(Error::Unauthorized) => return Ok(Json(response)),
+            Err(error) => return Err(error),
+        },
+        None => return Ok(Json(response)),
+    };
+    response.auth = 1;
+    response.last_refreshed_on_time = Some(Utc::now().timestamp());
+
+    let mut include_unread_item_ids = params.unread_item_ids.is_some();
+    let mut include_saved_item_ids = params.saved_item_ids.is_some();
+    if params.mark.is_some() {
+        info!(mark = ?params.mark, mark_as = ?params.mark_as, id = ?params.id, "fever mark");
+        if mark(&pool, &user, &params).await? {
+            include_unread_item_ids = true;
+        } else {
+            include_saved_item_ids = true;
+        }
+    }
+
+    if params.groups.is_some() {
+        response.groups = Some(FeverGroup::get_all(&pool, user.user_id).await?);
+    }
+    if params.feeds.is_some() {
+        response.feeds = Some(FeverFeed::get_all(&pool, user.user_id).await?);
+    }
+    if params.groups.is_some() || params.feeds.is_some() {
+        response.feeds_groups = Some(FeverFeedsGroup::get_all(&pool, user.user_id).await?);
+    }
+    if params.favicons.is_some() {
+        response.favicons = Some(vec![]);
+    }
+    if params.items.is_some() {
+        let with_ids: Vec<i64> = params
+            .with_ids
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|id| id.trim().parse().ok())
+            .take(FEVER_ITEMS_PAGE_SIZE as usize)
+            .collect();
+        let items = FeverItem::get_all(
+            &pool,
+            user.user_id,
+            params.since_id,
+            params.max_id,
+            &with_ids,
+        )
+        .await?;
+        let content_dir = std::path::Path::new(&config.content_dir);
+        response.items = Some(
+            items
+                .into_iter()
+                .map(|item| {
+                    let content_path = content_dir.join(format!("{}.html", item.entry_id));
+                    let html = fs::read_to_string(content_path)
+                        .ok()
+                        .or(item.description)
+                        .unwrap_or_default();
+                    FeverItemResponse {
+                        id: item.id,
+                        feed_id: item.feed_id,
+                        title: item.title,
+                        author: String::new(),
+                        html,
+                        url: item.url,
+                        is_saved: item.is_saved.into(),
+                        is_read: item.is_read.into(),
+                        created_on_time: item.created_on_time,
+                    }
+                })
+                .collect(),
+        );
+        response.total_items = Some(FeverItem::count(&pool, user.user_id).await?);
+    }
+    if include_unread_item_ids {
+        response.unread_item_ids = Some(join_ids(
+            FeverItem::get_unread_ids(&pool, user.user_id).await?,
+        ));
+    }
+    if include_saved_item_ids {
+        response.saved_item_ids = Some(join_ids(
+            FeverItem::get_saved_ids(&pool, user.user_id).await?,
+        ));
+    }
+
+    Ok(Json(response))
+}
@@ -0,0 +1,677 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+
+use axum::async_trait;
+use axum::body::Bytes;
+use axum::extract::{FromRef, FromRequestParts, Path, RawQuery, State};
+use axum::http::{header, request::Parts};
+use axum::Json;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use url::form_urlencoded;
+use uuid::Uuid;
+
+use crate::auth::verify_password;
+use crate::config::Config;
+use crate::error::{Error, Result};
+use crate::models::entry::{Entry, GetEntriesOptions};
+use crate::models::entry_read::EntryRead;
+use crate::models::entry_star::EntryStar;
+use crate::models::folder::Folder;
+use crate::models::greader_token::GReaderToken;
+use crate::models::subscription::{SubscribedFeed, Subscription};
+use crate::models::user::User;
+use crate::uuid::Base62Uuid;
+
+const READING_LIST: &str = "user/-/state/com.google/reading-list";
+const READ: &str = "user/-/state/com.google/read";
+const STARRED: &str = "user/-/state/com.google/starred";
+const KEPT_UNREAD: &str = "user/-/state/com.google/kept-unread";
+const LABEL_PREFIX: &str = "user/-/label/";
+const FEED_PREFIX: &str = "feed/";
+const ITEM_PREFIX: &str = "tag:google.com,2005:reader/item/";
+const DEFAULT_ITEMS_COUNT: i64 = 20;
+const MAX_ITEMS_COUNT: i64 = 1000;
+
+/// The user authenticated with the `Authorization: GoogleLogin auth=<token>` header, where the
+/// token was returned by `client_login`.
+pub struct GReaderUser(pub User);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for GReaderUser
+where
+    S: Send + Sync,
+    PgPool: FromRef<S>,
+{
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let pool = PgPool::from_ref(state);
+        let token = parts
+            .headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("GoogleLogin auth="))
+            .ok_or(Error::Unauthorized)?;
+        Ok(GReaderUser(GReaderToken::get_user(&pool, token).await?))
+    }
+}
+
+/// Parameters of a Google Reader API request, from both the query string and the form body.
+///
+/// Unlike with `Query` or `Form`, parameters (like the `i` item ids of `edit-tag`) can be repeated.
+struct Params(Vec<(String, String)>);
+
+impl Params {
+    fn new(query: Option<String>, body: &[u8]) -> Self {
+        let query = query.unwrap_or_default();
+        Params(
+            form_urlencoded::parse(query.as_bytes())
+                .chain(form_urlencoded::parse(body))
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect(),
+        )
+    }
+
+    fn get<'a>(&'a self, name: &'a str) -> Option<&'a str> {
+        self.get_all(name).next()
+    }
+
+    fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.0
+            .iter()
+            .filter(move |(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Clients sometimes send tags with their user id in place of "-", so replace it.
+fn normalize_tag(tag: &str) -> String {
+    if let Some((_, rest)) = tag
+        .strip_prefix("user/")
+        .and_then(|rest| rest.split_once('/'))
+    {
+        return format!("user/-/{}", rest);
+    }
+    tag.to_string()
+}
+
+fn item_id(entry_id: Uuid) -> String {
+    format!("{}{}", ITEM_PREFIX, Base62Uuid::from(entry_id))
+}
+
+/// Parses an item id in either the long "tag:google.com,2005:reader/item/<id>" or the short form.
+fn parse_item_id(id: &str) -> Result<Uuid> {
+    let id = id.strip_prefix(ITEM_PREFIX).unwrap_or(id);
+    Base62Uuid::try_from(id)
+        .map(|id| id.as_uuid())
+        .map_err(|_| Error::BadRequest("invalid item id"))
+}
+
+fn feed_stream_id(feed_id: Uuid) -> String {
+    format!("{}{}", FEED_PREFIX, Base62Uuid::from(feed_id))
+}
+
+enum Stream {
+    ReadingList,
+    Starred,
+    Feed(Uuid),
+    Label(String),
+}
+
+impl Stream {
+    fn parse(stream_id: &str) -> Result<Stream> {
+        let stream_id = normalize_tag(stream_id);
+        if stream_id == READING_LIST {
+            Ok(Stream::ReadingList)
+        } else if stream_id == STARRED {
+            Ok(Stream::Starred)
+        } else if let Some(feed_id) = stream_id.strip_prefix(FEED_PREFIX) {
+            Base62Uuid::try_from(feed_id)
+                .map(|feed_id| Stream::Feed(feed_id.as_uuid()))
+                .map_err(|_| Error::BadRequest("invalid feed stream id"))
+        } else if let Some(label) = stream_id.strip_prefix(LABEL_PREFIX) {
+            Ok(Stream::Label(label.to_string()))
+        } else {
+            Err(Error::BadRequest("unsupported stream id"))
+        }
+    }
+}
+
+/// Continuations are the publish time and id of the last entry of the previous page.
+fn continuation(entries: &[Entry], limit: i64) -> Option<String> {
+    if entries.len() as i64 != limit {
+        return None;
+    }
+    entries.last().map(|entry| {
+        format!(
+            "{}_{}",
+            entry.published_at.timestamp_micros(),
+            Base62Uuid::from(entry.entry_id)
+        )
+    })
+}
+
+fn parse_continuation(continuation: &str) -> Result<(DateTime<Utc>, Uuid)> {
+    let (published_before, id_before) = continuation
+        .split_once('_')
+        .ok_or(Error::BadRequest("invalid continuation"))?;
+    let published_before = published_before
+        .parse()
+        .ok()
+        .and_then(DateTime::from_timestamp_micros)
+        .ok_or(Error::BadRequest("invalid continuation"))?;
+    let id_before =
+        Base62Uuid::try_from(id_before).map_err(|_| Error::BadRequest("invalid continuation"))?;
+    Ok((published_before, id_before.as_uuid()))
+}
+
+async fn stream_entries(
+    pool: &PgPool,
+    user: &User,
+    stream: &Stream,
+    params: &Params,
+) -> Result<(Vec<Entry>, i64)> {
+    let limit = params
+        .get("n")
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(DEFAULT_ITEMS_COUNT)
+        .clamp(1, MAX_ITEMS_COUNT);
+    let mut options = GetEntriesOptions {
+        user_id: Some(user.user_id),
+        limit: Some(limit),
+        unread: params.get_all("xt").any(|tag| normalize_tag(tag) == READ),
+        ..Default::default()
+    };
+    match stream {
+        Stream::ReadingList => {}
+        Stream::Starred => options.starred = true,
+        Stream::Feed(feed_id) => options.feed_id = Some(*feed_id),
+        Stream::Label(label) => {
+            options.folder_id = Some(
+                Folder::get_by_name(pool, user.user_id, label)
+                    .await?
+                    .folder_id,
+            )
+        }
+    }
+    if let Some(continuation) = params.get("c") {
+        let (published_before, id_before) = parse_continuation(continuation)?;
+        options.published_before = Some(published_before);
+        options.id_before = Some(id_before);
+    }
+    // Despite its name, `ot` is the time to start from: only newer items are returned.
+    if let Some(start_time) = params.get("ot") {
+        options.published_after = start_time
+            .parse()
+            .ok()
+            .and_then(|start_time| DateTime::from_timestamp(start_time, 0));
+    }
+    Ok((Entry::get_all(pool, &options).await?, limit))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserInfo {
+    user_id: String,
+    user_name: String,
+    user_profile_id: String,
+    user_email: String,
+}
+
+#[derive(Serialize)]
+pub struct Category {
+    id: String,
+    label: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GReaderSubscription {
+    id: String,
+    title: String,
+    categories: Vec<Category>,
+    url: String,
+    html_url: String,
+    icon_url: String,
+}
+
+#[derive(Serialize)]
+pub struct SubscriptionList {
+    subscriptions: Vec<GReaderSubscription>,
+}
+
+#[derive(Serialize)]
+pub struct Tag {
+    id: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    tag_type: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct TagList {
+    tags: Vec<Tag>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnreadCount {
+    id: String,
+    count: i64,
+    newest_item_timestamp_usec: String,
+}
+
+#[derive(Serialize)]
+pub struct UnreadCounts {
+    max: i64,
+    unreadcounts: Vec<UnreadCount>,
+}
+
+#[derive(Serialize)]
+pub struct Link {
+    href: String,
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    link_type: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+pub struct Content {
+    direction: &'static str,
+    content: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Origin {
+    stream_id: String,
+    title: String,
+    html_url: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    id: String,
+    crawl_time_msec: String,
+    timestamp_usec: String,
+    published: i64,
+    updated: i64,
+    title: String,
+    author: String,
+    canonical: Vec<Link>,
+    alternate: Vec<Link>,
+    summary: Content,
+    categories: Vec<String>,
+    origin: Origin,
+}
+
+#[derive(Serialize)]
+pub struct StreamContents {
+    direction: &'static str,
+    id: String,
+    updated: i64,
+    items: Vec<Item>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    continuation: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemRef {
+    id: String,
+    direct_stream_ids: Vec<String>,
+    timestamp_usec: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemRefs {
+    item_refs: Vec<ItemRef>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    continuation: Option<String>,
+}
+
+async fn items(
+    pool: &PgPool,
+    config: &Config,
+    user: &User,
+    entries: Vec<Entry>,
+) -> Result<Vec<Item>> {
+    let entry_ids: Vec<Uuid> = entries.iter().map(|entry| entry.entry_id).collect();
+    let read: HashSet<Uuid> = EntryRead::get_read_ids(pool, user.user_id, &entry_ids)
+        .await?
+        .into_iter()
+        .collect();
+    let starred: HashSet<Uuid> = EntryStar::get_starred_ids(pool, user.user_id, &entry_ids)
+        .await?
+        .into_iter()
+        .collect();
+    let feeds: HashMap<Uuid, SubscribedFeed> = Subscription::get_all_feeds(pool, user.user_id)
+        .await?
+        .into_iter()
+        .map(|feed| (feed.feed_id, feed))
+        .collect();
+    let content_dir = std::path::Path::new(&config.content_dir);
+    Ok(entries
+        .into_iter()
+        .map(|entry| {
+            let feed = feeds.get(&entry.feed_id);
+            let mut categories = vec![READING_LIST.to_string()];
+            if read.contains(&entry.entry_id) {
+                categories.push(READ.to_string());
+            }
+            if starred.contains(&entry.entry_id) {
+                categories.push(STARRED.to_string());
+            }
+            if let Some(folder) = feed.and_then(|feed| feed.folder.as_ref()) {
+                categories.push(format!("{}{}", LABEL_PREFIX, folder));
+            }
+            let content_path = content_dir.join(format!("{}.html", entry.entry_id));
+            let content = fs::read_to_string(content_path)
+                .ok()
+                .or(entry.description)
+                .unwrap_or_default();
+            Item {
+                id: item_id(entry.entry_id),
+                crawl_time_msec: entry.created_at.timestamp_millis().to_string(),
+                timestamp_usec: entry.published_at.timestamp_micros().to_string(),
+                published: entry.published_at.timestamp(),
+                updated: entry.updated_at.unwrap_or(entry.created_at).timestamp(),
+                title: entry.title.unwrap_or_default(),
+                author: String::new(),
+                canonical: vec![Link {
+                    href: entry.url.clone(),
+                    link_type: None,
+                }],
+                alternate: vec![Link {
+                    href: entry.url,
+                    link_type: Some("text/html"),
+                }],
+                summary: Content {
+                    direction: "ltr",
+                    content,
+                },
+                categories,
+                origin: Origin {
+                    stream_id: feed_stream_id(entry.feed_id),
+                    title: feed.and_then(|feed| feed.title.clone()).unwrap_or_default(),
+                    html_url: feed
+                        .and_then(|feed| feed.site_url.clone())
+                        .unwrap_or_default(),
+                },
+            }
+        })
+        .collect())
+}
+
+/// Logs a client in with the email and password in the form body, never the query string, so that
+/// passwords don't end up in logs.
+pub async fn client_login(State(pool): State<PgPool>, body: Bytes) -> Result<String> {
+    let params = Params::new(None, &body);
+    let email = params.get("Email").ok_or(Error::Unauthorized)?;
+    let password = params.get("Passwd").ok_or(Error::Unauthorized)?;
+    let user = User::get_by_email(&pool, email.to_string())
+        .await
+        .map_err(|_| Error::Unauthorized)?;
+    verify_password(password.to_string(), user.password_hash.clone())
+        .await
+        .map_err(|_| Error::Unauthorized)?;
+    let token = GReaderToken::create(&pool, user.user_id).await?;
+    Ok(format!("SID={token}\nLSID={token}\nAuth={token}\n"))
+}
+
+/// Clients fetch this token and send it back as the `T` parameter of edits. Edits are already
+/// authenticated by the auth token, so it isn't checked.
+pub async fn token(GReaderUser(_): GReaderUser) -> String {
+    Base62Uuid::new().to_string()
+}
+
+pub async fn user_info(GReaderUser(user): GReaderUser) -> Json<UserInfo> {
+    let user_id = Base62Uuid::from(user.user_id).to_string();
+    Json(UserInfo {
+        user_id: user_id.clone(),
+        user_name: user.name.unwrap_or_else(|| user.email.clone()),
+        user_profile_id: user_id,
+        user_email: user.email,
+    })
+}
+
+pub async fn subscription_list(
+    State(pool): State<PgPool>,
+    GReaderUser(user): GReaderUser,
+) -> Result<Json<SubscriptionList>> {
+    let feeds = Subscription::get_all_feeds(&pool, user.user_id).await?;
+    Ok(Json(SubscriptionList {
+        subscriptions: feeds
+            .into_iter()
+            .map(|feed| GReaderSubscription {
+                id: feed_stream_id(feed.feed_id),
+                title: feed.title.unwrap_or_else(|| feed.url.clone()),
+                categories: feed
+                    .folder
+                    .into_iter()
+                    .map(|folder| Category {
+                        id: format!("{}{}", LABEL_PREFIX, folder),
+                        label: folder,
+                    })
+                    .collect(),
+                url: feed.url,
+                html_url: feed.site_url.unwrap_or_default(),
+                icon_url: String::new(),
+            })
+            .collect(),
+    }))
+}
+
+pub async fn tag_list(
+    State(pool): State<PgPool>,
+    GReaderUser(user): GReaderUser,
+) -> Result<Json<TagList>> {
+    let folders = Folder::get_all(&pool, user.user_id).await?;
+    let mut tags = vec![Tag {
+        id: STARRED.to_string(),
+        tag_type: None,
+    }];
+    tags.extend(folders.into_iter().map(|folder| Tag {
+        id: format!("{}{}", LABEL_PREFIX, folder.name),
+        tag_type: Some("folder"),
+    }));
+    Ok(Json(TagList { tags }))
+}
+
+pub async fn unread_count(
+    State(pool): State<PgPool>,
+    GReaderUser(user): GReaderUser,
+) -> Result<Json<UnreadCounts>> {
+    let counts = Subscription::get_unread_counts(&pool, user.user_id).await?;
+    let total = counts.iter().map(|count| count.count).sum();
+    let newest = counts
+        .iter()
+        .filter_map(|count| count.newest_published_at)
+        .max();
+    let mut unreadcounts: Vec<UnreadCount> = counts
+        .into_iter()
+        .map(|count| UnreadCount {
+            id: feed_stream_id(count.feed_id),
+            count: count.count,
+            newest_item_timestamp_usec: count
+                .newest_published_at
+                .map(|published_at| published_at.timestamp_micros())
+                .unwrap_or_default()
+                .to_string(),
+        })
+        .collect();
+    unreadcounts.push(UnreadCount {
+        id: READING_LIST.to_string(),
+        count: total,
+        newest_item_timestamp_usec: newest
+            .map(|published_at| published_at.timestamp_micros())
+            .unwrap_or_default()
+            .to_string(),
+    });
+    Ok(Json(UnreadCounts {
+        max: total,
+        unreadcounts,
+    }))
+}
+
+async fn stream_contents_response(
+    pool: &PgPool,
+    config: &Config,
+    user: &User,
+    stream_id: &str,
+    params: &Params,
+) -> Result<Json<StreamContents>> {
+    let stream = Stream::parse(stream_id)?;
+    let (entries, limit) = stream_entries(pool, user, &stream, params).await?;
+    let continuation = continuation(&entries, limit);
+    Ok(Json(StreamContents {
+        direction: "ltr",
+        id: stream_id.to_string(),
+        updated: Utc::now().timestamp(),
+        items: items(pool, config, user, entries).await?,
+        continuation,
+    }))
+}
+
+/// `/stream/contents/<stream id>`
+pub async fn stream_contents(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+    GReaderUser(user): GReaderUser,
+    Path(stream_id): Path<String>,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<Json<StreamContents>> {
+    let params = Params::new(query, &body);
+    stream_contents_response(&pool, &config, &user, &stream_id, &params).await
+}
+
+/// `/stream/contents?s=<stream id>`
+pub async fn stream_contents_param(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+    GReaderUser(user): GReaderUser,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<Json<StreamContents>> {
+    let params = Params::new(query, &body);
+    let stream_id = params.get("s").unwrap_or(READING_LIST).to_string();
+    stream_contents_response(&pool, &config, &user, &stream_id, &params).await
+}
+
+pub async fn stream_items_ids(
+    State(pool): State<PgPool>,
+    GReaderUser(user): GReaderUser,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<Json<ItemRefs>> {
+    let params = Params::new(query, &body);
+    let stream = Stream::parse(params.get("s").unwrap_or(READING_LIST))?;
+    let (entries, limit) = stream_entries(&pool, &user, &stream, &params).await?;
+    let continuation = continuation(&entries, limit);
+    Ok(Json(ItemRefs {
+        item_refs: entries
+            .into_iter()
+            .map(|entry| ItemRef {
+                id: Base62Uuid::from(entry.entry_id).to_string(),
+                direct_stream_ids: vec![feed_stream_id(entry.feed_id)],
+                timestamp_usec: entry.published_at.timestamp_micros().to_string(),
+            })
+            .collect(),
+        continuation,
+    }))
+}
+
+pub async fn stream_items_contents(
+    State(pool): State<PgPool>,
+    State(config): State<Config>,
+    GReaderUser(user): GReaderUser,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<Json<StreamContents>> {
+    let params = Params::new(query, &body);
+    let mut entries = Vec::new();
+    for id in params.get_all("i").take(MAX_ITEMS_COUNT as usize) {
+        match Entry::get_for_user(&pool, parse_item_id(id)?, user.user_id).await {
+            Ok(entry) => entries.push(entry),
+            Err(Error::NotFoundUuid(..)) => continue,
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(Json(StreamContents {
+        direction: "ltr",
+        id: READING_LIST.to_string(),
+        updated: Utc::now().timestamp(),
+        items: items(&pool, &config, &user, entries).await?,
+        continuation: None,
+    }))
+}
+
+pub async fn edit_tag(
+    State(pool): State<PgPool>,
+    GReaderUser(user): GReaderUser,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<&'static str> {
+    let params = Params::new(query, &body);
+    let add: Vec<String> = params.get_all("a").map(normalize_tag).collect();
+    let remove: Vec<String> = params.get_all("r").map(normalize_tag).collect();
+    for id in params.get_all("i") {
+        let entry = Entry::get_for_user(&pool, parse_item_id(id)?, user.user_id).await?;
+        for tag in &add {
+            match tag.as_str() {
+                READ => EntryRead::create(&pool, user.user_id, entry.entry_id).await?,
+                KEPT_UNREAD => EntryRead::delete(&pool, user.user_id, entry.entry_id).await?,
+                STARRED => EntryStar::create(&pool, user.user_id, entry.entry_id).await?,
+                _ => {}
+            }
+        }
+        for tag in &remove {
+            match tag.as_str() {
+                READ => EntryRead::delete(&pool, user.user_id, entry.entry_id).await?,
+                STARRED => EntryStar::delete(&pool, user.user_id, entry.entry_id).await?,
+                _ => {}
+            }
+        }
+    }
+    Ok("OK")
+}
+
+pub async fn mark_all_as_read(
+    State(pool): State<PgPool>,
+    GReaderUser(user): GReaderUser,
+    RawQuery(query): RawQuery,
+    body: Bytes,
+) -> Result<&'static str> {
+    let params = Params::new(query, &body);
+    let stream = Stream::parse(params.get("s").unwrap_or(READING_LIST))?;
+    let published_before = params
+        .get("ts")
+        .and_then(|ts| ts.parse().ok())
+        .and_then(DateTime::from_timestamp_micros)
+        .unwrap_or_else(Utc::now);
+    match stream {
+        Stream::ReadingList => {
+            EntryRead::create_all_before(&pool, user.user_id, published_before).await?;
+        }
+        Stream::Feed(feed_id) => {
+            Subscription::get(&pool, user.user_id, feed_id).await?;
+            EntryRead::create_all_in_feed_before(&pool, user.user_id, feed_id, published_before)
+                .await?;
+        }
+        Stream::Label(label) => {
+            let folder = Folder::get_by_name(&pool, user.user_id, &label).await?;
+            EntryRead::create_all_in_folder_before(
+                &pool,
+                user.user_id,
+                folder.folder_id,
+                published_before,
+            )
+            .await?;
+        }
+        Stream::Starred => return Err(Error::BadRequest("unsupported stream id")),
+    }
+    Ok("OK")
+}
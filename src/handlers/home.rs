@@ -1,22 +1,72 @@
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::response::Response;
 use axum_extra::TypedHeader;
+use chrono::Utc;
 use maud::html;
 use sqlx::PgPool;
+use uuid::Uuid;
 
-use crate::error::Result;
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
 use crate::htmx::HXTarget;
-use crate::models::entry::Entry;
+use crate::models::entry::{Entry, GetEntriesOptions};
+use crate::models::folder::Folder;
 use crate::partials::{entry_list::entry_list, layout::Layout};
 
+fn home_url(folder_id: Option<Uuid>, unread: bool) -> String {
+    match (folder_id, unread) {
+        (Some(folder_id), true) => format!("/?folder_id={}&unread=true", folder_id),
+        (Some(folder_id), false) => format!("/?folder_id={}", folder_id),
+        (None, true) => "/?unread=true".to_string(),
+        (None, false) => "/".to_string(),
+    }
+}
+
 pub async fn get(
+    Query(mut options): Query<GetEntriesOptions>,
     State(pool): State<PgPool>,
+    auth: AuthSession,
     hx_target: Option<TypedHeader<HXTarget>>,
     layout: Layout,
 ) -> Result<Response> {
-    let options = Default::default();
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    options.user_id = Some(user.user_id);
     let entries = Entry::get_all(&pool, &options).await?;
+    let folders = Folder::get_all(&pool, user.user_id).await?;
     Ok(layout.targeted(hx_target).render(html! {
+        @if !folders.is_empty() {
+            nav class="mb-4" {
+                ul class="flex flex-row flex-wrap list-none gap-4" {
+                    li {
+                        @if options.folder_id.is_none() {
+                            strong { "all" }
+                        } @else {
+                            a href=(home_url(None, options.unread)) { "all" }
+                        }
+                    }
+                    @for folder in folders {
+                        li {
+                            @if options.folder_id == Some(folder.folder_id) {
+                                strong { (folder.name) }
+                            } @else {
+                                a href=(home_url(Some(folder.folder_id), options.unread)) { (folder.name) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        header class="mb-4 flex flex-row items-center gap-4" {
+            @if options.unread {
+                a href=(home_url(options.folder_id, false)) class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Show all" }
+            } @else {
+                a href=(home_url(options.folder_id, true)) class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Show unread" }
+            }
+            form action="/entries/read" method="post" {
+                input type="hidden" name="published_before" value=(Utc::now().to_rfc3339());
+                button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "✔️ Mark all as read" }
+            }
+        }
         ul class="list-none flex flex-col gap-4" {
             (entry_list(entries, &options, true))
         }
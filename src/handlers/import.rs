@@ -11,6 +11,7 @@ use tokio_stream::StreamExt;
 use crate::actors::crawl_scheduler::CrawlSchedulerHandleMessage;
 use crate::actors::feed_crawler::FeedCrawlerHandleMessage;
 use crate::actors::importer::{ImporterHandle, ImporterHandleMessage};
+use crate::auth::AuthSession;
 use crate::error::{Error, Result};
 use crate::partials::entry_link::entry_link;
 use crate::partials::feed_link::feed_link;
@@ -21,13 +22,17 @@ use crate::uuid::Base62Uuid;
 pub async fn opml(
     State(imports): State<Imports>,
     State(importer): State<ImporterHandle>,
+    auth: AuthSession,
     mut multipart: Multipart,
 ) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
     if let Some(field) = multipart.next_field().await? {
         let import_id = Base62Uuid::new();
         let file_name = field.file_name().map(|s| s.to_string());
         let bytes = field.bytes().await?;
-        let receiver = importer.import(import_id.as_uuid(), file_name, bytes).await;
+        let receiver = importer
+            .import(import_id.as_uuid(), user.user_id, file_name, bytes)
+            .await;
         {
             let mut imports = imports.lock().await;
             imports.insert(import_id.as_uuid(), receiver);
@@ -149,6 +154,12 @@ pub async fn stream(
             }
             .into_string(),
         )),
+        Ok(ImporterHandleMessage::Subscribed(feed)) => Ok(Event::default().data(
+            html! {
+                li hx-target="#main-content" hx-swap="innerHTML" { "Subscribed to feed: " (feed_link(&feed)) }
+            }
+            .into_string(),
+        )),
         _ => Ok(Event::default()),
     });
     Ok(Sse::new(stream).keep_alive(
@@ -3,9 +3,12 @@ pub mod api;
 pub mod confirm_email;
 pub mod entries;
 pub mod entry;
+pub mod export;
 pub mod feed;
 pub mod feeds;
+pub mod fever;
 pub mod forgot_password;
+pub mod greader;
 pub mod home;
 pub mod import;
 pub mod log;
@@ -13,3 +16,6 @@ pub mod login;
 pub mod logout;
 pub mod register;
 pub mod reset_password;
+pub mod search;
+pub mod starred;
+pub mod websub;
@@ -0,0 +1,47 @@
+use axum::extract::{Query, State};
+use axum::response::Response;
+use axum_extra::TypedHeader;
+use maud::html;
+use sqlx::PgPool;
+
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
+use crate::htmx::HXTarget;
+use crate::models::entry::{Entry, GetEntriesOptions};
+use crate::partials::{layout::Layout, search_result_list::search_result_list};
+
+pub async fn get(
+    Query(mut options): Query<GetEntriesOptions>,
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    hx_target: Option<TypedHeader<HXTarget>>,
+    layout: Layout,
+) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    options.user_id = Some(user.user_id);
+    let q = options.q.clone().filter(|q| !q.trim().is_empty());
+    let results = match q {
+        Some(_) => Some(Entry::search(&pool, &options).await?),
+        None => None,
+    };
+    Ok(layout
+        .with_subtitle("search")
+        .targeted(hx_target)
+        .render(html! {
+            header { h2 class="mb-4 text-2xl font-medium" { "Search" } }
+            form action="/search" method="get" class="mb-6 flex flex-row gap-4 items-end max-w-xl" {
+                input
+                    type="search"
+                    name="q"
+                    value=[&q]
+                    placeholder="sqlx migrations"
+                    class="grow p-2 bg-gray-50 border border-gray-300 shadow-sm rounded-md focus:ring focus:ring-blue-500 focus:border-blue-500 focus:ring-opacity-50";
+                button type="submit" class="py-2 px-4 font-medium rounded-md border border-gray-200" { "Search" }
+            }
+            @if let Some(results) = results {
+                ul class="list-none flex flex-col gap-4" {
+                    (search_result_list(results, &options, true))
+                }
+            }
+        }))
+}
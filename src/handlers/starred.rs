@@ -0,0 +1,35 @@
+use axum::extract::State;
+use axum::response::Response;
+use axum_extra::TypedHeader;
+use maud::html;
+use sqlx::PgPool;
+
+use crate::auth::AuthSession;
+use crate::error::{Error, Result};
+use crate::htmx::HXTarget;
+use crate::models::entry::{Entry, GetEntriesOptions};
+use crate::partials::{entry_list::entry_list, layout::Layout};
+
+pub async fn get(
+    State(pool): State<PgPool>,
+    auth: AuthSession,
+    hx_target: Option<TypedHeader<HXTarget>>,
+    layout: Layout,
+) -> Result<Response> {
+    let user = auth.user.ok_or(Error::Unauthorized)?;
+    let options = GetEntriesOptions {
+        user_id: Some(user.user_id),
+        starred: true,
+        ..Default::default()
+    };
+    let entries = Entry::get_all(&pool, &options).await?;
+    Ok(layout
+        .with_subtitle("starred")
+        .targeted(hx_target)
+        .render(html! {
+            header { h2 class="mb-4 text-2xl font-medium" { "Starred" } }
+            ul class="list-none flex flex-col gap-4" {
+                (entry_list(entries, &options, true))
+            }
+        }))
+}
@@ -0,0 +1,103 @@
+use axum::body::Bytes;
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::{info, warn};
+
+use crate::actors::crawl_scheduler::CrawlSchedulerHandle;
+use crate::error::{Error, Result};
+use crate::models::websub_subscription::WebSubSubscription;
+use crate::uuid::Base62Uuid;
+use crate::websub::{verify_signature, MAX_WEBSUB_LEASE_SECONDS, WEBSUB_LEASE_SECONDS};
+
+#[derive(Debug, Deserialize)]
+pub struct Verification {
+    #[serde(rename = "hub.mode")]
+    mode: String,
+    #[serde(rename = "hub.topic")]
+    topic: String,
+    #[serde(rename = "hub.challenge")]
+    challenge: Option<String>,
+    #[serde(rename = "hub.lease_seconds")]
+    lease_seconds: Option<i64>,
+    #[serde(rename = "hub.reason")]
+    reason: Option<String>,
+}
+
+/// Hubs request the callback URL to verify the intent of (un)subscribing, which is confirmed by
+/// echoing back the challenge. Hubs also use it to tell crawlnicle a subscription was denied.
+pub async fn verify(
+    State(pool): State<PgPool>,
+    Path(id): Path<Base62Uuid>,
+    Query(verification): Query<Verification>,
+) -> Result<String> {
+    let feed_id = id.as_uuid();
+    let subscription = WebSubSubscription::get(&pool, feed_id).await;
+    match verification.mode.as_str() {
+        "subscribe" => {
+            let challenge = verification
+                .challenge
+                .ok_or(Error::BadRequest("missing hub.challenge"))?;
+            let subscription = subscription?;
+            if subscription.topic_url != verification.topic {
+                return Err(Error::NotFoundString("websub topic", verification.topic));
+            }
+            let lease_seconds = verification
+                .lease_seconds
+                .unwrap_or(WEBSUB_LEASE_SECONDS)
+                .clamp(0, MAX_WEBSUB_LEASE_SECONDS);
+            WebSubSubscription::verify(&pool, feed_id, lease_seconds).await?;
+            info!(%feed_id, lease_seconds, "verified websub subscription");
+            Ok(challenge)
+        }
+        "unsubscribe" => {
+            let challenge = verification
+                .challenge
+                .ok_or(Error::BadRequest("missing hub.challenge"))?;
+            // Only confirm unsubscribing from feeds crawlnicle is no longer subscribed to
+            if subscription.is_ok() {
+                return Err(Error::BadRequest("still subscribed to websub topic"));
+            }
+            Ok(challenge)
+        }
+        "denied" => {
+            let subscription = subscription?;
+            if subscription.topic_url != verification.topic {
+                return Err(Error::NotFoundString("websub topic", verification.topic));
+            }
+            warn!(%feed_id, reason = ?verification.reason, "websub subscription denied by hub");
+            WebSubSubscription::delete(&pool, feed_id).await?;
+            Ok(String::new())
+        }
+        _ => Err(Error::BadRequest("unsupported hub.mode")),
+    }
+}
+
+/// Hubs push new content of the feed to the callback URL, which is saved the same way as a crawl
+/// of the feed.
+///
+/// Content without a valid signature is acknowledged but ignored, as required by the WebSub spec.
+pub async fn receive(
+    State(pool): State<PgPool>,
+    State(crawl_scheduler): State<CrawlSchedulerHandle>,
+    Path(id): Path<Base62Uuid>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<StatusCode> {
+    let feed_id = id.as_uuid();
+    let subscription = WebSubSubscription::get(&pool, feed_id).await?;
+    let signature = headers
+        .get("X-Hub-Signature")
+        .and_then(|signature| signature.to_str().ok());
+    if !signature.is_some_and(|signature| verify_signature(&subscription.secret, signature, &body))
+    {
+        warn!(%feed_id, "ignoring websub content with invalid signature");
+        return Ok(StatusCode::ACCEPTED);
+    }
+
+    info!(%feed_id, "received websub content");
+    // The hub only needs to know the content was received, so don't wait for it to be saved
+    let _ = crawl_scheduler.ingest(feed_id, body).await;
+    Ok(StatusCode::ACCEPTED)
+}
@@ -2,10 +2,13 @@ use axum::http::{HeaderName, HeaderValue};
 use axum_extra::headers::{self, Header};
 
 /// Typed header implementation for the `Accept` header.
+///
+/// Decodes to the first media type in the header that is one of these, ignoring quality values.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Accept {
     TextHtml,
     ApplicationJson,
+    ApplicationProblemJson,
 }
 
 impl std::fmt::Display for Accept {
@@ -13,6 +16,7 @@ impl std::fmt::Display for Accept {
         match self {
             Accept::TextHtml => write!(f, "text/html"),
             Accept::ApplicationJson => write!(f, "application/json"),
+            Accept::ApplicationProblemJson => write!(f, "application/problem+json"),
         }
     }
 }
@@ -28,11 +32,19 @@ impl Header for Accept {
     {
         let value = values.next().ok_or_else(headers::Error::invalid)?;
 
-        match value.to_str().map_err(|_| headers::Error::invalid())? {
-            "text/html" => Ok(Accept::TextHtml),
-            "application/json" => Ok(Accept::ApplicationJson),
-            _ => Err(headers::Error::invalid()),
-        }
+        value
+            .to_str()
+            .map_err(|_| headers::Error::invalid())?
+            .split(',')
+            .find_map(
+                |media_type| match media_type.split(';').next().unwrap_or_default().trim() {
+                    "text/html" => Some(Accept::TextHtml),
+                    "application/json" => Some(Accept::ApplicationJson),
+                    "application/problem+json" => Some(Accept::ApplicationProblemJson),
+                    _ => None,
+                },
+            )
+            .ok_or_else(headers::Error::invalid)
     }
 
     fn encode<E>(&self, values: &mut E)
@@ -54,3 +66,30 @@ impl From<&Accept> for HeaderValue {
         HeaderValue::from_str(value.to_string().as_str()).unwrap()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum_extra::headers::HeaderMapExt;
+    use http::HeaderMap;
+
+    use super::*;
+
+    fn decode(value: &'static str) -> Option<Accept> {
+        let mut headers = HeaderMap::new();
+        headers.insert(http::header::ACCEPT, HeaderValue::from_static(value));
+        headers.typed_get::<Accept>()
+    }
+
+    #[test]
+    fn decodes_first_known_media_type() {
+        assert_eq!(
+            decode("text/html,application/xhtml+xml,*/*;q=0.8"),
+            Some(Accept::TextHtml)
+        );
+        assert_eq!(
+            decode("application/problem+json, application/json;q=0.9"),
+            Some(Accept::ApplicationProblemJson)
+        );
+        assert_eq!(decode("*/*"), None);
+    }
+}
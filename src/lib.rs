@@ -2,8 +2,10 @@ pub mod actors;
 pub mod api_response;
 pub mod auth;
 pub mod config;
+pub mod discovery;
 pub mod domain_locks;
 pub mod error;
+pub mod exporter;
 pub mod handlers;
 pub mod headers;
 pub mod htmx;
@@ -11,9 +13,11 @@ pub mod log;
 pub mod mailers;
 pub mod models;
 pub mod partials;
+pub mod robots_txt;
 pub mod state;
 pub mod utils;
 pub mod uuid;
+pub mod websub;
 
 pub const USER_AGENT: &str = "crawlnicle/0.1.0";
 pub const JS_MANIFEST: &str = include_str!("../static/js/manifest.txt");
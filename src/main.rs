@@ -2,6 +2,7 @@ use std::{collections::HashMap, net::SocketAddr, path::Path, sync::Arc};
 
 use anyhow::Result;
 use axum::{
+    middleware,
     routing::{get, post},
     Router,
 };
@@ -29,13 +30,19 @@ use tower_sessions::cookie::Key;
 use tower_sessions_redis_store::{fred::prelude::*, RedisStore};
 use tracing::debug;
 
+use lib::actors::entry_crawler::EntryCrawlerHandle;
 use lib::config::Config;
 use lib::domain_locks::DomainLocks;
 use lib::handlers;
 use lib::log::init_tracing;
+use lib::robots_txt::RobotsTxt;
 use lib::state::AppState;
 use lib::USER_AGENT;
-use lib::{actors::crawl_scheduler::CrawlSchedulerHandle, auth::Backend};
+use lib::{
+    actors::crawl_scheduler::CrawlSchedulerHandle,
+    auth::{self, Backend},
+    error,
+};
 use lib::{actors::importer::ImporterHandle, htmx::not_htmx_predicate};
 
 async fn serve(app: Router, addr: SocketAddr) -> Result<()> {
@@ -60,7 +67,7 @@ async fn main() -> Result<()> {
 
     let crawls = Arc::new(Mutex::new(HashMap::new()));
     let imports = Arc::new(Mutex::new(HashMap::new()));
-    let domain_locks = DomainLocks::new();
+    let robots_txt = RobotsTxt::new(config.robots_txt_check_feeds);
     let client = Client::builder().user_agent(USER_AGENT).build()?;
 
     let pool = PgPoolOptions::new()
@@ -92,41 +99,188 @@ async fn main() -> Result<()> {
         .build();
 
     sqlx::migrate!().run(&pool).await?;
+    let domain_locks = if config.shared_domain_locks {
+        DomainLocks::shared(pool.clone())
+    } else {
+        DomainLocks::new()
+    };
+    domain_locks.load(&pool).await?;
 
-    let crawl_scheduler = CrawlSchedulerHandle::new(
+    let entry_crawler = EntryCrawlerHandle::new(
         pool.clone(),
         client.clone(),
         domain_locks.clone(),
+        robots_txt.clone(),
         config.content_dir.clone(),
+        config.entry_crawl_concurrency,
+    );
+    let crawl_scheduler = CrawlSchedulerHandle::new(
+        pool.clone(),
+        client.clone(),
+        domain_locks.clone(),
+        robots_txt,
+        entry_crawler,
         crawls.clone(),
+        config.public_url.clone(),
     );
-    let _ = crawl_scheduler.bootstrap().await;
+    let _ = crawl_scheduler.bootstrap(config.crawl_workers).await;
     let importer = ImporterHandle::new(pool.clone(), crawl_scheduler.clone(), imports.clone());
 
     let ip_source_extension = config.ip_source.0.clone().into_extension();
 
     let addr = format!("{}:{}", &config.host, &config.port).parse()?;
-    let mut app = Router::new()
-        .route("/account", get(handlers::account::get))
-        .route_layer(login_required!(Backend, login_url = "/login"))
+    let state = AppState {
+        pool,
+        config,
+        log_receiver,
+        crawls,
+        domain_locks,
+        client,
+        crawl_scheduler,
+        importer,
+        imports,
+        mailer,
+    };
+
+    let api = Router::new()
         .route("/api/v1/feeds", get(handlers::api::feeds::get))
         .route("/api/v1/feed", post(handlers::api::feed::post))
-        .route("/api/v1/feed/:id", get(handlers::api::feed::get))
+        .route(
+            "/api/v1/feed/:id",
+            get(handlers::api::feed::get)
+                .patch(handlers::api::feed::patch)
+                .delete(handlers::api::feed::delete),
+        )
+        .route("/api/v1/feed/:id/read", post(handlers::api::feed::read))
+        .route("/api/v1/feed/:id/folder", post(handlers::api::feed::folder))
+        .route("/api/v1/feed/:id/crawls", get(handlers::api::feed::crawls))
+        .route("/api/v1/feed/:id/crawl", post(handlers::api::feed::crawl))
+        .route(
+            "/api/v1/feed/:id/entries",
+            get(handlers::api::feed::entries),
+        )
+        .route("/api/v1/folders", get(handlers::api::folders::get))
         .route("/api/v1/entries", get(handlers::api::entries::get))
+        .route("/api/v1/entries/read", post(handlers::api::entries::read))
         .route("/api/v1/entry", post(handlers::api::entry::post))
-        .route("/api/v1/entry/:id", get(handlers::api::entry::get))
+        .route(
+            "/api/v1/entry/:id",
+            get(handlers::api::entry::get)
+                .patch(handlers::api::entry::patch)
+                .delete(handlers::api::entry::delete),
+        )
+        .route("/api/v1/entry/:id/read", post(handlers::api::entry::read))
+        .route(
+            "/api/v1/entry/:id/unread",
+            post(handlers::api::entry::unread),
+        )
+        .route("/api/v1/entry/:id/star", post(handlers::api::entry::star))
+        .route(
+            "/api/v1/entry/:id/unstar",
+            post(handlers::api::entry::unstar),
+        )
+        .route_layer(middleware::from_fn_with_state(
+            state.pool.clone(),
+            auth::require_api_user,
+        ));
+    let mut app = Router::new()
+        .route("/account", get(handlers::account::get))
+        .route("/account/fever", post(handlers::account::fever))
+        .route(
+            "/account/fever/delete",
+            post(handlers::account::delete_fever),
+        )
+        .route(
+            "/account/greader/delete",
+            post(handlers::account::delete_greader_tokens),
+        )
+        .route("/account/tokens", post(handlers::account::create_api_token))
+        .route(
+            "/account/tokens/:id/delete",
+            post(handlers::account::delete_api_token),
+        )
         .route("/", get(handlers::home::get))
         .route("/feeds", get(handlers::feeds::get))
         .route("/feed", post(handlers::feed::post))
         .route("/feed/:id", get(handlers::feed::get))
         .route("/feed/:id/stream", get(handlers::feed::stream))
+        .route(
+            "/feed/:id/edit",
+            get(handlers::feed::edit).post(handlers::feed::update),
+        )
         .route("/feed/:id/delete", post(handlers::feed::delete))
+        .route("/feed/:id/read", post(handlers::feed::read))
+        .route("/feed/:id/pause", post(handlers::feed::pause))
+        .route("/feed/:id/resume", post(handlers::feed::resume))
+        .route("/feed/:id/folder", post(handlers::feed::folder))
         .route("/entries", get(handlers::entries::get))
+        .route("/entries/read", post(handlers::entries::read))
         .route("/entry/:id", get(handlers::entry::get))
-        .route("/log", get(handlers::log::get))
-        .route("/log/stream", get(handlers::log::stream))
+        .route("/entry/:id/read", post(handlers::entry::read))
+        .route("/entry/:id/unread", post(handlers::entry::unread))
+        .route("/entry/:id/star", post(handlers::entry::star))
+        .route("/entry/:id/unstar", post(handlers::entry::unstar))
+        .route("/starred", get(handlers::starred::get))
+        .route("/search", get(handlers::search::get))
         .route("/import/opml", post(handlers::import::opml))
+        .route("/export/opml", get(handlers::export::opml))
         .route("/import/:id/stream", get(handlers::import::stream))
+        .route_layer(login_required!(Backend, login_url = "/login"))
+        .merge(api)
+        .route("/api/v1/openapi.json", get(handlers::api::openapi::get))
+        .route("/api/v1/docs", get(handlers::api::openapi::docs))
+        .route(
+            "/fever",
+            get(handlers::fever::api).post(handlers::fever::api),
+        )
+        .route(
+            "/fever/",
+            get(handlers::fever::api).post(handlers::fever::api),
+        )
+        .route(
+            "/accounts/ClientLogin",
+            post(handlers::greader::client_login),
+        )
+        .route("/reader/api/0/token", get(handlers::greader::token))
+        .route("/reader/api/0/user-info", get(handlers::greader::user_info))
+        .route(
+            "/reader/api/0/subscription/list",
+            get(handlers::greader::subscription_list),
+        )
+        .route("/reader/api/0/tag/list", get(handlers::greader::tag_list))
+        .route(
+            "/reader/api/0/unread-count",
+            get(handlers::greader::unread_count),
+        )
+        .route(
+            "/reader/api/0/stream/contents",
+            get(handlers::greader::stream_contents_param)
+                .post(handlers::greader::stream_contents_param),
+        )
+        .route(
+            "/reader/api/0/stream/contents/*stream",
+            get(handlers::greader::stream_contents).post(handlers::greader::stream_contents),
+        )
+        .route(
+            "/reader/api/0/stream/items/ids",
+            get(handlers::greader::stream_items_ids).post(handlers::greader::stream_items_ids),
+        )
+        .route(
+            "/reader/api/0/stream/items/contents",
+            get(handlers::greader::stream_items_contents)
+                .post(handlers::greader::stream_items_contents),
+        )
+        .route("/reader/api/0/edit-tag", post(handlers::greader::edit_tag))
+        .route(
+            "/reader/api/0/mark-all-as-read",
+            post(handlers::greader::mark_all_as_read),
+        )
+        .route(
+            "/websub/:id",
+            get(handlers::websub::verify).post(handlers::websub::receive),
+        )
+        .route("/log", get(handlers::log::get))
+        .route("/log/stream", get(handlers::log::stream))
         .route("/login", get(handlers::login::get))
         .route("/login", post(handlers::login::post))
         .route("/logout", get(handlers::logout::get))
@@ -139,18 +293,11 @@ async fn main() -> Result<()> {
         .route("/reset-password", get(handlers::reset_password::get))
         .route("/reset-password", post(handlers::reset_password::post))
         .nest_service("/static", ServeDir::new("static"))
-        .with_state(AppState {
-            pool,
-            config,
-            log_receiver,
-            crawls,
-            domain_locks,
-            client,
-            crawl_scheduler,
-            importer,
-            imports,
-            mailer,
-        })
+        .with_state(state.clone())
+        .layer(middleware::from_fn_with_state(
+            state,
+            error::negotiate_error_response,
+        ))
         .layer(ServiceBuilder::new().layer(TraceLayer::new_for_http()))
         .layer(auth_layer)
         .layer(ip_source_extension);
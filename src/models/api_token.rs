@@ -0,0 +1,194 @@
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::{Error, Result};
+use crate::models::user::User;
+use crate::uuid::Base62Uuid;
+
+/// The prefix of every API token, so that leaked tokens are easy to recognize.
+pub const API_TOKEN_PREFIX: &str = "cn_";
+
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "api_token_scope", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum ApiTokenScope {
+    Read,
+    ReadWrite,
+}
+
+impl ApiTokenScope {
+    /// Whether a request with the method can be made with a token of this scope. Read-only tokens
+    /// can only make requests that do not change anything.
+    pub fn allows(&self, method: &Method) -> bool {
+        match self {
+            ApiTokenScope::Read => {
+                method == Method::GET || method == Method::HEAD || method == Method::OPTIONS
+            }
+            ApiTokenScope::ReadWrite => true,
+        }
+    }
+}
+
+/// A personal access token for the JSON API, sent as `Authorization: Bearer <token>`.
+///
+/// The token itself is only returned once from `create`, only its sha256 hash is stored. Tokens
+/// are random, so unlike passwords they don't need a slow hash, and a fast one lets every API
+/// request look its token up by hash.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiToken {
+    pub api_token_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    #[serde(skip_serializing)]
+    pub token_hash: String,
+    pub scope: ApiTokenScope,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateApiToken {
+    #[validate(length(
+        min = 1,
+        max = 255,
+        message = "name must be between 1 and 255 characters"
+    ))]
+    pub name: String,
+    pub scope: ApiTokenScope,
+}
+
+impl ApiToken {
+    /// Returns the tokens of the user, newest first.
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<ApiToken>> {
+        Ok(sqlx::query_as!(
+            ApiToken,
+            r#"select
+                api_token_id,
+                user_id,
+                name,
+                token_hash,
+                scope as "scope: ApiTokenScope",
+                last_used_at,
+                created_at,
+                updated_at
+            from api_token
+            where user_id = $1
+            order by created_at desc"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Creates a new token for the user and returns it along with the token itself.
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        payload: CreateApiToken,
+    ) -> Result<(ApiToken, String)> {
+        payload.validate()?;
+        let token = format!(
+            "{}{}{}",
+            API_TOKEN_PREFIX,
+            Base62Uuid::new(),
+            Base62Uuid::new()
+        );
+        let api_token = sqlx::query_as!(
+            ApiToken,
+            r#"insert into api_token (
+                user_id, name, token_hash, scope
+            ) values (
+                $1, $2, encode(sha256(convert_to($3, 'UTF8')), 'hex'), $4
+            ) returning
+                api_token_id,
+                user_id,
+                name,
+                token_hash,
+                scope as "scope: ApiTokenScope",
+                last_used_at,
+                created_at,
+                updated_at"#,
+            user_id,
+            payload.name,
+            token,
+            payload.scope as ApiTokenScope,
+        )
+        .fetch_one(db)
+        .await?;
+        Ok((api_token, token))
+    }
+
+    /// Finds the token and records that it was used.
+    pub async fn authenticate(
+        db: impl Executor<'_, Database = Postgres>,
+        token: &str,
+    ) -> Result<ApiToken> {
+        sqlx::query_as!(
+            ApiToken,
+            r#"update api_token set last_used_at = now()
+            where token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex')
+            returning
+                api_token_id,
+                user_id,
+                name,
+                token_hash,
+                scope as "scope: ApiTokenScope",
+                last_used_at,
+                created_at,
+                updated_at"#,
+            token
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::Unauthorized;
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    /// Returns the user the token belongs to, unless they were deleted.
+    pub async fn get_user(&self, db: impl Executor<'_, Database = Postgres>) -> Result<User> {
+        sqlx::query_as!(
+            User,
+            "select * from users where user_id = $1 and deleted_at is null",
+            self.user_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::Unauthorized;
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    /// Revokes the token of the user.
+    pub async fn delete(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        api_token_id: Uuid,
+    ) -> Result<()> {
+        let result = sqlx::query!(
+            "delete from api_token where api_token_id = $1 and user_id = $2",
+            api_token_id,
+            user_id
+        )
+        .execute(db)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(Error::NotFoundUuid("api_token", api_token_id));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// The Postgres channel notified with the feed id whenever a worker finishes crawling a feed, so
+/// that other processes can tell their listeners about crawls they did not run themselves.
+pub const CRAWL_JOB_DONE_CHANNEL: &str = "crawl_job_done";
+
+/// The next crawl of an active feed, claimed by one crawl worker at a time.
+///
+/// Jobs are claimed with `for update skip locked` so that any number of workers, in any number of
+/// processes, can poll the table without crawling a feed twice. A claimed job is leased to its
+/// worker until `locked_until`, which the worker extends with `heartbeat` while it crawls. If the
+/// worker dies, the job is claimed again once its lease expires.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CrawlJob {
+    pub feed_id: Uuid,
+    pub run_at: DateTime<Utc>,
+    pub locked_by: Option<Uuid>,
+    pub locked_until: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl CrawlJob {
+    /// Adds a job for the feed unless it already has one. Returns whether a job was added.
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        run_at: DateTime<Utc>,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            "insert into crawl_job (feed_id, run_at) values ($1, $2)
+            on conflict (feed_id) do nothing",
+            feed_id,
+            run_at
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Adds a job for the feed, or moves its existing job to `run_at`.
+    pub async fn upsert(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        run_at: DateTime<Utc>,
+    ) -> Result<CrawlJob> {
+        Ok(sqlx::query_as!(
+            CrawlJob,
+            "insert into crawl_job (feed_id, run_at) values ($1, $2)
+            on conflict (feed_id) do update set run_at = excluded.run_at
+            returning *",
+            feed_id,
+            run_at
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    pub async fn delete(db: impl Executor<'_, Database = Postgres>, feed_id: Uuid) -> Result<()> {
+        sqlx::query!("delete from crawl_job where feed_id = $1", feed_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Claims the job that has been due the longest and that no other worker holds a lease on,
+    /// leasing it to the worker for `lease_seconds`.
+    pub async fn claim(
+        db: impl Executor<'_, Database = Postgres>,
+        worker_id: Uuid,
+        lease_seconds: i32,
+    ) -> Result<Option<CrawlJob>> {
+        Ok(sqlx::query_as!(
+            CrawlJob,
+            "update crawl_job set
+                locked_by = $1,
+                locked_until = now() + make_interval(secs => $2)
+            where feed_id = (
+                select feed_id from crawl_job
+                where run_at <= now()
+                and (locked_until is null or locked_until < now())
+                order by run_at
+                limit 1
+                for update skip locked
+            )
+            returning *",
+            worker_id,
+            lease_seconds as f64
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// Extends the worker's lease on the job. Returns false if the worker lost the job, e.g.
+    /// because its lease expired and another worker claimed it, or because the feed was
+    /// unscheduled.
+    pub async fn heartbeat(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        worker_id: Uuid,
+        lease_seconds: i32,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            "update crawl_job set locked_until = now() + make_interval(secs => $3)
+            where feed_id = $1 and locked_by = $2",
+            feed_id,
+            worker_id,
+            lease_seconds as f64
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Releases the worker's lease on the job and schedules the next crawl at `run_at`, or removes
+    /// the job if there is no next crawl. Notifies `CRAWL_JOB_DONE_CHANNEL` either way.
+    pub async fn complete(
+        db: impl Executor<'_, Database = Postgres> + Copy,
+        feed_id: Uuid,
+        worker_id: Uuid,
+        run_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        match run_at {
+            Some(run_at) => {
+                sqlx::query!(
+                    "update crawl_job set run_at = $3, locked_by = null, locked_until = null
+                    where feed_id = $1 and locked_by = $2",
+                    feed_id,
+                    worker_id,
+                    run_at
+                )
+                .execute(db)
+                .await?;
+            }
+            None => {
+                sqlx::query!(
+                    "delete from crawl_job where feed_id = $1 and locked_by = $2",
+                    feed_id,
+                    worker_id
+                )
+                .execute(db)
+                .await?;
+            }
+        }
+        sqlx::query!(
+            "select pg_notify($1, $2)",
+            CRAWL_JOB_DONE_CHANNEL,
+            feed_id.to_string()
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
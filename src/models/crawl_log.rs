@@ -0,0 +1,123 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::error::Result;
+
+pub const DEFAULT_CRAWL_LOGS_PAGE_SIZE: i64 = 20;
+/// Crawl logs older than this are deleted when a feed is crawled.
+pub const CRAWL_LOG_RETENTION_DAYS: i64 = 30;
+
+/// A record of a single run of the `FeedCrawler` for a feed.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct CrawlLog {
+    pub crawl_log_id: Uuid,
+    pub feed_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: i32,
+    pub pushed: bool,
+    pub status_code: Option<i16>,
+    pub not_modified: bool,
+    pub bytes: Option<i64>,
+    pub new_entries: i32,
+    pub updated_entries: i32,
+    pub error: Option<String>,
+    pub error_message: Option<String>,
+    /// The url the feed permanently redirected to, which it is crawled from after this crawl.
+    pub moved_to: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Filled in by the `FeedCrawler` as a crawl progresses, and saved once it is done.
+#[derive(Debug, Default, Clone)]
+pub struct CreateCrawlLog {
+    pub feed_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: i32,
+    pub pushed: bool,
+    pub status_code: Option<i16>,
+    pub not_modified: bool,
+    pub bytes: Option<i64>,
+    pub new_entries: i32,
+    pub updated_entries: i32,
+    pub error: Option<String>,
+    pub error_message: Option<String>,
+    pub moved_to: Option<String>,
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct GetCrawlLogsOptions {
+    pub before: Option<DateTime<Utc>>,
+    pub limit: Option<i64>,
+}
+
+impl CrawlLog {
+    /// Returns the crawl logs of the feed, latest first.
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        options: &GetCrawlLogsOptions,
+    ) -> Result<Vec<CrawlLog>> {
+        Ok(sqlx::query_as!(
+            CrawlLog,
+            "select * from crawl_log
+            where feed_id = $1
+            and ($2::timestamptz is null or started_at < $2)
+            order by started_at desc
+            limit $3",
+            feed_id,
+            options.before,
+            options.limit.unwrap_or(DEFAULT_CRAWL_LOGS_PAGE_SIZE)
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        payload: CreateCrawlLog,
+    ) -> Result<CrawlLog> {
+        Ok(sqlx::query_as!(
+            CrawlLog,
+            "insert into crawl_log (
+                feed_id, started_at, duration_ms, pushed, status_code, not_modified, bytes,
+                new_entries, updated_entries, error, error_message, moved_to
+            ) values (
+                $1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12
+            ) returning *",
+            payload.feed_id,
+            payload.started_at,
+            payload.duration_ms,
+            payload.pushed,
+            payload.status_code,
+            payload.not_modified,
+            payload.bytes,
+            payload.new_entries,
+            payload.updated_entries,
+            payload.error,
+            payload.error_message,
+            payload.moved_to
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    /// Deletes the crawl logs of the feed that started before `before`.
+    pub async fn delete_before(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        before: DateTime<Utc>,
+    ) -> Result<()> {
+        sqlx::query!(
+            "delete from crawl_log where feed_id = $1 and started_at < $2",
+            feed_id,
+            before
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,50 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+
+use crate::error::Result;
+
+/// The delay between requests to a domain, for domains that need a different one than the
+/// default `DOMAIN_LOCK_DURATION`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DomainDelay {
+    pub domain: String,
+    pub delay_ms: i32,
+    pub retry_after: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl DomainDelay {
+    pub async fn get_all(db: impl Executor<'_, Database = Postgres>) -> Result<Vec<DomainDelay>> {
+        Ok(
+            sqlx::query_as!(DomainDelay, "select * from domain_delay order by domain")
+                .fetch_all(db)
+                .await?,
+        )
+    }
+
+    pub async fn upsert(
+        db: impl Executor<'_, Database = Postgres>,
+        domain: &str,
+        delay_ms: i32,
+        retry_after: Option<DateTime<Utc>>,
+    ) -> Result<DomainDelay> {
+        Ok(sqlx::query_as!(
+            DomainDelay,
+            "insert into domain_delay (
+                domain, delay_ms, retry_after
+            ) values (
+                $1, $2, $3
+            ) on conflict (domain) do update set
+                delay_ms = excluded.delay_ms,
+                retry_after = excluded.retry_after
+            returning *",
+            domain,
+            delay_ms,
+            retry_after
+        )
+        .fetch_one(db)
+        .await?)
+    }
+}
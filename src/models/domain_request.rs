@@ -0,0 +1,64 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+
+use crate::error::Result;
+
+/// When a domain was last requested by any process sharing the database.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DomainRequest {
+    pub domain: String,
+    pub last_request_at: DateTime<Utc>,
+}
+
+impl DomainRequest {
+    /// The earliest time the next request to the domain may be made: `delay_ms` (or the delay
+    /// set for the domain in the `domain_delay` table, if longer) after the last request to it,
+    /// and not before its `Retry-After` has passed.
+    ///
+    /// Returns `None` if the domain may be requested right away.
+    pub async fn next_request_at(
+        db: impl Executor<'_, Database = Postgres>,
+        domain: &str,
+        delay_ms: i32,
+    ) -> Result<Option<DateTime<Utc>>> {
+        Ok(sqlx::query_scalar!(
+            "select greatest(
+                domain_request.last_request_at
+                    + make_interval(secs => greatest(domain_delay.delay_ms, $2) / 1000.0),
+                domain_delay.retry_after
+            )
+            from (select $1::varchar as domain) as requested
+            left join domain_request on domain_request.domain = requested.domain
+            left join domain_delay on domain_delay.domain = requested.domain",
+            domain,
+            delay_ms
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    /// Records a request to the domain, unless another request to it was recorded less than
+    /// `delay_ms` (or the delay set for the domain in the `domain_delay` table, if longer) ago.
+    ///
+    /// Returns whether the request was recorded, i.e. whether it may be made now.
+    pub async fn claim(
+        db: impl Executor<'_, Database = Postgres>,
+        domain: &str,
+        delay_ms: i32,
+    ) -> Result<bool> {
+        Ok(sqlx::query_scalar!(
+            "insert into domain_request (domain) values ($1)
+            on conflict (domain) do update set last_request_at = now()
+            where domain_request.last_request_at + make_interval(secs => greatest(
+                (select delay_ms from domain_delay where domain_delay.domain = $1), $2
+            ) / 1000.0) <= now()
+            returning last_request_at",
+            domain,
+            delay_ms
+        )
+        .fetch_optional(db)
+        .await?
+        .is_some())
+    }
+}
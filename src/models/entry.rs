@@ -1,6 +1,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Executor, Postgres};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::{Validate, ValidationErrors};
 
@@ -8,7 +9,13 @@ use crate::error::{Error, Result};
 
 pub const DEFAULT_ENTRIES_PAGE_SIZE: i64 = 50;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+/// Delimiters `ts_headline` puts around matched words in search snippets. Control characters are
+/// used so they can't be confused with anything in the entry's text, and are replaced with `<mark>`
+/// tags once the rest of the snippet has been escaped.
+const SNIPPET_START_SEL: &str = "\u{2}";
+const SNIPPET_STOP_SEL: &str = "\u{3}";
+
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
 pub struct Entry {
     pub entry_id: Uuid,
     pub title: Option<String>,
@@ -21,9 +28,11 @@ pub struct Entry {
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// The user who added the entry through the API, or null if it was crawled from its feed.
+    pub created_by: Option<Uuid>,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Serialize, Deserialize, Validate, ToSchema)]
 pub struct CreateEntry {
     #[validate(length(max = 255))]
     pub title: Option<String>,
@@ -33,14 +42,144 @@ pub struct CreateEntry {
     pub description: Option<String>,
     pub feed_id: Uuid,
     pub published_at: DateTime<Utc>,
+    /// The user adding the entry. Set by handlers from the logged-in user, never from the request
+    /// body.
+    #[serde(skip)]
+    pub created_by: Option<Uuid>,
+}
+
+/// The fields of an entry to change. Fields that are missing are left as they are, while
+/// nullable fields that are `null` are cleared.
+#[derive(Debug, Default, Serialize, Deserialize, Validate, ToSchema)]
+pub struct UpdateEntry {
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[schema(value_type = Option<String>)]
+    #[validate(length(max = 255))]
+    pub title: Option<Option<String>>,
+    #[validate(url)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[schema(value_type = Option<String>)]
+    #[validate(length(max = 524288))]
+    pub description: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+impl UpdateEntry {
+    /// Returns the entry with the fields of the update applied.
+    pub fn apply(self, mut entry: Entry) -> Entry {
+        if let Some(title) = self.title {
+            entry.title = title;
+        }
+        if let Some(url) = self.url {
+            entry.url = url;
+        }
+        if let Some(description) = self.description {
+            entry.description = description;
+        }
+        if let Some(published_at) = self.published_at {
+            entry.published_at = published_at;
+        }
+        entry
+    }
 }
 
-#[derive(Default, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetEntriesOptions {
     pub feed_id: Option<Uuid>,
+    /// Only return entries of feeds in the user's folder.
+    pub folder_id: Option<Uuid>,
     pub published_before: Option<DateTime<Utc>>,
     pub id_before: Option<Uuid>,
+    /// Only return entries published after this time.
+    pub published_after: Option<DateTime<Utc>>,
     pub limit: Option<i64>,
+    /// Full-text search query. When set, entries are ordered by how well they match instead of by
+    /// publish date, and paginated with `offset` instead of `published_before` and `id_before`.
+    pub q: Option<String>,
+    pub offset: Option<i64>,
+    /// Only return entries the user has not read yet.
+    #[serde(default)]
+    pub unread: bool,
+    /// Only return entries the user has starred, including those of feeds they are no longer
+    /// subscribed to.
+    #[serde(default)]
+    pub starred: bool,
+    /// Only return entries of feeds the user is subscribed to. Set by handlers from the logged-in
+    /// user, never from the query string.
+    #[serde(skip)]
+    pub user_id: Option<Uuid>,
+}
+
+/// An entry matching a full-text search, with its search rank and a snippet of the matching text.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct EntrySearchResult {
+    #[serde(flatten)]
+    pub entry: Entry,
+    pub rank: f32,
+    /// HTML-escaped text around the matched words, which are wrapped in `<mark>` tags.
+    pub snippet: String,
+}
+
+struct EntrySearchRow {
+    entry_id: Uuid,
+    title: Option<String>,
+    url: String,
+    description: Option<String>,
+    feed_id: Uuid,
+    etag_header: Option<String>,
+    last_modified_header: Option<String>,
+    published_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    updated_at: Option<DateTime<Utc>>,
+    deleted_at: Option<DateTime<Utc>>,
+    created_by: Option<Uuid>,
+    rank: f32,
+    snippet: String,
+}
+
+impl From<EntrySearchRow> for EntrySearchResult {
+    fn from(row: EntrySearchRow) -> Self {
+        EntrySearchResult {
+            entry: Entry {
+                entry_id: row.entry_id,
+                title: row.title,
+                url: row.url,
+                description: row.description,
+                feed_id: row.feed_id,
+                etag_header: row.etag_header,
+                last_modified_header: row.last_modified_header,
+                published_at: row.published_at,
+                created_at: row.created_at,
+                updated_at: row.updated_at,
+                deleted_at: row.deleted_at,
+                created_by: row.created_by,
+            },
+            rank: row.rank,
+            snippet: highlight_snippet(&row.snippet),
+        }
+    }
+}
+
+fn highlight_snippet(snippet: &str) -> String {
+    snippet
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace(SNIPPET_START_SEL, "<mark>")
+        .replace(SNIPPET_STOP_SEL, "</mark>")
 }
 
 impl Entry {
@@ -56,6 +195,35 @@ impl Entry {
             })
     }
 
+    /// Gets the entry if the user is subscribed to its feed or has starred it.
+    pub async fn get_for_user(
+        db: impl Executor<'_, Database = Postgres>,
+        entry_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Entry> {
+        sqlx::query_as!(
+            Entry,
+            "select * from entry
+                where entry_id = $1
+                and (
+                    feed_id in (select feed_id from subscription where user_id = $2)
+                    or exists (
+                        select 1 from entry_star where entry_id = $1 and user_id = $2
+                    )
+                )",
+            entry_id,
+            user_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("entry", entry_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
     pub async fn get_all(
         db: impl Executor<'_, Database = Postgres>,
         options: &GetEntriesOptions,
@@ -67,6 +235,21 @@ impl Entry {
                         Entry,
                         "select * from entry
                             where deleted_at is null
+                            and ($9::timestamptz is null or published_at > $9)
+                            and ($5::uuid is null or $7::bool or feed_id in (
+                                select feed_id from subscription where user_id = $5
+                            ))
+                            and ($8::uuid is null or feed_id in (
+                                select feed_id from subscription where user_id = $5 and folder_id = $8
+                            ))
+                            and (not $6::bool or not exists (
+                                select 1 from entry_read
+                                where entry_read.entry_id = entry.entry_id and entry_read.user_id = $5
+                            ))
+                            and (not $7::bool or exists (
+                                select 1 from entry_star
+                                where entry_star.entry_id = entry.entry_id and entry_star.user_id = $5
+                            ))
                             and feed_id = $1
                             and (published_at, entry_id) < ($2, $3)
                             order by published_at desc, entry_id desc
@@ -75,7 +258,12 @@ impl Entry {
                         feed_id,
                         published_before,
                         id_before,
-                        options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE)
+                        options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+                        options.user_id,
+                        options.unread,
+                        options.starred,
+                        options.folder_id,
+                        options.published_after
                     )
                     .fetch_all(db)
                     .await
@@ -84,6 +272,21 @@ impl Entry {
                         Entry,
                         "select * from entry
                             where deleted_at is null
+                            and ($8::timestamptz is null or published_at > $8)
+                            and ($4::uuid is null or $6::bool or feed_id in (
+                                select feed_id from subscription where user_id = $4
+                            ))
+                            and ($7::uuid is null or feed_id in (
+                                select feed_id from subscription where user_id = $4 and folder_id = $7
+                            ))
+                            and (not $5::bool or not exists (
+                                select 1 from entry_read
+                                where entry_read.entry_id = entry.entry_id and entry_read.user_id = $4
+                            ))
+                            and (not $6::bool or exists (
+                                select 1 from entry_star
+                                where entry_star.entry_id = entry.entry_id and entry_star.user_id = $4
+                            ))
                             and feed_id = $1
                             and published_at < $2
                             order by published_at desc
@@ -91,7 +294,12 @@ impl Entry {
                         ",
                         feed_id,
                         published_before,
-                        options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE)
+                        options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+                        options.user_id,
+                        options.unread,
+                        options.starred,
+                        options.folder_id,
+                        options.published_after
                     )
                     .fetch_all(db)
                     .await
@@ -101,12 +309,32 @@ impl Entry {
                     Entry,
                     "select * from entry
                         where deleted_at is null
+                        and ($7::timestamptz is null or published_at > $7)
+                        and ($3::uuid is null or $5::bool or feed_id in (
+                            select feed_id from subscription where user_id = $3
+                        ))
+                        and ($6::uuid is null or feed_id in (
+                            select feed_id from subscription where user_id = $3 and folder_id = $6
+                        ))
+                        and (not $4::bool or not exists (
+                            select 1 from entry_read
+                            where entry_read.entry_id = entry.entry_id and entry_read.user_id = $3
+                        ))
+                        and (not $5::bool or exists (
+                            select 1 from entry_star
+                            where entry_star.entry_id = entry.entry_id and entry_star.user_id = $3
+                        ))
                         and feed_id = $1
                         order by published_at desc
                         limit $2
                     ",
                     feed_id,
-                    options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE)
+                    options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+                    options.user_id,
+                    options.unread,
+                    options.starred,
+                    options.folder_id,
+                    options.published_after
                 )
                 .fetch_all(db)
                 .await
@@ -117,13 +345,33 @@ impl Entry {
                     Entry,
                     "select * from entry
                         where deleted_at is null
+                        and ($8::timestamptz is null or published_at > $8)
+                        and ($4::uuid is null or $6::bool or feed_id in (
+                            select feed_id from subscription where user_id = $4
+                        ))
+                        and ($7::uuid is null or feed_id in (
+                            select feed_id from subscription where user_id = $4 and folder_id = $7
+                        ))
+                        and (not $5::bool or not exists (
+                            select 1 from entry_read
+                            where entry_read.entry_id = entry.entry_id and entry_read.user_id = $4
+                        ))
+                        and (not $6::bool or exists (
+                            select 1 from entry_star
+                            where entry_star.entry_id = entry.entry_id and entry_star.user_id = $4
+                        ))
                         and (published_at, entry_id) < ($1, $2)
                         order by published_at desc, entry_id desc
                         limit $3
                     ",
                     published_before,
                     id_before,
-                    options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE)
+                    options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+                    options.user_id,
+                    options.unread,
+                    options.starred,
+                    options.folder_id,
+                    options.published_after
                 )
                 .fetch_all(db)
                 .await
@@ -132,12 +380,32 @@ impl Entry {
                     Entry,
                     "select * from entry
                         where deleted_at is null
+                        and ($7::timestamptz is null or published_at > $7)
+                        and ($3::uuid is null or $5::bool or feed_id in (
+                            select feed_id from subscription where user_id = $3
+                        ))
+                        and ($6::uuid is null or feed_id in (
+                            select feed_id from subscription where user_id = $3 and folder_id = $6
+                        ))
+                        and (not $4::bool or not exists (
+                            select 1 from entry_read
+                            where entry_read.entry_id = entry.entry_id and entry_read.user_id = $3
+                        ))
+                        and (not $5::bool or exists (
+                            select 1 from entry_star
+                            where entry_star.entry_id = entry.entry_id and entry_star.user_id = $3
+                        ))
                         and published_at < $1
                         order by published_at desc
                         limit $2
                     ",
                     published_before,
-                    options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE)
+                    options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+                    options.user_id,
+                    options.unread,
+                    options.starred,
+                    options.folder_id,
+                    options.published_after
                 )
                 .fetch_all(db)
                 .await
@@ -147,16 +415,128 @@ impl Entry {
                 Entry,
                 "select * from entry
                     where deleted_at is null
+                    and ($6::timestamptz is null or published_at > $6)
+                    and ($2::uuid is null or $4::bool or feed_id in (
+                        select feed_id from subscription where user_id = $2
+                    ))
+                    and ($5::uuid is null or feed_id in (
+                        select feed_id from subscription where user_id = $2 and folder_id = $5
+                    ))
+                    and (not $3::bool or not exists (
+                        select 1 from entry_read
+                        where entry_read.entry_id = entry.entry_id and entry_read.user_id = $2
+                    ))
+                    and (not $4::bool or exists (
+                        select 1 from entry_star
+                        where entry_star.entry_id = entry.entry_id and entry_star.user_id = $2
+                    ))
                     order by published_at desc
                     limit $1
                 ",
-                options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE)
+                options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+                options.user_id,
+                options.unread,
+                options.starred,
+                options.folder_id,
+                options.published_after
             )
             .fetch_all(db)
             .await
         }
     }
 
+    /// Searches the title, description and extracted content of the entries matching the options
+    /// for `options.q`, best matches first.
+    pub async fn search(
+        db: impl Executor<'_, Database = Postgres>,
+        options: &GetEntriesOptions,
+    ) -> Result<Vec<EntrySearchResult>> {
+        let headline_options = format!(
+            "StartSel={}, StopSel={}, MaxFragments=2, MinWords=10, MaxWords=30",
+            SNIPPET_START_SEL, SNIPPET_STOP_SEL
+        );
+        let rows = sqlx::query_as!(
+            EntrySearchRow,
+            r#"select
+                entry.entry_id,
+                entry.title,
+                entry.url,
+                entry.description,
+                entry.feed_id,
+                entry.etag_header,
+                entry.last_modified_header,
+                entry.published_at,
+                entry.created_at,
+                entry.updated_at,
+                entry.deleted_at,
+                entry.created_by,
+                ts_rank(entry_search.search_vector, query) as "rank!",
+                ts_headline(
+                    'english',
+                    concat_ws(' ', entry.title, entry.description, entry_search.content_text),
+                    query,
+                    $2
+                ) as "snippet!"
+            from entry
+            join entry_search using (entry_id)
+            cross join websearch_to_tsquery('english', $1) as query
+            where entry.deleted_at is null
+            and entry_search.search_vector @@ query
+            and ($3::uuid is null or $4::bool or entry.feed_id in (
+                select feed_id from subscription where user_id = $3
+            ))
+            and (not $5::bool or not exists (
+                select 1 from entry_read
+                where entry_read.entry_id = entry.entry_id and entry_read.user_id = $3
+            ))
+            and (not $4::bool or exists (
+                select 1 from entry_star
+                where entry_star.entry_id = entry.entry_id and entry_star.user_id = $3
+            ))
+            and ($6::uuid is null or entry.feed_id in (
+                select feed_id from subscription where user_id = $3 and folder_id = $6
+            ))
+            and ($7::uuid is null or entry.feed_id = $7)
+            order by "rank!" desc, entry.published_at desc, entry.entry_id desc
+            limit $8
+            offset $9
+            "#,
+            options.q.as_deref().unwrap_or_default(),
+            headline_options,
+            options.user_id,
+            options.starred,
+            options.unread,
+            options.folder_id,
+            options.feed_id,
+            options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE),
+            options.offset.unwrap_or(0),
+        )
+        .fetch_all(db)
+        .await?;
+        Ok(rows.into_iter().map(EntrySearchResult::from).collect())
+    }
+
+    /// Adds the text extracted from the entry's page to the entry's full-text search index.
+    pub async fn update_content_text(
+        db: impl Executor<'_, Database = Postgres>,
+        entry_id: Uuid,
+        content_text: &str,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"update entry_search set
+                content_text = $2,
+                search_vector = entry_search_vector(entry.title, entry.description, $2)
+            from entry
+            where entry_search.entry_id = $1
+            and entry.entry_id = $1"#,
+            entry_id,
+            content_text
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
     pub async fn create(
         db: impl Executor<'_, Database = Postgres>,
         payload: CreateEntry,
@@ -165,15 +545,16 @@ impl Entry {
         sqlx::query_as!(
             Entry,
             "insert into entry (
-                title, url, description, feed_id, published_at
+                title, url, description, feed_id, published_at, created_by
             ) values (
-                $1, $2, $3, $4, $5
+                $1, $2, $3, $4, $5, $6
             ) returning *",
             payload.title,
             payload.url,
             payload.description,
             payload.feed_id,
             payload.published_at,
+            payload.created_by,
         )
         .fetch_one(db)
         .await
@@ -265,6 +646,8 @@ impl Entry {
         })
     }
 
+    /// Creates the entries, or updates the existing entries of the feed with the same urls. Entries
+    /// a user added themselves are left as they are.
     pub async fn bulk_upsert(
         db: impl Executor<'_, Database = Postgres>,
         payload: Vec<CreateEntry>,
@@ -294,6 +677,7 @@ impl Entry {
                 title = excluded.title,
                 description = excluded.description,
                 published_at = excluded.published_at
+            where entry.created_by is null
             returning *",
             titles.as_slice() as &[Option<String>],
             urls.as_slice(),
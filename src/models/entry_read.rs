@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// Marks an entry as read by a user. Entries are unread for a user until one of these exists.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntryRead {
+    pub user_id: Uuid,
+    pub entry_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MarkReadBefore {
+    pub published_before: DateTime<Utc>,
+}
+
+impl EntryRead {
+    /// Returns which of the given entries the user has read.
+    pub async fn get_read_ids(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>> {
+        Ok(sqlx::query_scalar!(
+            r#"select entry_id from entry_read
+            where user_id = $1 and entry_id = any($2)"#,
+            user_id,
+            entry_ids
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Marks the entry as read by the user. Does nothing if the entry is already read.
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"insert into entry_read (
+                user_id, entry_id
+            ) values (
+                $1, $2
+            ) on conflict do nothing"#,
+            user_id,
+            entry_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Marks every entry of the feed as read by the user. Returns the number of newly read entries.
+    pub async fn create_all_in_feed(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        feed_id: Uuid,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"insert into entry_read (user_id, entry_id)
+            select $1, entry_id from entry
+            where deleted_at is null
+            and feed_id = $2
+            on conflict do nothing"#,
+            user_id,
+            feed_id
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Marks every entry published before the given time in the feeds the user is subscribed to as
+    /// read by the user. Returns the number of newly read entries.
+    pub async fn create_all_before(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        published_before: DateTime<Utc>,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"insert into entry_read (user_id, entry_id)
+            select $1, entry_id from entry
+            where deleted_at is null
+            and feed_id in (select feed_id from subscription where user_id = $1)
+            and published_at < $2
+            on conflict do nothing"#,
+            user_id,
+            published_before
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Marks every entry of the feed published before the given time as read by the user. Returns
+    /// the number of newly read entries.
+    pub async fn create_all_in_feed_before(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        feed_id: Uuid,
+        published_before: DateTime<Utc>,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"insert into entry_read (user_id, entry_id)
+            select $1, entry_id from entry
+            where deleted_at is null
+            and feed_id = $2
+            and published_at < $3
+            on conflict do nothing"#,
+            user_id,
+            feed_id,
+            published_before
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Marks every entry of the feeds in the user's folder published before the given time as
+    /// read by the user. Returns the number of newly read entries.
+    pub async fn create_all_in_folder_before(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        folder_id: Uuid,
+        published_before: DateTime<Utc>,
+    ) -> Result<u64> {
+        let result = sqlx::query!(
+            r#"insert into entry_read (user_id, entry_id)
+            select $1, entry_id from entry
+            where deleted_at is null
+            and feed_id in (
+                select feed_id from subscription where user_id = $1 and folder_id = $2
+            )
+            and published_at < $3
+            on conflict do nothing"#,
+            user_id,
+            folder_id,
+            published_before
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    /// Marks the entry as unread by the user.
+    pub async fn delete(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"delete from entry_read
+            where user_id = $1 and entry_id = $2"#,
+            user_id,
+            entry_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
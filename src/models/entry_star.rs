@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// An entry a user has starred to keep around.
+///
+/// Starred entries (and their content) are kept even if the user unsubscribes from the feed or the
+/// feed is deleted.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EntryStar {
+    pub user_id: Uuid,
+    pub entry_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EntryStar {
+    pub async fn get(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_id: Uuid,
+    ) -> Result<Option<EntryStar>> {
+        Ok(sqlx::query_as!(
+            EntryStar,
+            r#"select * from entry_star where user_id = $1 and entry_id = $2"#,
+            user_id,
+            entry_id
+        )
+        .fetch_optional(db)
+        .await?)
+    }
+
+    /// Returns which of the given entries the user has starred.
+    pub async fn get_starred_ids(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_ids: &[Uuid],
+    ) -> Result<Vec<Uuid>> {
+        Ok(sqlx::query_scalar!(
+            r#"select entry_id from entry_star
+            where user_id = $1 and entry_id = any($2)"#,
+            user_id,
+            entry_ids
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Stars the entry for the user. Does nothing if the entry is already starred.
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"insert into entry_star (
+                user_id, entry_id
+            ) values (
+                $1, $2
+            ) on conflict do nothing"#,
+            user_id,
+            entry_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        entry_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query!(
+            r#"delete from entry_star
+            where user_id = $1 and entry_id = $2"#,
+            user_id,
+            entry_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
@@ -1,16 +1,19 @@
 use std::str::FromStr;
 
+use base64::prelude::*;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{postgres::PgQueryResult, Executor, FromRow, Postgres};
+use sqlx::{postgres::PgQueryResult, Executor, FromRow, PgPool, Postgres};
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 use validator::Validate;
 
 use crate::error::{Error, Result};
+use crate::models::crawl_job::CrawlJob;
 
 pub const DEFAULT_FEEDS_PAGE_SIZE: i64 = 50;
 
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, Copy, ToSchema)]
 #[sqlx(type_name = "feed_type", rename_all = "lowercase")]
 #[serde(rename_all = "lowercase")]
 pub enum FeedType {
@@ -51,8 +54,13 @@ impl From<feed_rs::model::FeedType> for FeedType {
 
 pub const MIN_CRAWL_INTERVAL_MINUTES: i32 = 1;
 pub const MAX_CRAWL_INTERVAL_MINUTES: i32 = 5040;
+/// The crawl interval of failing feeds is doubled for each consecutive failure up to this maximum
+/// (unless the feed's normal interval is already longer).
+pub const MAX_FAILURE_BACKOFF_MINUTES: i32 = 60 * 24;
+/// Feeds that have failed every crawl for this many days are paused.
+pub const AUTO_PAUSE_AFTER_FAILING_DAYS: i32 = 14;
 
-#[derive(Debug, Serialize, Deserialize, Clone, FromRow)]
+#[derive(Debug, Serialize, Deserialize, Clone, FromRow, ToSchema)]
 pub struct Feed {
     pub feed_id: Uuid,
     pub title: Option<String>,
@@ -66,12 +74,30 @@ pub struct Feed {
     pub last_modified_header: Option<String>,
     pub last_crawled_at: Option<DateTime<Utc>>,
     pub last_entry_published_at: Option<DateTime<Utc>>,
+    /// Url of the website the feed belongs to, from the feed's alternate link.
+    pub site_url: Option<String>,
+    /// Set when the feed responded with 410 Gone, after which it is no longer crawled.
+    pub inactive_at: Option<DateTime<Utc>>,
+    /// The number of crawls in a row that failed, which backs off the crawl interval.
+    pub consecutive_failures: i32,
+    /// When the first of the consecutive failed crawls happened.
+    pub failing_since: Option<DateTime<Utc>>,
+    /// Set when a user paused the feed, or when it has been failing for
+    /// `AUTO_PAUSE_AFTER_FAILING_DAYS`, after which it is no longer crawled until a user resumes it.
+    pub paused_at: Option<DateTime<Utc>>,
+    /// Set when a user edited the title, which crawls then no longer overwrite.
+    pub title_pinned: bool,
+    /// Set when a user edited the description, which crawls then no longer overwrite.
+    pub description_pinned: bool,
+    /// Set when a user chose the crawl interval, which crawls then no longer adjust to how often
+    /// the feed is updated.
+    pub crawl_interval_pinned: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize, Default, Validate)]
+#[derive(Debug, Serialize, Deserialize, Default, Validate, ToSchema)]
 pub struct CreateFeed {
     #[validate(length(max = 255))]
     pub title: Option<String>,
@@ -94,22 +120,69 @@ pub struct UpsertFeed {
     pub last_crawled_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize, Default, Validate)]
+/// The fields of a feed an admin can edit. Fields left out are not changed.
+///
+/// Editing the title, description, or crawl interval pins it, so that crawls no longer overwrite
+/// it. Setting it to `null` unpins it again: the title and description are filled in from the feed
+/// on its next crawl, and the crawl interval is adjusted to how often the feed is updated again.
+#[derive(Debug, Serialize, Deserialize, Default, Validate, ToSchema)]
 pub struct UpdateFeed {
     #[validate(length(max = 255))]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[schema(value_type = Option<String>)]
     pub title: Option<Option<String>>,
     #[validate(url)]
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub url: Option<String>,
-    pub feed_type: Option<FeedType>,
     #[validate(length(max = 524288))]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[schema(value_type = Option<String>)]
     pub description: Option<Option<String>>,
-    pub crawl_interval_minutes: Option<i32>,
-    pub last_crawl_error: Option<Option<String>>,
-    pub last_crawled_at: Option<Option<DateTime<Utc>>>,
-    pub last_entry_published_at: Option<Option<DateTime<Utc>>>,
+    #[validate(range(min = MIN_CRAWL_INTERVAL_MINUTES, max = MAX_CRAWL_INTERVAL_MINUTES))]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "::serde_with::rust::double_option"
+    )]
+    #[schema(value_type = Option<i32>, minimum = 1, maximum = 5040)]
+    pub crawl_interval_minutes: Option<Option<i32>>,
+}
+
+impl UpdateFeed {
+    /// Whether the update changes when the feed should be crawled next, so that it needs to be
+    /// rescheduled.
+    pub fn affects_schedule(&self) -> bool {
+        self.url.is_some() || self.crawl_interval_minutes.is_some()
+    }
+}
+
+/// How `Feed::create_or_restore` got the feed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedAddition {
+    /// No feed with the url existed, so it was created.
+    Created,
+    /// The feed had been deleted once no one was subscribed to it anymore, so it was undeleted.
+    Restored,
+    /// Someone is already subscribed to the feed, so it is already scheduled to be crawled.
+    Existing,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl FeedAddition {
+    /// Whether the feed isn't scheduled to be crawled yet.
+    pub fn needs_schedule(&self) -> bool {
+        *self != FeedAddition::Existing
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema)]
 pub enum GetFeedsSort {
     Title,
     CreatedAt,
@@ -117,22 +190,114 @@ pub enum GetFeedsSort {
     LastEntryPublishedAt,
 }
 
-#[derive(Debug, Default, Clone, Deserialize)]
+impl GetFeedsSort {
+    /// The name of the sort in query strings.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GetFeedsSort::Title => "Title",
+            GetFeedsSort::CreatedAt => "CreatedAt",
+            GetFeedsSort::LastCrawledAt => "LastCrawledAt",
+            GetFeedsSort::LastEntryPublishedAt => "LastEntryPublishedAt",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
 pub struct GetFeedsOptions {
     pub sort: Option<GetFeedsSort>,
-    pub before: Option<DateTime<Utc>>,
-    pub after_title: Option<String>,
-    pub before_id: Option<Uuid>,
+    /// Where the previous page ended, from `FeedsPage::next_cursor`. Only valid with the same sort.
+    pub cursor: Option<String>,
     pub limit: Option<i64>,
+    /// Only return feeds the user is subscribed to. Set by handlers from the logged-in user,
+    /// never from the query string.
+    #[serde(skip)]
+    pub user_id: Option<Uuid>,
+}
+
+impl GetFeedsOptions {
+    pub fn sort(&self) -> GetFeedsSort {
+        self.sort.unwrap_or(GetFeedsSort::CreatedAt)
+    }
+
+    /// The cursor of the page after `feeds`, or `None` if `feeds` is the last page.
+    pub fn next_cursor(&self, feeds: &[Feed]) -> Option<String> {
+        if (feeds.len() as i64) < self.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE) {
+            return None;
+        }
+        let feed = feeds.last()?;
+        let key = match self.sort() {
+            GetFeedsSort::Title => feed.title.clone().unwrap_or_default(),
+            GetFeedsSort::CreatedAt => feed.created_at.to_rfc3339(),
+            GetFeedsSort::LastCrawledAt => feed
+                .last_crawled_at
+                .unwrap_or(DateTime::UNIX_EPOCH)
+                .to_rfc3339(),
+            GetFeedsSort::LastEntryPublishedAt => feed
+                .last_entry_published_at
+                .unwrap_or(DateTime::UNIX_EPOCH)
+                .to_rfc3339(),
+        };
+        Some(encode_cursor(&key, feed.feed_id))
+    }
+}
+
+/// A page of feeds returned by the API, with the cursor to pass to get the next page.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct FeedsPage {
+    pub feeds: Vec<Feed>,
+    pub next_cursor: Option<String>,
+}
+
+/// Cursors are the sorted value of the last feed of a page and its id (which breaks ties), encoded
+/// so that clients treat them as opaque.
+fn encode_cursor(key: &str, feed_id: Uuid) -> String {
+    BASE64_URL_SAFE_NO_PAD.encode(format!("{}\t{}", key, feed_id))
+}
+
+fn decode_cursor(cursor: &str) -> Option<(String, Uuid)> {
+    let cursor = String::from_utf8(BASE64_URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+    let (key, feed_id) = cursor.rsplit_once('\t')?;
+    Some((key.to_owned(), Uuid::parse_str(feed_id).ok()?))
+}
+
+fn time_cursor(cursor: Option<(String, Uuid)>) -> Result<(Option<DateTime<Utc>>, Option<Uuid>)> {
+    let Some((key, feed_id)) = cursor else {
+        return Ok((None, None));
+    };
+    let time =
+        DateTime::parse_from_rfc3339(&key).map_err(|_| Error::BadRequest("invalid cursor"))?;
+    Ok((Some(time.with_timezone(&Utc)), Some(feed_id)))
+}
+
+fn backoff_interval_minutes(crawl_interval_minutes: i32, consecutive_failures: i32) -> i32 {
+    if consecutive_failures <= 0 {
+        return crawl_interval_minutes;
+    }
+    let max = MAX_FAILURE_BACKOFF_MINUTES.max(crawl_interval_minutes);
+    crawl_interval_minutes
+        .checked_mul(1 << consecutive_failures.min(30))
+        .map_or(max, |backoff| backoff.min(max))
 }
 
 impl Feed {
     pub fn next_crawl_time(&self) -> Option<DateTime<Utc>> {
         self.last_crawled_at.map(|last_crawled_at| {
-            last_crawled_at + chrono::Duration::minutes(self.crawl_interval_minutes as i64)
+            last_crawled_at + chrono::Duration::minutes(self.backoff_interval_minutes() as i64)
         })
     }
 
+    /// Whether the feed is still crawled: it is not deleted, gone, or paused.
+    pub fn is_active(&self) -> bool {
+        self.deleted_at.is_none() && self.inactive_at.is_none() && self.paused_at.is_none()
+    }
+
+    /// The crawl interval of the feed, doubled for each consecutive failed crawl up to
+    /// `MAX_FAILURE_BACKOFF_MINUTES`.
+    pub fn backoff_interval_minutes(&self) -> i32 {
+        backoff_interval_minutes(self.crawl_interval_minutes, self.consecutive_failures)
+    }
+
     pub async fn get(db: impl Executor<'_, Database = Postgres>, feed_id: Uuid) -> Result<Feed> {
         sqlx::query_as!(
             Feed,
@@ -150,6 +315,14 @@ impl Feed {
                 last_modified_header,
                 last_crawled_at,
                 last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
                 created_at,
                 updated_at,
                 deleted_at
@@ -166,253 +339,243 @@ impl Feed {
         })
     }
 
+    /// Returns the feed with the url, or the feed that permanently moved away from the url.
+    pub async fn get_by_url(db: impl Executor<'_, Database = Postgres>, url: &str) -> Result<Feed> {
+        sqlx::query_as!(
+            Feed,
+            r#"select
+                feed_id,
+                title,
+                url,
+                type as "feed_type: FeedType",
+                description,
+                crawl_interval_minutes,
+                last_crawl_error,
+                etag_header,
+                last_modified_header,
+                last_crawled_at,
+                last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
+                created_at,
+                updated_at,
+                deleted_at
+            from feed
+            where url = $1
+            or feed_id = (select feed_id from feed_url_alias where url = $1)
+            order by url = $1 desc
+            limit 1"#,
+            url
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundString("feed", url.to_string());
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    /// Returns a page of feeds in the order of `options.sort`, starting after `options.cursor`.
+    ///
+    /// Feeds that have no value for the sorted column (no title, or not crawled yet) are sorted
+    /// last, so that they can be paginated like any other feed.
     pub async fn get_all(
         db: impl Executor<'_, Database = Postgres>,
         options: &GetFeedsOptions,
-    ) -> sqlx::Result<Vec<Feed>> {
+    ) -> Result<Vec<Feed>> {
+        let cursor = options
+            .cursor
+            .as_deref()
+            .map(|cursor| decode_cursor(cursor).ok_or(Error::BadRequest("invalid cursor")))
+            .transpose()?;
+        let limit = options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE);
         // TODO: make sure there are indices for all of these sort options
-        match options.sort.as_ref().unwrap_or(&GetFeedsSort::CreatedAt) {
+        Ok(match options.sort() {
             GetFeedsSort::Title => {
-                if let Some(after_title) = &options.after_title {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        and (title, feed_id) > ($1, $2)
-                        order by title asc, feed_id asc
-                        limit $3
-                        "#,
-                        after_title,
-                        options.before_id.unwrap_or(Uuid::nil()),
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                } else {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        order by title asc, feed_id asc
-                        limit $1
-                        "#,
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                }
+                let (after_title, after_id) = cursor.unzip();
+                sqlx::query_as!(
+                    Feed,
+                    r#"select
+                        feed_id,
+                        title,
+                        url,
+                        type as "feed_type: FeedType",
+                        description,
+                        crawl_interval_minutes,
+                        last_crawl_error,
+                        etag_header,
+                        last_modified_header,
+                        last_crawled_at,
+                        last_entry_published_at,
+                        site_url,
+                        inactive_at,
+                        consecutive_failures,
+                        failing_since,
+                        paused_at,
+                        title_pinned,
+                        description_pinned,
+                        crawl_interval_pinned,
+                        created_at,
+                        updated_at,
+                        deleted_at
+                    from feed
+                    where deleted_at is null
+                    and ($4::uuid is null or feed_id in (
+                        select feed_id from subscription where user_id = $4
+                    ))
+                    and ($1::text is null or (coalesce(title, ''), feed_id) > ($1, $2))
+                    order by coalesce(title, '') asc, feed_id asc
+                    limit $3
+                    "#,
+                    after_title,
+                    after_id,
+                    limit,
+                    options.user_id,
+                )
+                .fetch_all(db)
+                .await?
             }
             GetFeedsSort::CreatedAt => {
-                if let Some(created_before) = options.before {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        and (created_at, feed_id) < ($1, $2)
-                        order by created_at desc, feed_id desc
-                        limit $3
-                        "#,
-                        created_before,
-                        options.before_id.unwrap_or(Uuid::nil()),
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                } else {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        order by created_at desc, feed_id desc
-                        limit $1
-                        "#,
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                }
+                let (created_before, before_id) = time_cursor(cursor)?;
+                sqlx::query_as!(
+                    Feed,
+                    r#"select
+                        feed_id,
+                        title,
+                        url,
+                        type as "feed_type: FeedType",
+                        description,
+                        crawl_interval_minutes,
+                        last_crawl_error,
+                        etag_header,
+                        last_modified_header,
+                        last_crawled_at,
+                        last_entry_published_at,
+                        site_url,
+                        inactive_at,
+                        consecutive_failures,
+                        failing_since,
+                        paused_at,
+                        title_pinned,
+                        description_pinned,
+                        crawl_interval_pinned,
+                        created_at,
+                        updated_at,
+                        deleted_at
+                    from feed
+                    where deleted_at is null
+                    and ($4::uuid is null or feed_id in (
+                        select feed_id from subscription where user_id = $4
+                    ))
+                    and ($1::timestamptz is null or (created_at, feed_id) < ($1, $2))
+                    order by created_at desc, feed_id desc
+                    limit $3
+                    "#,
+                    created_before,
+                    before_id,
+                    limit,
+                    options.user_id,
+                )
+                .fetch_all(db)
+                .await?
             }
             GetFeedsSort::LastCrawledAt => {
-                if let Some(crawled_before) = options.before {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        and (last_crawled_at, feed_id) < ($1, $2)
-                        order by last_crawled_at desc, feed_id desc
-                        limit $3
-                        "#,
-                        crawled_before,
-                        options.before_id.unwrap_or(Uuid::nil()),
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                } else {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        order by last_crawled_at desc, feed_id desc
-                        limit $1
-                        "#,
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                }
+                let (crawled_before, before_id) = time_cursor(cursor)?;
+                sqlx::query_as!(
+                    Feed,
+                    r#"select
+                        feed_id,
+                        title,
+                        url,
+                        type as "feed_type: FeedType",
+                        description,
+                        crawl_interval_minutes,
+                        last_crawl_error,
+                        etag_header,
+                        last_modified_header,
+                        last_crawled_at,
+                        last_entry_published_at,
+                        site_url,
+                        inactive_at,
+                        consecutive_failures,
+                        failing_since,
+                        paused_at,
+                        title_pinned,
+                        description_pinned,
+                        crawl_interval_pinned,
+                        created_at,
+                        updated_at,
+                        deleted_at
+                    from feed
+                    where deleted_at is null
+                    and ($4::uuid is null or feed_id in (
+                        select feed_id from subscription where user_id = $4
+                    ))
+                    and ($1::timestamptz is null or (coalesce(last_crawled_at, 'epoch'), feed_id) < ($1, $2))
+                    order by coalesce(last_crawled_at, 'epoch') desc, feed_id desc
+                    limit $3
+                    "#,
+                    crawled_before,
+                    before_id,
+                    limit,
+                    options.user_id,
+                )
+                .fetch_all(db)
+                .await?
             }
             GetFeedsSort::LastEntryPublishedAt => {
-                if let Some(published_before) = options.before {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        and (last_entry_published_at, feed_id) < ($1, $2)
-                        order by last_entry_published_at desc, feed_id desc
-                        limit $3
-                        "#,
-                        published_before,
-                        options.before_id.unwrap_or(Uuid::nil()),
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                } else {
-                    sqlx::query_as!(
-                        Feed,
-                        r#"select
-                            feed_id,
-                            title,
-                            url,
-                            type as "feed_type: FeedType",
-                            description,
-                            crawl_interval_minutes,
-                            last_crawl_error,
-                            etag_header,
-                            last_modified_header,
-                            last_crawled_at,
-                            last_entry_published_at,
-                            created_at,
-                            updated_at,
-                            deleted_at
-                        from feed
-                        where deleted_at is null
-                        order by last_entry_published_at desc, feed_id desc
-                        limit $1
-                        "#,
-                        options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE),
-                    )
-                    .fetch_all(db)
-                    .await
-                }
+                let (published_before, before_id) = time_cursor(cursor)?;
+                sqlx::query_as!(
+                    Feed,
+                    r#"select
+                        feed_id,
+                        title,
+                        url,
+                        type as "feed_type: FeedType",
+                        description,
+                        crawl_interval_minutes,
+                        last_crawl_error,
+                        etag_header,
+                        last_modified_header,
+                        last_crawled_at,
+                        last_entry_published_at,
+                        site_url,
+                        inactive_at,
+                        consecutive_failures,
+                        failing_since,
+                        paused_at,
+                        title_pinned,
+                        description_pinned,
+                        crawl_interval_pinned,
+                        created_at,
+                        updated_at,
+                        deleted_at
+                    from feed
+                    where deleted_at is null
+                    and ($4::uuid is null or feed_id in (
+                        select feed_id from subscription where user_id = $4
+                    ))
+                    and ($1::timestamptz is null or (coalesce(last_entry_published_at, 'epoch'), feed_id) < ($1, $2))
+                    order by coalesce(last_entry_published_at, 'epoch') desc, feed_id desc
+                    limit $3
+                    "#,
+                    published_before,
+                    before_id,
+                    limit,
+                    options.user_id,
+                )
+                .fetch_all(db)
+                .await?
             }
-        }
+        })
     }
 
     pub async fn create(
@@ -438,6 +601,14 @@ impl Feed {
                 last_modified_header,
                 last_crawled_at,
                 last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
                 created_at,
                 updated_at,
                 deleted_at
@@ -450,6 +621,45 @@ impl Feed {
         .await?)
     }
 
+    /// Creates a new feed unless a feed with the same url already exists, in which case the
+    /// existing feed is returned instead (and undeleted if it had been deleted).
+    ///
+    /// Feeds are shared between all users subscribed to them, so adding a url that another user
+    /// has already added should not create a second feed that would be crawled separately.
+    ///
+    /// The returned `FeedAddition` tells whether the feed was created or restored, in which case it
+    /// still needs to be scheduled to be crawled.
+    pub async fn create_or_restore(
+        pool: &PgPool,
+        payload: CreateFeed,
+    ) -> Result<(Feed, FeedAddition)> {
+        let url = payload.url.clone();
+        // The url may be the old url of a feed that has since moved, which would not conflict
+        if let Ok(feed) = Feed::get_by_url(pool, &url).await {
+            return Feed::restore_if_deleted(pool, feed).await;
+        }
+        match Feed::create(pool, payload).await {
+            Ok(feed) => Ok((feed, FeedAddition::Created)),
+            Err(Error::Sqlx(sqlx::error::Error::Database(error)))
+                if error.is_unique_violation() =>
+            {
+                let feed = Feed::get_by_url(pool, &url).await?;
+                Feed::restore_if_deleted(pool, feed).await
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    async fn restore_if_deleted(pool: &PgPool, feed: Feed) -> Result<(Feed, FeedAddition)> {
+        if feed.deleted_at.is_some() {
+            return Ok((
+                Feed::restore(pool, feed.feed_id).await?,
+                FeedAddition::Restored,
+            ));
+        }
+        Ok((feed, FeedAddition::Existing))
+    }
+
     pub async fn upsert(
         db: impl Executor<'_, Database = Postgres>,
         payload: UpsertFeed,
@@ -478,6 +688,14 @@ impl Feed {
                 last_modified_header,
                 last_crawled_at,
                 last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
                 created_at,
                 updated_at,
                 deleted_at
@@ -497,45 +715,87 @@ impl Feed {
         payload: UpdateFeed,
     ) -> Result<Feed> {
         payload.validate()?;
-        let mut query = sqlx::QueryBuilder::new("UPDATE feed SET ");
+        let mut query = sqlx::QueryBuilder::new("update feed set ");
 
         let mut updates = query.separated(", ");
+        let mut updated = false;
         if let Some(title) = payload.title {
-            updates.push_unseparated("title = ");
-            updates.push_bind(title);
+            updates.push("title_pinned = ");
+            updates.push_bind_unseparated(title.is_some());
+            updates.push("title = ");
+            updates.push_bind_unseparated(title);
+            updated = true;
         }
         if let Some(url) = payload.url {
-            updates.push_unseparated("url = ");
-            updates.push_bind(url);
+            // The caching headers and failures were for the old url
+            updates.push("url = ");
+            updates.push_bind_unseparated(url);
+            updates.push("etag_header = null");
+            updates.push("last_modified_header = null");
+            updates.push("last_crawl_error = null");
+            updates.push("consecutive_failures = 0");
+            updates.push("failing_since = null");
+            updated = true;
         }
         if let Some(description) = payload.description {
-            updates.push_unseparated("description = ");
-            updates.push_bind(description);
+            updates.push("description_pinned = ");
+            updates.push_bind_unseparated(description.is_some());
+            updates.push("description = ");
+            updates.push_bind_unseparated(description);
+            updated = true;
         }
         if let Some(crawl_interval_minutes) = payload.crawl_interval_minutes {
-            updates.push("crawl_interval_minutes = ");
-            updates.push_bind(crawl_interval_minutes);
-        }
-        if let Some(last_crawl_error) = payload.last_crawl_error {
-            updates.push_unseparated("last_crawl_error = ");
-            updates.push_bind(last_crawl_error);
-        }
-        if let Some(last_crawled_at) = payload.last_crawled_at {
-            updates.push_unseparated("last_crawled_at = ");
-            updates.push_bind(last_crawled_at);
+            updates.push("crawl_interval_pinned = ");
+            updates.push_bind_unseparated(crawl_interval_minutes.is_some());
+            if let Some(crawl_interval_minutes) = crawl_interval_minutes {
+                updates.push("crawl_interval_minutes = ");
+                updates.push_bind_unseparated(crawl_interval_minutes);
+            }
+            updated = true;
         }
-        if let Some(last_entry_published_at) = payload.last_entry_published_at {
-            updates.push_unseparated("last_entry_published_at = ");
-            updates.push_bind(last_entry_published_at);
+        if !updated {
+            return Feed::get(db, feed_id).await;
         }
 
-        query.push(" WHERE id = ");
+        query.push(" where feed_id = ");
         query.push_bind(feed_id);
-        query.push(" RETURNING *");
-
-        let query = query.build_query_as();
+        query.push(
+            r#" returning
+                feed_id,
+                title,
+                url,
+                type as feed_type,
+                description,
+                crawl_interval_minutes,
+                last_crawl_error,
+                etag_header,
+                last_modified_header,
+                last_crawled_at,
+                last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
+                created_at,
+                updated_at,
+                deleted_at"#,
+        );
 
-        Ok(query.fetch_one(db).await?)
+        query.build_query_as().fetch_one(db).await.map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("feed", feed_id);
+            }
+            if let sqlx::error::Error::Database(db_error) = &error {
+                if db_error.is_unique_violation() {
+                    return Error::BadRequest("another feed already has this url");
+                }
+            }
+            Error::Sqlx(error)
+        })
     }
 
     pub async fn delete(db: impl Executor<'_, Database = Postgres>, feed_id: Uuid) -> Result<()> {
@@ -548,6 +808,127 @@ impl Feed {
         Ok(())
     }
 
+    /// Deletes the feed only if no users are subscribed to it anymore. Returns whether the feed
+    /// was deleted.
+    pub async fn delete_if_unsubscribed(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"update feed set deleted_at = now()
+            where feed_id = $1
+            and not exists (select 1 from subscription where feed_id = $1)"#,
+            feed_id
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Merges the feed into another feed it permanently redirected to, when that feed already
+    /// exists under its new url.
+    ///
+    /// Subscribers of the feed are subscribed to the other feed instead, the url of the feed (and
+    /// its aliases) become aliases of the other feed, and the feed is deleted. Its entries are
+    /// kept but are no longer shown since nobody is subscribed to it anymore.
+    ///
+    /// If the other feed had been deleted once no one was subscribed to it anymore, it is restored
+    /// and scheduled to be crawled right away, like a feed a user adds again.
+    pub async fn merge_into(pool: &PgPool, feed_id: Uuid, into_feed_id: Uuid) -> Result<()> {
+        let mut tx = pool.begin().await?;
+        let restored_active = sqlx::query_scalar!(
+            r#"update feed set deleted_at = null
+            where feed_id = $1 and deleted_at is not null
+            returning inactive_at is null and paused_at is null as "active!""#,
+            into_feed_id
+        )
+        .fetch_optional(tx.as_mut())
+        .await?;
+        if restored_active == Some(true) {
+            CrawlJob::create(tx.as_mut(), into_feed_id, Utc::now()).await?;
+        }
+        sqlx::query!(
+            r#"insert into subscription (user_id, feed_id, folder_id)
+            select user_id, $2, folder_id from subscription where feed_id = $1
+            on conflict do nothing"#,
+            feed_id,
+            into_feed_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        sqlx::query!("delete from subscription where feed_id = $1", feed_id)
+            .execute(tx.as_mut())
+            .await?;
+        sqlx::query!(
+            "update feed_url_alias set feed_id = $2 where feed_id = $1",
+            feed_id,
+            into_feed_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        sqlx::query!(
+            r#"insert into feed_url_alias (url, feed_id)
+            select url, $2 from feed where feed_id = $1
+            on conflict (url) do update set feed_id = excluded.feed_id"#,
+            feed_id,
+            into_feed_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        sqlx::query!(
+            "update feed set deleted_at = now() where feed_id = $1",
+            feed_id
+        )
+        .execute(tx.as_mut())
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn restore(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+    ) -> Result<Feed> {
+        sqlx::query_as!(
+            Feed,
+            r#"update feed set deleted_at = null
+            where feed_id = $1
+            returning
+                feed_id,
+                title,
+                url,
+                type as "feed_type: FeedType",
+                description,
+                crawl_interval_minutes,
+                last_crawl_error,
+                etag_header,
+                last_modified_header,
+                last_crawled_at,
+                last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
+                created_at,
+                updated_at,
+                deleted_at
+            "#,
+            feed_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("feed", feed_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
     pub async fn update_crawl_error(
         db: impl Executor<'_, Database = Postgres>,
         feed_id: Uuid,
@@ -564,6 +945,114 @@ impl Feed {
         .await?)
     }
 
+    /// Records whether a crawl of the feed failed. Failures are counted until a crawl succeeds,
+    /// and the feed is paused once it has been failing for longer than `pause_after_days`.
+    pub async fn record_crawl(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        failed: bool,
+        pause_after_days: i32,
+    ) -> Result<Feed> {
+        Ok(sqlx::query_as!(
+            Feed,
+            r#"update feed set
+                consecutive_failures = case when $2 then consecutive_failures + 1 else 0 end,
+                failing_since = case when $2 then coalesce(failing_since, now()) else null end,
+                paused_at = case
+                    when $2 and failing_since < now() - make_interval(days => $3)
+                    then coalesce(paused_at, now())
+                    else paused_at
+                end
+            where feed_id = $1
+            returning
+                feed_id,
+                title,
+                url,
+                type as "feed_type: FeedType",
+                description,
+                crawl_interval_minutes,
+                last_crawl_error,
+                etag_header,
+                last_modified_header,
+                last_crawled_at,
+                last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
+                created_at,
+                updated_at,
+                deleted_at
+            "#,
+            feed_id,
+            failed,
+            pause_after_days
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    /// Pauses crawling the feed until it is resumed.
+    pub async fn pause(db: impl Executor<'_, Database = Postgres>, feed_id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "update feed set paused_at = coalesce(paused_at, now()) where feed_id = $1",
+            feed_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Resumes crawling a feed that was paused or marked gone, starting over with no failures.
+    pub async fn resume(db: impl Executor<'_, Database = Postgres>, feed_id: Uuid) -> Result<Feed> {
+        sqlx::query_as!(
+            Feed,
+            r#"update feed set
+                paused_at = null,
+                inactive_at = null,
+                consecutive_failures = 0,
+                failing_since = null
+            where feed_id = $1
+            returning
+                feed_id,
+                title,
+                url,
+                type as "feed_type: FeedType",
+                description,
+                crawl_interval_minutes,
+                last_crawl_error,
+                etag_header,
+                last_modified_header,
+                last_crawled_at,
+                last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
+                created_at,
+                updated_at,
+                deleted_at
+            "#,
+            feed_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("feed", feed_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
     pub async fn save(&self, db: impl Executor<'_, Database = Postgres>) -> Result<Feed> {
         Ok(sqlx::query_as!(
             Feed,
@@ -577,7 +1066,9 @@ impl Feed {
                 etag_header = $8,
                 last_modified_header = $9,
                 last_crawled_at = $10,
-                last_entry_published_at = $11
+                last_entry_published_at = $11,
+                site_url = $12,
+                inactive_at = $13
             where feed_id = $1
             returning
                 feed_id,
@@ -591,6 +1082,14 @@ impl Feed {
                 last_modified_header,
                 last_crawled_at,
                 last_entry_published_at,
+                site_url,
+                inactive_at,
+                consecutive_failures,
+                failing_since,
+                paused_at,
+                title_pinned,
+                description_pinned,
+                crawl_interval_pinned,
                 created_at,
                 updated_at,
                 deleted_at
@@ -606,8 +1105,81 @@ impl Feed {
             self.last_modified_header,
             self.last_crawled_at,
             self.last_entry_published_at,
+            self.site_url,
+            self.inactive_at,
         )
         .fetch_one(db)
         .await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::subscription::Subscription;
+
+    #[test]
+    fn backs_off_crawl_interval() {
+        assert_eq!(backoff_interval_minutes(60, 0), 60);
+        assert_eq!(backoff_interval_minutes(60, 1), 120);
+        assert_eq!(backoff_interval_minutes(60, 3), 480);
+        assert_eq!(
+            backoff_interval_minutes(60, 10),
+            MAX_FAILURE_BACKOFF_MINUTES
+        );
+        assert_eq!(
+            backoff_interval_minutes(60, 1000),
+            MAX_FAILURE_BACKOFF_MINUTES
+        );
+        assert_eq!(backoff_interval_minutes(5040, 2), 5040);
+    }
+
+    #[sqlx::test]
+    async fn merges_into_deleted_feed(pool: PgPool) -> Result<()> {
+        let user_id = sqlx::query_scalar!(
+            "insert into users (email, password_hash) values ('a@example.com', '') returning user_id"
+        )
+        .fetch_one(&pool)
+        .await?;
+        let create = |url: &str| CreateFeed {
+            url: url.to_string(),
+            ..Default::default()
+        };
+        let feed = Feed::create(&pool, create("https://example.com/old.xml")).await?;
+        let moved_to = Feed::create(&pool, create("https://example.com/new.xml")).await?;
+        Subscription::create(&pool, user_id, feed.feed_id).await?;
+        assert!(Feed::delete_if_unsubscribed(&pool, moved_to.feed_id).await?);
+
+        Feed::merge_into(&pool, feed.feed_id, moved_to.feed_id).await?;
+
+        assert!(Feed::get(&pool, feed.feed_id).await?.deleted_at.is_some());
+        assert!(Feed::get(&pool, moved_to.feed_id)
+            .await?
+            .deleted_at
+            .is_none());
+        let feeds = Subscription::get_all_feeds(&pool, user_id).await?;
+        assert_eq!(
+            feeds.iter().map(|feed| feed.feed_id).collect::<Vec<_>>(),
+            vec![moved_to.feed_id]
+        );
+        let scheduled = sqlx::query_scalar!(
+            r#"select exists(select 1 from crawl_job where feed_id = $1) as "exists!""#,
+            moved_to.feed_id
+        )
+        .fetch_one(&pool)
+        .await?;
+        assert!(scheduled);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trips_feeds_cursor() {
+        let feed_id = Uuid::from_u128(42);
+        let cursor = encode_cursor("A\ttitle", feed_id);
+        assert_eq!(
+            decode_cursor(&cursor),
+            Some(("A\ttitle".to_owned(), feed_id))
+        );
+        assert_eq!(decode_cursor("not a cursor"), None);
+    }
+}
@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::Result;
+
+/// A url a feed was fetched from before it permanently redirected to its current url.
+///
+/// `Feed::get_by_url` also finds feeds by their aliases, so adding a feed by its old url (e.g. from
+/// an old OPML export) subscribes to the existing feed instead of creating a duplicate.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeedUrlAlias {
+    pub url: String,
+    pub feed_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FeedUrlAlias {
+    /// Returns the old urls of the feed, most recently moved from first.
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+    ) -> Result<Vec<FeedUrlAlias>> {
+        Ok(sqlx::query_as!(
+            FeedUrlAlias,
+            "select * from feed_url_alias where feed_id = $1 order by created_at desc",
+            feed_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Records that the feed moved away from the url. If the url was an alias of another feed,
+    /// it now points to this feed instead.
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        url: &str,
+    ) -> Result<FeedUrlAlias> {
+        Ok(sqlx::query_as!(
+            FeedUrlAlias,
+            "insert into feed_url_alias (url, feed_id) values ($1, $2)
+            on conflict (url) do update set feed_id = excluded.feed_id, created_at = now()
+            returning *",
+            url,
+            feed_id
+        )
+        .fetch_one(db)
+        .await?)
+    }
+}
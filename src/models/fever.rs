@@ -0,0 +1,305 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::user::User;
+
+/// Fever clients only handle 50 items per request.
+pub const FEVER_ITEMS_PAGE_SIZE: i64 = 50;
+
+/// A key that authenticates a user with the Fever API.
+///
+/// Fever clients send the md5 hash of "email:password" as the api key. The password is one the
+/// user sets just for Fever clients, so that their account password is never hashed with md5.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct FeverApiKey {
+    pub user_id: Uuid,
+    pub api_key: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A folder, in the shape of a Fever group.
+#[derive(Debug, Serialize, Clone)]
+pub struct FeverGroup {
+    pub id: i64,
+    pub title: String,
+}
+
+/// The feeds in a Fever group, as a comma-separated list of feed ids.
+#[derive(Debug, Serialize, Clone)]
+pub struct FeverFeedsGroup {
+    pub group_id: i64,
+    pub feed_ids: String,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct FeverFeed {
+    pub id: i64,
+    pub favicon_id: i64,
+    pub title: String,
+    pub url: String,
+    pub site_url: String,
+    pub is_spark: i32,
+    pub last_updated_on_time: i64,
+}
+
+/// An entry, in the shape of a Fever item (minus the `html`, which is read from `content_dir`).
+#[derive(Debug, Clone)]
+pub struct FeverItem {
+    pub id: i64,
+    pub entry_id: Uuid,
+    pub feed_id: i64,
+    pub title: String,
+    pub url: String,
+    pub description: Option<String>,
+    pub is_saved: bool,
+    pub is_read: bool,
+    pub created_on_time: i64,
+}
+
+impl FeverApiKey {
+    /// Sets the user's Fever api key from the password they will enter in their Fever client.
+    pub async fn upsert(
+        db: impl Executor<'_, Database = Postgres>,
+        user: &User,
+        password: &str,
+    ) -> Result<FeverApiKey> {
+        Ok(sqlx::query_as!(
+            FeverApiKey,
+            r#"insert into fever_api_key (
+                user_id, api_key
+            ) values (
+                $1, md5($2 || ':' || $3)
+            ) on conflict (user_id) do update set
+                api_key = excluded.api_key
+            returning *"#,
+            user.user_id,
+            user.email,
+            password
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    pub async fn get_user(
+        db: impl Executor<'_, Database = Postgres>,
+        api_key: &str,
+    ) -> Result<User> {
+        sqlx::query_as!(
+            User,
+            r#"select users.* from users
+            join fever_api_key using (user_id)
+            where fever_api_key.api_key = lower($1)
+            and users.deleted_at is null"#,
+            api_key
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::Unauthorized;
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn exists(db: impl Executor<'_, Database = Postgres>, user_id: Uuid) -> Result<bool> {
+        Ok(sqlx::query_scalar!(
+            r#"select exists(select 1 from fever_api_key where user_id = $1) as "exists!""#,
+            user_id
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    pub async fn delete(db: impl Executor<'_, Database = Postgres>, user_id: Uuid) -> Result<()> {
+        sqlx::query!("delete from fever_api_key where user_id = $1", user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
+
+impl FeverGroup {
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<FeverGroup>> {
+        Ok(sqlx::query_as!(
+            FeverGroup,
+            r#"select
+                numeric_id.numeric_id as id,
+                folder.name as title
+            from folder
+            join numeric_id on numeric_id.id = folder.folder_id
+            where folder.user_id = $1
+            order by folder.name"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+}
+
+impl FeverFeedsGroup {
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<FeverFeedsGroup>> {
+        Ok(sqlx::query_as!(
+            FeverFeedsGroup,
+            r#"select
+                folder_numeric_id.numeric_id as group_id,
+                string_agg(feed_numeric_id.numeric_id::text, ',') as "feed_ids!"
+            from subscription
+            join numeric_id as folder_numeric_id on folder_numeric_id.id = subscription.folder_id
+            join numeric_id as feed_numeric_id on feed_numeric_id.id = subscription.feed_id
+            where subscription.user_id = $1
+            group by folder_numeric_id.numeric_id"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+}
+
+impl FeverFeed {
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<FeverFeed>> {
+        Ok(sqlx::query_as!(
+            FeverFeed,
+            r#"select
+                numeric_id.numeric_id as id,
+                0::bigint as "favicon_id!",
+                coalesce(feed.title, feed.url) as "title!",
+                feed.url,
+                coalesce(feed.site_url, '') as "site_url!",
+                0 as "is_spark!",
+                coalesce(
+                    extract(epoch from coalesce(feed.last_entry_published_at, feed.last_crawled_at))::bigint,
+                    0
+                ) as "last_updated_on_time!"
+            from feed
+            join subscription using (feed_id)
+            join numeric_id on numeric_id.id = feed.feed_id
+            where subscription.user_id = $1
+            and feed.deleted_at is null
+            order by feed.title"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+}
+
+impl FeverItem {
+    /// Gets up to `FEVER_ITEMS_PAGE_SIZE` items of the feeds the user is subscribed to (or that the
+    /// user starred).
+    ///
+    /// With `since_id`, returns the items after it in ascending order. With `max_id`, returns the
+    /// items before it in descending order. With `with_ids`, returns only those items.
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        since_id: Option<i64>,
+        max_id: Option<i64>,
+        with_ids: &[i64],
+    ) -> Result<Vec<FeverItem>> {
+        Ok(sqlx::query_as!(
+            FeverItem,
+            r#"select
+                numeric_id.numeric_id as id,
+                entry.entry_id,
+                feed_numeric_id.numeric_id as feed_id,
+                coalesce(entry.title, '') as "title!",
+                entry.url,
+                entry.description,
+                exists (
+                    select 1 from entry_star
+                    where entry_star.entry_id = entry.entry_id and entry_star.user_id = $1
+                ) as "is_saved!",
+                exists (
+                    select 1 from entry_read
+                    where entry_read.entry_id = entry.entry_id and entry_read.user_id = $1
+                ) as "is_read!",
+                extract(epoch from entry.published_at)::bigint as "created_on_time!"
+            from entry
+            join numeric_id on numeric_id.id = entry.entry_id
+            join numeric_id as feed_numeric_id on feed_numeric_id.id = entry.feed_id
+            where entry.deleted_at is null
+            and (
+                entry.feed_id in (select feed_id from subscription where user_id = $1)
+                or exists (
+                    select 1 from entry_star
+                    where entry_star.entry_id = entry.entry_id and entry_star.user_id = $1
+                )
+            )
+            and ($2::bigint is null or numeric_id.numeric_id > $2)
+            and ($3::bigint is null or numeric_id.numeric_id < $3)
+            and (cardinality($4::bigint[]) = 0 or numeric_id.numeric_id = any($4))
+            order by case when $3::bigint is null
+                then numeric_id.numeric_id
+                else -numeric_id.numeric_id
+            end
+            limit $5"#,
+            user_id,
+            since_id,
+            max_id,
+            with_ids,
+            FEVER_ITEMS_PAGE_SIZE
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn count(db: impl Executor<'_, Database = Postgres>, user_id: Uuid) -> Result<i64> {
+        Ok(sqlx::query_scalar!(
+            r#"select count(*) as "count!" from entry
+            where deleted_at is null
+            and feed_id in (select feed_id from subscription where user_id = $1)"#,
+            user_id
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    pub async fn get_unread_ids(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar!(
+            r#"select numeric_id.numeric_id from entry
+            join numeric_id on numeric_id.id = entry.entry_id
+            where entry.deleted_at is null
+            and entry.feed_id in (select feed_id from subscription where user_id = $1)
+            and not exists (
+                select 1 from entry_read
+                where entry_read.entry_id = entry.entry_id and entry_read.user_id = $1
+            )
+            order by numeric_id.numeric_id"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn get_saved_ids(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<i64>> {
+        Ok(sqlx::query_scalar!(
+            r#"select numeric_id.numeric_id from entry_star
+            join numeric_id on numeric_id.id = entry_star.entry_id
+            where entry_star.user_id = $1
+            order by numeric_id.numeric_id"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+}
@@ -0,0 +1,121 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::{Error, Result};
+
+/// A user-defined folder that groups the feeds a user is subscribed to.
+///
+/// A feed can be in at most one of the user's folders, set on the user's `Subscription`.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Folder {
+    pub folder_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateFolder {
+    #[validate(length(min = 1, max = 255))]
+    pub name: String,
+}
+
+impl Folder {
+    pub async fn get(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        folder_id: Uuid,
+    ) -> Result<Folder> {
+        sqlx::query_as!(
+            Folder,
+            "select * from folder where user_id = $1 and folder_id = $2",
+            user_id,
+            folder_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("folder", folder_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn get_by_name(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        name: &str,
+    ) -> Result<Folder> {
+        sqlx::query_as!(
+            Folder,
+            "select * from folder where user_id = $1 and name = $2",
+            user_id,
+            name
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundString("folder", name.to_string());
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn get_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<Folder>> {
+        Ok(sqlx::query_as!(
+            Folder,
+            "select * from folder where user_id = $1 order by name",
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    /// Gets the user's folder with the given name, creating it if it doesn't exist yet.
+    pub async fn get_or_create(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        payload: CreateFolder,
+    ) -> Result<Folder> {
+        payload.validate()?;
+        Ok(sqlx::query_as!(
+            Folder,
+            "insert into folder (
+                user_id, name
+            ) values (
+                $1, $2
+            ) on conflict (user_id, name) do update set
+                name = excluded.name
+            returning *",
+            user_id,
+            payload.name
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    pub async fn delete(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        folder_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query!(
+            "delete from folder where user_id = $1 and folder_id = $2",
+            user_id,
+            folder_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+}
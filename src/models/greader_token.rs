@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::user::User;
+use crate::uuid::Base62Uuid;
+
+/// How long a Google Reader API token can be used before the client has to log in again.
+pub const GREADER_TOKEN_EXPIRY_DAYS: i32 = 30;
+
+/// An auth token for Google Reader API clients, created when a client logs in with ClientLogin.
+///
+/// The token itself is only returned once from `create`, only its sha256 hash is stored. Tokens
+/// expire after `GREADER_TOKEN_EXPIRY_DAYS`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct GReaderToken {
+    pub token_hash: String,
+    pub user_id: Uuid,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl GReaderToken {
+    /// Creates a new token for the user and returns it. The user's expired tokens are deleted.
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<String> {
+        let token = format!("{}{}", Base62Uuid::new(), Base62Uuid::new());
+        sqlx::query!(
+            r#"with expired as (
+                delete from greader_token where user_id = $2 and expires_at <= now()
+            )
+            insert into greader_token (
+                token_hash, user_id, expires_at
+            ) values (
+                encode(sha256(convert_to($1, 'UTF8')), 'hex'), $2, now() + make_interval(days => $3)
+            )"#,
+            token,
+            user_id,
+            GREADER_TOKEN_EXPIRY_DAYS
+        )
+        .execute(db)
+        .await?;
+        Ok(token)
+    }
+
+    pub async fn get_user(db: impl Executor<'_, Database = Postgres>, token: &str) -> Result<User> {
+        sqlx::query_as!(
+            User,
+            r#"select users.* from users
+            join greader_token using (user_id)
+            where greader_token.token_hash = encode(sha256(convert_to($1, 'UTF8')), 'hex')
+            and greader_token.expires_at > now()
+            and users.deleted_at is null"#,
+            token
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::Unauthorized;
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn exists(db: impl Executor<'_, Database = Postgres>, user_id: Uuid) -> Result<bool> {
+        Ok(sqlx::query_scalar!(
+            r#"select exists(
+                select 1 from greader_token where user_id = $1 and expires_at > now()
+            ) as "exists!""#,
+            user_id
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    /// Revokes all tokens of the user, signing out every Google Reader API client.
+    pub async fn delete_all(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<()> {
+        sqlx::query!("delete from greader_token where user_id = $1", user_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
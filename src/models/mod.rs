@@ -1,5 +1,19 @@
+pub mod api_token;
+pub mod crawl_job;
+pub mod crawl_log;
+pub mod domain_delay;
+pub mod domain_request;
 pub mod entry;
+pub mod entry_read;
+pub mod entry_star;
 pub mod feed;
+pub mod feed_url_alias;
+pub mod fever;
+pub mod folder;
+pub mod greader_token;
+pub mod numeric_id;
+pub mod subscription;
 pub mod user;
 pub mod user_email_verification_token;
 pub mod user_password_reset_token;
+pub mod websub_subscription;
@@ -0,0 +1,29 @@
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// An integer id for an entry, feed or folder, for APIs (like Fever) whose clients can't handle
+/// UUIDs. Created automatically by the database whenever one of those is inserted.
+#[derive(Debug, Clone, Copy)]
+pub struct NumericId {
+    pub numeric_id: i64,
+    pub id: Uuid,
+}
+
+impl NumericId {
+    pub async fn get(db: impl Executor<'_, Database = Postgres>, numeric_id: i64) -> Result<Uuid> {
+        sqlx::query_scalar!(
+            "select id from numeric_id where numeric_id = $1",
+            numeric_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundString("numeric id", numeric_id.to_string());
+            }
+            Error::Sqlx(error)
+        })
+    }
+}
@@ -0,0 +1,232 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, NoneAsEmptyString};
+use sqlx::{Executor, Postgres};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+
+/// A user's subscription to a feed.
+///
+/// Feeds are global so that each feed url is only crawled once, no matter how many users are
+/// subscribed to it. Users only see the feeds (and entries of those feeds) they are subscribed to.
+#[derive(Debug, Serialize, Deserialize, Clone, ToSchema)]
+pub struct Subscription {
+    pub user_id: Uuid,
+    pub feed_id: Uuid,
+    pub folder_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+#[serde_as]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SetFolder {
+    /// Name of the folder to move the feed into, created if it doesn't exist yet. Removes the feed
+    /// from its folder if empty.
+    #[serde(default)]
+    #[serde_as(as = "NoneAsEmptyString")]
+    pub folder: Option<String>,
+}
+
+/// A feed the user is subscribed to along with the name of the folder the user put it in.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SubscribedFeed {
+    pub feed_id: Uuid,
+    pub title: Option<String>,
+    pub url: String,
+    pub site_url: Option<String>,
+    pub description: Option<String>,
+    pub folder: Option<String>,
+}
+
+/// The number of unread entries in a feed the user is subscribed to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct UnreadCount {
+    pub feed_id: Uuid,
+    pub count: i64,
+    pub newest_published_at: Option<DateTime<Utc>>,
+}
+
+impl Subscription {
+    pub async fn get(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        feed_id: Uuid,
+    ) -> Result<Subscription> {
+        sqlx::query_as!(
+            Subscription,
+            r#"select
+                *
+            from subscription
+            where user_id = $1 and feed_id = $2"#,
+            user_id,
+            feed_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("feed", feed_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn get_all_feeds(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<SubscribedFeed>> {
+        Ok(sqlx::query_as!(
+            SubscribedFeed,
+            r#"select
+                feed.feed_id,
+                feed.title,
+                feed.url,
+                feed.site_url,
+                feed.description,
+                folder.name as "folder?"
+            from subscription
+            join feed using (feed_id)
+            left join folder using (folder_id)
+            where subscription.user_id = $1
+            and feed.deleted_at is null
+            order by folder.name nulls first, feed.title, feed.url"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn get_unread_counts(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+    ) -> Result<Vec<UnreadCount>> {
+        Ok(sqlx::query_as!(
+            UnreadCount,
+            r#"select
+                subscription.feed_id,
+                count(entry.entry_id) as "count!",
+                max(entry.published_at) as newest_published_at
+            from subscription
+            join entry on entry.feed_id = subscription.feed_id and entry.deleted_at is null
+            where subscription.user_id = $1
+            and not exists (
+                select 1 from entry_read
+                where entry_read.entry_id = entry.entry_id and entry_read.user_id = $1
+            )
+            group by subscription.feed_id"#,
+            user_id
+        )
+        .fetch_all(db)
+        .await?)
+    }
+
+    pub async fn create(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        feed_id: Uuid,
+    ) -> Result<Subscription> {
+        sqlx::query_as!(
+            Subscription,
+            r#"insert into subscription (
+                user_id, feed_id
+            ) values (
+                $1, $2
+            ) returning *"#,
+            user_id,
+            feed_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::Database(ref psql_error) = error {
+                if psql_error.code().as_deref() == Some("23503") {
+                    return Error::RelationNotFound("feed");
+                }
+                if psql_error.code().as_deref() == Some("23505")
+                    && psql_error.constraint() == Some("subscription_pkey")
+                {
+                    return Error::Conflict("already subscribed to feed");
+                }
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn set_folder(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        feed_id: Uuid,
+        folder_id: Option<Uuid>,
+    ) -> Result<Subscription> {
+        sqlx::query_as!(
+            Subscription,
+            r#"update subscription set
+                folder_id = $3
+            where user_id = $1 and feed_id = $2
+            returning *"#,
+            user_id,
+            feed_id,
+            folder_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("feed", feed_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    /// Unsubscribes the user from the feed. Returns whether the user was subscribed to it.
+    pub async fn delete(
+        db: impl Executor<'_, Database = Postgres>,
+        user_id: Uuid,
+        feed_id: Uuid,
+    ) -> Result<bool> {
+        let result = sqlx::query!(
+            r#"delete from subscription
+            where user_id = $1 and feed_id = $2"#,
+            user_id,
+            feed_id
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::PgPool;
+
+    use super::*;
+    use crate::models::feed::{CreateFeed, Feed};
+
+    #[sqlx::test]
+    async fn conflicts_when_already_subscribed(pool: PgPool) -> Result<()> {
+        let user_id = sqlx::query_scalar!(
+            "insert into users (email, password_hash) values ('a@example.com', '') returning user_id"
+        )
+        .fetch_one(&pool)
+        .await?;
+        let feed = Feed::create(
+            &pool,
+            CreateFeed {
+                url: "https://example.com/feed.xml".to_string(),
+                ..Default::default()
+            },
+        )
+        .await?;
+        Subscription::create(&pool, user_id, feed.feed_id).await?;
+
+        let err = Subscription::create(&pool, user_id, feed.feed_id)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, Error::Conflict(_)), "{err:?}");
+        Ok(())
+    }
+}
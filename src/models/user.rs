@@ -17,6 +17,8 @@ pub struct User {
     pub created_at: DateTime<Utc>,
     pub updated_at: Option<DateTime<Utc>>,
     pub deleted_at: Option<DateTime<Utc>>,
+    /// Admins can edit feeds, which are shared by all of their subscribers.
+    pub admin: bool,
 }
 
 #[derive(Debug, Deserialize, Default, Validate)]
@@ -106,7 +108,8 @@ impl User {
                 name,
                 created_at,
                 updated_at,
-                deleted_at
+                deleted_at,
+                admin
             "#,
             payload.email,
             password_hash,
@@ -161,7 +164,8 @@ impl User {
                 name,
                 created_at,
                 updated_at,
-                deleted_at
+                deleted_at,
+                admin
             "#,
             self.user_id,
             password_hash,
@@ -169,4 +173,30 @@ impl User {
         .fetch_one(db)
         .await?)
     }
+
+    /// Grants or revokes admin rights of the user with the email.
+    pub async fn set_admin(
+        db: impl Executor<'_, Database = Postgres>,
+        email: String,
+        admin: bool,
+    ) -> Result<User> {
+        sqlx::query_as!(
+            User,
+            r#"update users set
+                admin = $2
+            where email = $1
+            returning *
+            "#,
+            email,
+            admin
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundString("user", email);
+            }
+            Error::Sqlx(error)
+        })
+    }
 }
@@ -0,0 +1,128 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Executor, Postgres};
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::uuid::Base62Uuid;
+
+/// A subscription to the WebSub hub of a feed.
+///
+/// It is created when crawlnicle asks the hub to subscribe, and only becomes active once the hub
+/// verifies the intent of the subscription and sets the lease.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WebSubSubscription {
+    pub feed_id: Uuid,
+    pub hub_url: String,
+    pub topic_url: String,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+impl WebSubSubscription {
+    pub async fn get(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+    ) -> Result<WebSubSubscription> {
+        sqlx::query_as!(
+            WebSubSubscription,
+            "select * from websub_subscription where feed_id = $1",
+            feed_id
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("websub_subscription", feed_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    /// Creates the subscription with a new secret, or replaces the existing one for the feed
+    /// which has to be verified by the hub again.
+    pub async fn upsert(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        hub_url: &str,
+        topic_url: &str,
+    ) -> Result<WebSubSubscription> {
+        let secret = format!("{}{}", Base62Uuid::new(), Base62Uuid::new());
+        Ok(sqlx::query_as!(
+            WebSubSubscription,
+            "insert into websub_subscription (
+                feed_id, hub_url, topic_url, secret
+            ) values (
+                $1, $2, $3, $4
+            ) on conflict (feed_id) do update set
+                hub_url = excluded.hub_url,
+                topic_url = excluded.topic_url,
+                secret = excluded.secret,
+                lease_expires_at = null
+            returning *",
+            feed_id,
+            hub_url,
+            topic_url,
+            secret
+        )
+        .fetch_one(db)
+        .await?)
+    }
+
+    /// Marks the subscription as verified by the hub for the lease it granted.
+    pub async fn verify(
+        db: impl Executor<'_, Database = Postgres>,
+        feed_id: Uuid,
+        lease_seconds: i64,
+    ) -> Result<WebSubSubscription> {
+        sqlx::query_as!(
+            WebSubSubscription,
+            "update websub_subscription set
+                lease_expires_at = now() + make_interval(secs => $2)
+            where feed_id = $1
+            returning *",
+            feed_id,
+            lease_seconds as f64
+        )
+        .fetch_one(db)
+        .await
+        .map_err(|error| {
+            if let sqlx::error::Error::RowNotFound = error {
+                return Error::NotFoundUuid("websub_subscription", feed_id);
+            }
+            Error::Sqlx(error)
+        })
+    }
+
+    pub async fn delete(db: impl Executor<'_, Database = Postgres>, feed_id: Uuid) -> Result<()> {
+        sqlx::query!(
+            "delete from websub_subscription where feed_id = $1",
+            feed_id
+        )
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Whether the hub has verified the subscription and its lease has not expired yet.
+    pub fn is_active(&self) -> bool {
+        self.lease_expires_at
+            .is_some_and(|lease_expires_at| lease_expires_at > Utc::now())
+    }
+
+    /// Whether crawlnicle should (re)subscribe to the hub: because the feed now advertises a
+    /// different hub or topic, the lease is about to expire, or the hub never verified the
+    /// subscription.
+    pub fn needs_renewal(&self, hub_url: &str, topic_url: &str, renew_before: Duration) -> bool {
+        if self.hub_url != hub_url || self.topic_url != topic_url {
+            return true;
+        }
+        match self.lease_expires_at {
+            Some(lease_expires_at) => lease_expires_at - renew_before < Utc::now(),
+            None => self.updated_at.unwrap_or(self.created_at) + renew_before < Utc::now(),
+        }
+    }
+}
@@ -18,7 +18,7 @@ pub fn add_feed_form() -> Markup {
                     type="text"
                     id="url"
                     name="url"
-                    placeholder="https://example.com/feed.xml"
+                    placeholder="https://example.com"
                     required="true"
                     class="w-full mt-1 p-2 bg-gray-50 border border-gray-300 shadow-sm rounded-md focus:ring focus:ring-blue-500 focus:border-blue-500 focus:ring-opacity-50";
             }
@@ -0,0 +1,71 @@
+use maud::{html, Markup};
+
+use crate::models::crawl_log::CrawlLog;
+use crate::partials::time::relative_time;
+
+fn format_bytes(bytes: i64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else if bytes < 1024 * 1024 {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+pub fn crawl_log_table(crawl_logs: &[CrawlLog]) -> Markup {
+    html! {
+        @if crawl_logs.is_empty() {
+            p class="text-gray-600" { "No crawls yet." }
+        } @else {
+            table class="w-full text-sm text-left" {
+                thead class="text-gray-600" {
+                    tr {
+                        th class="pr-4" { "Time" }
+                        th class="pr-4" { "Status" }
+                        th class="pr-4" { "Duration" }
+                        th class="pr-4" { "Size" }
+                        th class="pr-4" { "Entries" }
+                        th { "Details" }
+                    }
+                }
+                tbody {
+                    @for crawl_log in crawl_logs {
+                        tr {
+                            td class="pr-4 whitespace-nowrap" { (relative_time(crawl_log.started_at)) }
+                            td class="pr-4 whitespace-nowrap" {
+                                @if crawl_log.pushed {
+                                    "pushed by hub"
+                                } @else if crawl_log.not_modified {
+                                    "304 not modified"
+                                } @else if let Some(status_code) = crawl_log.status_code {
+                                    (status_code)
+                                } @else {
+                                    "-"
+                                }
+                            }
+                            td class="pr-4 whitespace-nowrap" { (crawl_log.duration_ms) " ms" }
+                            td class="pr-4 whitespace-nowrap" {
+                                @if let Some(bytes) = crawl_log.bytes {
+                                    (format_bytes(bytes))
+                                } @else {
+                                    "-"
+                                }
+                            }
+                            td class="pr-4 whitespace-nowrap" {
+                                (crawl_log.new_entries) " new, " (crawl_log.updated_entries) " updated"
+                            }
+                            td class="text-red-600" title=[crawl_log.error.as_ref()] {
+                                @if let Some(error_message) = &crawl_log.error_message {
+                                    (error_message)
+                                } @else if let Some(moved_to) = &crawl_log.moved_to {
+                                    span class="text-gray-600" { "moved permanently to " (moved_to) }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -13,17 +13,23 @@ pub fn entry_list(entries: Vec<Entry>, options: &GetEntriesOptions, first_page:
     let limit = options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE);
     if len == limit {
         let last_entry = entries.last().unwrap();
+        let mut query = format!(
+            "/api/v1/entries?published_before={}&id_before={}&limit={}",
+            last_entry.published_at, last_entry.entry_id, limit
+        );
         if let Some(feed_id) = options.feed_id {
-            more_query = Some(format!(
-                "/api/v1/entries?feed_id={}&published_before={}&id_before={}&limit={}",
-                feed_id, last_entry.published_at, last_entry.entry_id, limit
-            ));
-        } else {
-            more_query = Some(format!(
-                "/api/v1/entries?published_before={}&id_before={}&limit={}",
-                last_entry.published_at, last_entry.entry_id, limit
-            ));
+            query.push_str(&format!("&feed_id={}", feed_id));
         }
+        if let Some(folder_id) = options.folder_id {
+            query.push_str(&format!("&folder_id={}", folder_id));
+        }
+        if options.unread {
+            query.push_str("&unread=true");
+        }
+        if options.starred {
+            query.push_str("&starred=true");
+        }
+        more_query = Some(query);
     }
 
     html! {
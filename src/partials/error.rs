@@ -0,0 +1,24 @@
+use maud::{html, Markup};
+
+use crate::error::ErrorDetails;
+
+/// The message of an error, swapped into the target of the htmx request that failed.
+pub fn error_message(details: &ErrorDetails) -> Markup {
+    html! {
+        div role="alert" class="p-2 rounded-md border border-red-200 bg-red-50 text-red-800" {
+            (details.detail)
+        }
+    }
+}
+
+pub fn error_page(details: &ErrorDetails) -> Markup {
+    html! {
+        header {
+            h2 class="mb-4 text-2xl font-medium" {
+                (details.status.as_u16()) " " (details.status.canonical_reason().unwrap_or("Error"))
+            }
+        }
+        (error_message(details))
+        p class="mt-4" { a href="/" { "Back to home" } }
+    }
+}
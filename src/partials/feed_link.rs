@@ -55,7 +55,11 @@ impl FeedLink<'_> {
                         (relative_time(last_crawl))
                     }
                 }
-                @if let Some(next_crawl) = self.feed.next_crawl_time() {
+                @if self.feed.inactive_at.is_some() {
+                    span class="text-sm text-red-600 font-semibold" { "gone" }
+                } @else if self.feed.paused_at.is_some() {
+                    span class="text-sm text-red-600 font-semibold" { "paused" }
+                } @else if let Some(next_crawl) = self.feed.next_crawl_time() {
                     span class="text-sm text-gray-600" {
                         span class="font-semibold" { "next crawl: " }
                         (relative_time(next_crawl))
@@ -9,15 +9,15 @@ pub fn feed_list(feeds: Vec<Feed>, options: &GetFeedsOptions, first_page: bool)
         return html! { p { "No feeds found." } };
     }
 
-    let mut more_query = None;
     let limit = options.limit.unwrap_or(DEFAULT_FEEDS_PAGE_SIZE);
-    if len == limit {
-        let last_feed = feeds.last().unwrap();
-        more_query = Some(format!(
-            "/api/v1/feeds?sort=CreatedAt&before={}&id_before={}&limit={}",
-            last_feed.created_at, last_feed.feed_id, limit
-        ));
-    }
+    let more_query = options.next_cursor(&feeds).map(|cursor| {
+        format!(
+            "/api/v1/feeds?sort={}&cursor={}&limit={}",
+            options.sort().as_str(),
+            cursor,
+            limit
+        )
+    });
 
     html! {
         @for (i, feed) in feeds.iter().enumerate() {
@@ -18,6 +18,8 @@ pub fn header(title: &str, user: Option<User>) -> Markup {
                     }
                     ul class="flex flex-row list-none gap-4" {
                         li { (link(LinkProps { destination: "/feeds", title: "feeds", ..Default::default() })) }
+                        li { (link(LinkProps { destination: "/starred", title: "starred", ..Default::default() })) }
+                        li { (link(LinkProps { destination: "/search", title: "search", ..Default::default() })) }
                         li { (link(LinkProps { destination: "/log", title: "log", ..Default::default() })) }
                     }
                 }
@@ -1,7 +1,9 @@
 pub mod add_feed_form;
 pub mod confirm_email_form;
+pub mod crawl_log_table;
 pub mod entry_link;
 pub mod entry_list;
+pub mod error;
 pub mod feed_link;
 pub mod feed_list;
 pub mod footer;
@@ -13,5 +15,6 @@ pub mod login_form;
 pub mod opml_import_form;
 pub mod register_form;
 pub mod reset_password_form;
+pub mod search_result_list;
 pub mod time;
 pub mod user_name;
@@ -0,0 +1,70 @@
+use maud::{html, Markup, PreEscaped};
+use url::form_urlencoded;
+
+use crate::models::entry::{EntrySearchResult, GetEntriesOptions, DEFAULT_ENTRIES_PAGE_SIZE};
+use crate::partials::entry_link::{entry_link, EntryLink};
+
+pub fn search_result_list(
+    results: Vec<EntrySearchResult>,
+    options: &GetEntriesOptions,
+    first_page: bool,
+) -> Markup {
+    let len = results.len() as i64;
+    if first_page && len == 0 {
+        return html! { p { "No matching entries found." } };
+    }
+
+    let mut more_query = None;
+    let limit = options.limit.unwrap_or(DEFAULT_ENTRIES_PAGE_SIZE);
+    if len == limit {
+        let q: String =
+            form_urlencoded::byte_serialize(options.q.as_deref().unwrap_or_default().as_bytes())
+                .collect();
+        let mut query = format!(
+            "/api/v1/entries?q={}&offset={}&limit={}",
+            q,
+            options.offset.unwrap_or(0) + len,
+            limit
+        );
+        if let Some(feed_id) = options.feed_id {
+            query.push_str(&format!("&feed_id={}", feed_id));
+        }
+        if let Some(folder_id) = options.folder_id {
+            query.push_str(&format!("&folder_id={}", folder_id));
+        }
+        if options.unread {
+            query.push_str("&unread=true");
+        }
+        if options.starred {
+            query.push_str("&starred=true");
+        }
+        more_query = Some(query);
+    }
+
+    html! {
+        @for (i, result) in results.iter().enumerate() {
+            @if i == results.len() - 1 {
+                @if let Some(ref more_query) = more_query {
+                    li hx-get=(more_query) hx-trigger="revealed" hx-target="this" hx-swap="afterend" {
+                        (EntryLink::new(&result.entry).reset_htmx_target().render())
+                        p class="text-sm text-gray-600" { (PreEscaped(&result.snippet)) }
+                        div class="list-loading" {
+                            img class="mt-4 max-h-4 invert" src="/static/img/three-dots.svg" alt="Loading...";
+                        }
+                    }
+                } @else {
+                    li { (search_result(result)) }
+                }
+            } @else {
+                li { (search_result(result)) }
+            }
+        }
+    }
+}
+
+fn search_result(result: &EntrySearchResult) -> Markup {
+    html! {
+        (entry_link(&result.entry))
+        p class="text-sm text-gray-600" { (PreEscaped(&result.snippet)) }
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use reqwest::Client;
+use tokio::sync::Mutex;
+use tokio::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::domain_locks::{DomainLocks, MAX_DOMAIN_LOCK_DURATION};
+use crate::USER_AGENT;
+
+/// How long the robots.txt of a site is cached before it is fetched again.
+pub const ROBOTS_TXT_CACHE_DURATION: Duration = Duration::from_secs(60 * 60 * 24);
+/// How long to wait before fetching a robots.txt again after it could not be fetched.
+pub const ROBOTS_TXT_ERROR_CACHE_DURATION: Duration = Duration::from_secs(60 * 60);
+/// Robots.txt files are only read up to this size, as recommended by RFC 9309.
+pub const MAX_ROBOTS_TXT_SIZE: usize = 500 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// The rules of a robots.txt (RFC 9309) that apply to crawlnicle.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct RobotsRules {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsRules {
+    pub fn allow_all() -> Self {
+        Self::default()
+    }
+
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// Parses the groups of the robots.txt that apply to the user agent, or to all user agents
+    /// ("*") if there is no group for the user agent.
+    pub fn parse(robots_txt: &str, user_agent: &str) -> Self {
+        #[derive(Default)]
+        struct Group {
+            user_agents: Vec<String>,
+            rules: Vec<Rule>,
+            crawl_delay: Option<Duration>,
+        }
+
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_user_agents = false;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let key = key.trim().to_ascii_lowercase();
+            let value = value.trim();
+            if key == "user-agent" {
+                if !in_user_agents {
+                    groups.push(Group::default());
+                    in_user_agents = true;
+                }
+                if let Some(group) = groups.last_mut() {
+                    group.user_agents.push(value.to_ascii_lowercase());
+                }
+                continue;
+            }
+            in_user_agents = false;
+            let Some(group) = groups.last_mut() else {
+                continue;
+            };
+            match key.as_str() {
+                "allow" | "disallow" if !value.is_empty() => group.rules.push(Rule {
+                    allow: key == "allow",
+                    pattern: value.to_string(),
+                }),
+                "crawl-delay" => {
+                    group.crawl_delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+                        // Delays too long for a `Duration` are capped like any other long delay
+                        .map(|seconds| {
+                            Duration::try_from_secs_f64(seconds)
+                                .unwrap_or(MAX_DOMAIN_LOCK_DURATION)
+                                .min(MAX_DOMAIN_LOCK_DURATION)
+                        })
+                }
+                _ => {}
+            }
+        }
+
+        let user_agent = user_agent.to_ascii_lowercase();
+        let matches = |name: &str| {
+            groups
+                .iter()
+                .filter(|group| group.user_agents.iter().any(|agent| agent == name))
+                .collect::<Vec<_>>()
+        };
+        let mut matching = matches(&user_agent);
+        if matching.is_empty() {
+            matching = matches("*");
+        }
+        Self {
+            rules: matching
+                .iter()
+                .flat_map(|group| group.rules.iter().cloned())
+                .collect(),
+            crawl_delay: matching.iter().find_map(|group| group.crawl_delay),
+        }
+    }
+
+    /// Whether the path (including the query string) may be crawled. The rule with the longest
+    /// matching pattern applies, and allow rules win ties.
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| pattern_matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Matches a path against a robots.txt pattern, where `*` matches any characters and a trailing
+/// `$` anchors the pattern to the end of the path.
+fn pattern_matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    if parts.is_empty() {
+        return !anchored || rest.is_empty();
+    }
+    for (index, part) in parts.iter().enumerate() {
+        if anchored && index == parts.len() - 1 {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(start) => rest = &rest[start + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[derive(Debug)]
+struct CachedRules {
+    rules: Arc<RobotsRules>,
+    expires_at: Instant,
+}
+
+/// A cache of the robots.txt rules of each site crawlnicle requests, so that crawlers can check
+/// whether they are allowed to fetch a URL before fetching it.
+///
+/// Any `Crawl-delay` in a robots.txt is applied to the domain in `DomainLocks` when it is fetched.
+#[derive(Debug, Clone)]
+pub struct RobotsTxt {
+    cache: Arc<Mutex<HashMap<String, CachedRules>>>,
+    check_feeds: bool,
+}
+
+impl RobotsTxt {
+    /// Article pages are always checked against the robots.txt of their site, while feeds are
+    /// only checked if `check_feeds` is true.
+    pub fn new(check_feeds: bool) -> Self {
+        Self {
+            cache: Arc::new(Mutex::new(HashMap::new())),
+            check_feeds,
+        }
+    }
+
+    pub fn check_feeds(&self) -> bool {
+        self.check_feeds
+    }
+
+    /// Whether crawlnicle is allowed to fetch the URL by the robots.txt of its site.
+    pub async fn is_allowed(&self, client: &Client, domain_locks: &DomainLocks, url: &Url) -> bool {
+        let path = match url.query() {
+            Some(query) => format!("{}?{}", url.path(), query),
+            None => url.path().to_string(),
+        };
+        self.get_rules(client, domain_locks, url)
+            .await
+            .is_allowed(&path)
+    }
+
+    async fn get_rules(
+        &self,
+        client: &Client,
+        domain_locks: &DomainLocks,
+        url: &Url,
+    ) -> Arc<RobotsRules> {
+        let origin = url.origin().ascii_serialization();
+        if let Some(cached) = self.cache.lock().await.get(&origin) {
+            if cached.expires_at > Instant::now() {
+                return cached.rules.clone();
+            }
+        }
+
+        let (rules, cache_duration) = match fetch_rules(client, domain_locks, url).await {
+            Some(rules) => (rules, ROBOTS_TXT_CACHE_DURATION),
+            None => (RobotsRules::disallow_all(), ROBOTS_TXT_ERROR_CACHE_DURATION),
+        };
+        if let (Some(domain), Some(crawl_delay)) = (url.domain(), rules.crawl_delay()) {
+            domain_locks.set_min_delay(domain, crawl_delay).await;
+        }
+        let rules = Arc::new(rules);
+        self.cache.lock().await.insert(
+            origin,
+            CachedRules {
+                rules: rules.clone(),
+                expires_at: Instant::now() + cache_duration,
+            },
+        );
+        rules
+    }
+}
+
+/// Fetches the robots.txt of the URL's site. Returns `None` if the site could not be reached or
+/// returned a server error, in which case it must be assumed that everything is disallowed.
+async fn fetch_rules(
+    client: &Client,
+    domain_locks: &DomainLocks,
+    url: &Url,
+) -> Option<RobotsRules> {
+    let robots_url = url.join("/robots.txt").ok()?;
+    let domain = url.domain().unwrap_or_default();
+    info!(%robots_url, "fetching robots.txt");
+    let resp = domain_locks
+        .run_request(domain, async {
+            client.get(robots_url.clone()).send().await
+        })
+        .await;
+    let resp = match resp {
+        Ok(resp) => resp,
+        Err(err) => {
+            warn!(%robots_url, "failed to fetch robots.txt: {}", err);
+            return None;
+        }
+    };
+    let status = resp.status();
+    if status.is_success() {
+        let bytes = resp.bytes().await.ok()?;
+        let robots_txt = String::from_utf8_lossy(&bytes[..bytes.len().min(MAX_ROBOTS_TXT_SIZE)]);
+        let user_agent = USER_AGENT.split('/').next().unwrap_or(USER_AGENT);
+        Some(RobotsRules::parse(&robots_txt, user_agent))
+    } else if status.is_client_error() && status != reqwest::StatusCode::TOO_MANY_REQUESTS {
+        debug!(%robots_url, %status, "no robots.txt, allowing all");
+        Some(RobotsRules::allow_all())
+    } else {
+        warn!(%robots_url, %status, "robots.txt unavailable, disallowing all");
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS_TXT: &str = "
+        User-agent: *
+        Disallow: /
+
+        User-agent: crawlnicle
+        User-agent: otherbot
+        Disallow: /private/
+        Allow: /private/public.html
+        Disallow: /*.pdf$
+        Crawl-delay: 2.5 # seconds
+    ";
+
+    #[test]
+    fn parses_group_for_user_agent() {
+        let rules = RobotsRules::parse(ROBOTS_TXT, "crawlnicle");
+        assert!(rules.is_allowed("/"));
+        assert!(rules.is_allowed("/blog/post.html"));
+        assert!(!rules.is_allowed("/private/secret.html"));
+        assert!(rules.is_allowed("/private/public.html"));
+        assert!(!rules.is_allowed("/files/report.pdf"));
+        assert!(rules.is_allowed("/files/report.pdf?download=1"));
+        assert_eq!(rules.crawl_delay(), Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn caps_huge_crawl_delay() {
+        for crawl_delay in ["120", "1e19", "1e20"] {
+            let rules = RobotsRules::parse(
+                &format!("User-agent: *\nCrawl-delay: {}", crawl_delay),
+                "crawlnicle",
+            );
+            assert_eq!(rules.crawl_delay(), Some(MAX_DOMAIN_LOCK_DURATION));
+        }
+        let rules = RobotsRules::parse("User-agent: *\nCrawl-delay: -1", "crawlnicle");
+        assert_eq!(rules.crawl_delay(), None);
+    }
+
+    #[test]
+    fn falls_back_to_wildcard_group() {
+        let rules = RobotsRules::parse(ROBOTS_TXT, "somebot");
+        assert!(!rules.is_allowed("/blog/post.html"));
+        assert_eq!(rules.crawl_delay(), None);
+    }
+
+    #[test]
+    fn allows_all_without_matching_group() {
+        let rules = RobotsRules::parse("User-agent: otherbot\nDisallow: /", "crawlnicle");
+        assert!(rules.is_allowed("/anything"));
+        let rules = RobotsRules::parse("User-agent: *\nDisallow:", "crawlnicle");
+        assert!(rules.is_allowed("/anything"));
+    }
+}
@@ -0,0 +1,144 @@
+use chrono::Duration;
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use sha1::Sha1;
+use sha2::{Sha256, Sha384, Sha512};
+use sqlx::PgPool;
+use tracing::info;
+use url::Url;
+use uuid::Uuid;
+
+use crate::error::{Error, Result};
+use crate::models::websub_subscription::WebSubSubscription;
+use crate::uuid::Base62Uuid;
+
+/// The lease crawlnicle asks hubs for, though hubs are free to grant a different one.
+pub const WEBSUB_LEASE_SECONDS: i64 = 60 * 60 * 24 * 10;
+
+/// The longest lease accepted from hubs. Leases are only a hint of when to renew the subscription,
+/// so hubs granting a longer one are still asked to renew it after this long.
+pub const MAX_WEBSUB_LEASE_SECONDS: i64 = 60 * 60 * 24 * 28;
+
+/// Subscriptions are renewed on the first crawl within this long of their lease expiring. It
+/// is also how long to wait for a hub to verify a subscription before asking it again.
+pub const WEBSUB_RENEW_BEFORE: Duration = Duration::days(1);
+
+/// Feeds with an active WebSub subscription are still crawled in case the hub misses an update,
+/// but no more often than this.
+pub const WEBSUB_CRAWL_INTERVAL_MINUTES: i32 = 60 * 12;
+
+/// The URL hubs verify subscriptions with and push the content of the feed to.
+pub fn callback_url(public_url: &Url, feed_id: Uuid) -> Result<Url> {
+    Ok(public_url
+        .join(&format!("websub/{}", Base62Uuid::from(feed_id)))
+        .map_err(anyhow::Error::from)?)
+}
+
+/// Asks the hub to subscribe crawlnicle to the topic (the feed's self URL).
+///
+/// The hub responds asynchronously by verifying the intent of the subscription at the callback
+/// URL, which is what activates the `WebSubSubscription`.
+pub async fn subscribe(
+    pool: &PgPool,
+    client: &Client,
+    public_url: &Url,
+    feed_id: Uuid,
+    hub_url: &Url,
+    topic_url: &Url,
+) -> Result<WebSubSubscription> {
+    let subscription =
+        WebSubSubscription::upsert(pool, feed_id, hub_url.as_str(), topic_url.as_str()).await?;
+    let callback_url = callback_url(public_url, feed_id)?;
+    info!(%hub_url, %topic_url, %callback_url, "subscribing to websub hub");
+    client
+        .post(hub_url.clone())
+        .form(&[
+            ("hub.mode", "subscribe"),
+            ("hub.topic", topic_url.as_str()),
+            ("hub.callback", callback_url.as_str()),
+            ("hub.secret", &subscription.secret),
+            ("hub.lease_seconds", &WEBSUB_LEASE_SECONDS.to_string()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(subscription)
+}
+
+/// Asks the hub to unsubscribe crawlnicle from the topic, e.g. because the feed was deleted.
+///
+/// The subscription is deleted first, so that the callback URL confirms the intent to unsubscribe
+/// when the hub verifies it. Feeds without a subscription are left alone.
+pub async fn unsubscribe(
+    pool: &PgPool,
+    client: &Client,
+    public_url: &Url,
+    feed_id: Uuid,
+) -> Result<()> {
+    let subscription = match WebSubSubscription::get(pool, feed_id).await {
+        Ok(subscription) => subscription,
+        Err(Error::NotFoundUuid(..)) => return Ok(()),
+        Err(err) => return Err(err),
+    };
+    WebSubSubscription::delete(pool, feed_id).await?;
+    let callback_url = callback_url(public_url, feed_id)?;
+    info!(hub_url = %subscription.hub_url, topic_url = %subscription.topic_url, %callback_url, "unsubscribing from websub hub");
+    client
+        .post(&subscription.hub_url)
+        .form(&[
+            ("hub.mode", "unsubscribe"),
+            ("hub.topic", subscription.topic_url.as_str()),
+            ("hub.callback", callback_url.as_str()),
+        ])
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+/// Checks the `X-Hub-Signature` header ("<method>=<hex signature>") of content pushed by a hub
+/// against the HMAC of the body with the subscription's secret.
+pub fn verify_signature(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some((method, signature)) = signature.split_once('=') else {
+        return false;
+    };
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    fn verify<M: Mac + hmac::digest::KeyInit>(secret: &str, signature: &[u8], body: &[u8]) -> bool {
+        let Ok(mut mac) = <M as hmac::digest::KeyInit>::new_from_slice(secret.as_bytes()) else {
+            return false;
+        };
+        mac.update(body);
+        mac.verify_slice(signature).is_ok()
+    }
+    match method {
+        "sha1" => verify::<Hmac<Sha1>>(secret, &signature, body),
+        "sha256" => verify::<Hmac<Sha256>>(secret, &signature, body),
+        "sha384" => verify::<Hmac<Sha384>>(secret, &signature, body),
+        "sha512" => verify::<Hmac<Sha512>>(secret, &signature, body),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verifies_signature() {
+        let body = b"<feed></feed>";
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(body);
+        let signature = format!("sha256={}", hex::encode(mac.finalize().into_bytes()));
+        assert!(verify_signature("secret", &signature, body));
+        assert!(!verify_signature("other secret", &signature, body));
+        assert!(!verify_signature(
+            "secret",
+            &signature,
+            b"<feed>changed</feed>"
+        ));
+        assert!(!verify_signature("secret", "md5=abcd", body));
+        assert!(!verify_signature("secret", "sha256", body));
+    }
+}